@@ -1,10 +1,17 @@
-#[cfg(target_os = "windows")]
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 use core::ptr;
 
 #[cfg(target_os = "windows")]
 use crate::windows::{
-    CloseHandle, CreateFileA, CreateFileMappingA, FILE_MAP_READ, GENERIC_READ, GetFileSizeEx,
-    HANDLE, INVALID_HANDLE_VALUE, MapViewOfFile, OPEN_EXISTING, PAGE_READONLY, UnmapViewOfFile,
+    CREATE_ALWAYS, CloseHandle, CreateFileA, CreateFileMappingA, FILE_MAP_READ, GENERIC_READ,
+    GENERIC_WRITE, GetFileSizeEx, HANDLE, INVALID_HANDLE_VALUE, MapViewOfFile, OPEN_EXISTING,
+    PAGE_READONLY, UnmapViewOfFile, WriteFile,
+};
+
+#[cfg(target_os = "linux")]
+use crate::linux::{
+    AT_FDCWD, EINTR, MAP_PRIVATE, O_CLOEXEC, O_CREAT, O_RDONLY, O_TRUNC, O_WRONLY, PROT_READ,
+    Stat, close, fstat, mmap, munmap, openat, write,
 };
 
 pub struct MappedFile {
@@ -12,9 +19,9 @@ pub struct MappedFile {
     file_handle: HANDLE,
     #[cfg(target_os = "windows")]
     mapping_handle: HANDLE,
-    #[cfg(target_os = "windows")]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     ptr: *const u8,
-    #[cfg(target_os = "windows")]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     len: usize,
 }
 
@@ -91,12 +98,58 @@ impl MappedFile {
         }
     }
 
-    #[cfg(not(target_os = "windows"))]
+    #[cfg(target_os = "linux")]
+    pub fn open(path: &str) -> Option<Self> {
+        let mut path_buf = [0u8; 4096];
+        if path.len() >= path_buf.len() || path.as_bytes().contains(&0) {
+            return None;
+        }
+        path_buf[..path.len()].copy_from_slice(path.as_bytes());
+        path_buf[path.len()] = 0;
+
+        unsafe {
+            let fd = openat(AT_FDCWD, path_buf.as_ptr(), O_RDONLY | O_CLOEXEC, 0);
+            if fd < 0 {
+                return None;
+            }
+            let fd = fd as i32;
+
+            let mut stat = Stat::zeroed();
+            if fstat(fd, &mut stat) < 0 || stat.size() < 0 {
+                close(fd);
+                return None;
+            }
+
+            let len = stat.size() as usize;
+            if len == 0 {
+                close(fd);
+                return Some(MappedFile {
+                    ptr: ptr::null(),
+                    len: 0,
+                });
+            }
+
+            let addr = mmap(ptr::null_mut(), len, PROT_READ, MAP_PRIVATE, fd, 0);
+            // The mapping keeps its own reference to the file.
+            close(fd);
+
+            if addr < 0 {
+                return None;
+            }
+
+            Some(MappedFile {
+                ptr: addr as *const u8,
+                len,
+            })
+        }
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
     pub fn open(_path: &str) -> Option<Self> {
         None
     }
 
-    #[cfg(target_os = "windows")]
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
     pub fn as_bytes(&self) -> &[u8] {
         if self.ptr.is_null() {
             &[]
@@ -105,7 +158,7 @@ impl MappedFile {
         }
     }
 
-    #[cfg(not(target_os = "windows"))]
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
     pub fn as_bytes(&self) -> &[u8] {
         &[]
     }
@@ -115,9 +168,9 @@ impl MappedFile {
     }
 
     pub fn len(&self) -> usize {
-        #[cfg(target_os = "windows")]
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
         return self.len;
-        #[cfg(not(target_os = "windows"))]
+        #[cfg(not(any(target_os = "windows", target_os = "linux")))]
         return 0;
     }
 
@@ -142,3 +195,95 @@ impl Drop for MappedFile {
         }
     }
 }
+
+#[cfg(target_os = "linux")]
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe { munmap(self.ptr, self.len) };
+        }
+    }
+}
+
+/// Creates or truncates the file at `path` and writes `contents` to it.
+#[cfg(target_os = "windows")]
+pub fn write_file(path: &str, contents: &[u8]) -> Option<()> {
+    let mut path_buf = [0u8; 260];
+    if path.len() >= path_buf.len() {
+        return None;
+    }
+    path_buf[..path.len()].copy_from_slice(path.as_bytes());
+    path_buf[path.len()] = 0;
+
+    unsafe {
+        let handle = CreateFileA(
+            path_buf.as_ptr(),
+            GENERIC_WRITE,
+            0,
+            ptr::null_mut(),
+            CREATE_ALWAYS,
+            0,
+            ptr::null_mut(),
+        );
+        if handle == INVALID_HANDLE_VALUE {
+            return None;
+        }
+
+        let mut rest = contents;
+        while !rest.is_empty() {
+            let mut written = 0;
+            let chunk = rest.len().min(u32::MAX as usize);
+            if WriteFile(handle, rest.as_ptr(), chunk as u32, &mut written, ptr::null_mut()) == 0 || written == 0 {
+                CloseHandle(handle);
+                return None;
+            }
+            rest = &rest[written as usize..];
+        }
+        CloseHandle(handle);
+        Some(())
+    }
+}
+
+/// Creates or truncates the file at `path` and writes `contents` to it.
+#[cfg(target_os = "linux")]
+pub fn write_file(path: &str, contents: &[u8]) -> Option<()> {
+    let mut path_buf = [0u8; 4096];
+    if path.len() >= path_buf.len() || path.as_bytes().contains(&0) {
+        return None;
+    }
+    path_buf[..path.len()].copy_from_slice(path.as_bytes());
+    path_buf[path.len()] = 0;
+
+    let fd = unsafe {
+        openat(
+            AT_FDCWD,
+            path_buf.as_ptr(),
+            O_WRONLY | O_CREAT | O_TRUNC | O_CLOEXEC,
+            0o644,
+        )
+    };
+    if fd < 0 {
+        return None;
+    }
+    let fd = fd as i32;
+
+    let mut rest = contents;
+    while !rest.is_empty() {
+        let written = write(fd, rest);
+        if written == -EINTR {
+            continue;
+        }
+        if written <= 0 {
+            close(fd);
+            return None;
+        }
+        rest = &rest[written as usize..];
+    }
+    close(fd);
+    Some(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn write_file(_path: &str, _contents: &[u8]) -> Option<()> {
+    None
+}
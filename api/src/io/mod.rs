@@ -1,3 +1,4 @@
 pub mod fs;
 pub mod stdin;
 pub mod stdout;
+pub mod time;
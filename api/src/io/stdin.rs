@@ -1,6 +1,9 @@
 #[cfg(target_os = "windows")]
 use crate::windows::{CommandLineToArgvW, GetCommandLineW, LocalFree};
 
+#[cfg(target_os = "linux")]
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
 #[cfg(target_os = "windows")]
 pub struct Args {
     argv: *mut *mut u16,
@@ -119,20 +122,155 @@ impl Arg {
     }
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "linux")]
+static ARGC: AtomicUsize = AtomicUsize::new(0);
+#[cfg(target_os = "linux")]
+static ARGV: AtomicPtr<*const u8> = AtomicPtr::new(core::ptr::null_mut());
+
+#[cfg(target_os = "linux")]
+pub struct Args {
+    argv: *const *const u8,
+    argc: usize,
+    current: usize,
+}
+
+#[cfg(target_os = "linux")]
+impl Args {
+    /// Records `argc`/`argv` from the initial process stack, which holds
+    /// `argc` followed by the `argv` pointers.
+    ///
+    /// # Safety
+    /// `stack` must be the stack pointer the kernel handed to `_start`.
+    pub unsafe fn init(stack: *const usize) {
+        unsafe {
+            ARGC.store(*stack, Ordering::Relaxed);
+            ARGV.store(stack.add(1) as *mut *const u8, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get() -> Option<Self> {
+        let argv = ARGV.load(Ordering::Relaxed);
+        if argv.is_null() {
+            return None;
+        }
+
+        Some(Args {
+            argv,
+            argc: ARGC.load(Ordering::Relaxed),
+            current: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.argc
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.argc == 0
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Iterator for Args {
+    type Item = Arg;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current >= self.argc {
+            return None;
+        }
+
+        let ptr = unsafe { *self.argv.add(self.current) };
+        self.current += 1;
+
+        Some(Arg { ptr })
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub struct Arg {
+    ptr: *const u8,
+}
+
+#[cfg(target_os = "linux")]
+impl Arg {
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        unsafe {
+            while *self.ptr.add(len) != 0 {
+                len += 1;
+            }
+        }
+        len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr, self.len()) }
+    }
+
+    pub fn to_utf8<'a>(&self, buf: &'a mut [u8]) -> Option<&'a str> {
+        let bytes = self.as_bytes();
+        if bytes.len() > buf.len() {
+            return None;
+        }
+        buf[..bytes.len()].copy_from_slice(bytes);
+        core::str::from_utf8(&buf[..bytes.len()]).ok()
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 pub struct Args;
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 impl Args {
     pub fn get() -> Option<Self> {
         None
     }
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 impl Iterator for Args {
     type Item = ();
     fn next(&mut self) -> Option<Self::Item> {
         None
     }
 }
+
+/// Reads from standard input into `buf`, blocking until something is
+/// available. Returns how many bytes were read, 0 at the end of input or on
+/// an error.
+#[cfg(target_os = "linux")]
+pub fn read(buf: &mut [u8]) -> usize {
+    loop {
+        let read = crate::linux::read(crate::linux::STDIN_FILENO, buf);
+        if read == -crate::linux::EINTR {
+            continue;
+        }
+        return read.max(0) as usize;
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn read(buf: &mut [u8]) -> usize {
+    use crate::windows::{DWORD, GetStdHandle, ReadFile, STD_INPUT_HANDLE};
+
+    let mut read: DWORD = 0;
+    let ok = unsafe {
+        ReadFile(
+            GetStdHandle(STD_INPUT_HANDLE),
+            buf.as_mut_ptr(),
+            buf.len().min(DWORD::MAX as usize) as DWORD,
+            &mut read,
+            core::ptr::null_mut(),
+        )
+    };
+    if ok == 0 { 0 } else { read as usize }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn read(_buf: &mut [u8]) -> usize {
+    0
+}
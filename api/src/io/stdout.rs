@@ -4,16 +4,22 @@ struct Stdout;
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        #[cfg(target_os = "windows")]
-        crate::windows::write_stdout(s.as_bytes());
+        write(s.as_bytes());
+        Ok(())
+    }
+}
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            // TODO: implement for other platforms via syscalls
-            let _ = s;
-        }
+/// Writes `bytes` as they are, whether or not they are UTF-8.
+pub fn write(bytes: &[u8]) {
+    #[cfg(target_os = "windows")]
+    crate::windows::write_stdout(bytes);
 
-        Ok(())
+    #[cfg(target_os = "linux")]
+    crate::linux::write_stdout(bytes);
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        let _ = bytes;
     }
 }
 
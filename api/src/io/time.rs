@@ -0,0 +1,29 @@
+/// Microseconds on a monotonic clock since an unspecified starting point,
+/// for measuring how long something takes. Always 0 where there's no clock.
+#[cfg(target_os = "linux")]
+pub fn now_micros() -> u64 {
+    let mut time = [0i64; 2];
+    if crate::linux::clock_gettime(crate::linux::CLOCK_MONOTONIC, &mut time) < 0 {
+        return 0;
+    }
+    time[0] as u64 * 1_000_000 + time[1] as u64 / 1_000
+}
+
+#[cfg(target_os = "windows")]
+pub fn now_micros() -> u64 {
+    let (mut count, mut frequency) = (0i64, 0i64);
+    unsafe {
+        if crate::windows::QueryPerformanceCounter(&mut count) == 0
+            || crate::windows::QueryPerformanceFrequency(&mut frequency) == 0
+            || frequency == 0
+        {
+            return 0;
+        }
+    }
+    (count as u128 * 1_000_000 / frequency as u128) as u64
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn now_micros() -> u64 {
+    0
+}
@@ -5,13 +5,23 @@ pub mod io;
 use core::panic::PanicInfo;
 
 pub fn abort(_info: &PanicInfo) -> ! {
+    exit(1)
+}
+
+pub fn exit(code: i32) -> ! {
     #[cfg(target_os = "windows")]
     unsafe {
-        windows::ExitProcess(1);
+        windows::ExitProcess(code as u32);
     }
 
-    #[cfg(not(target_os = "windows"))]
-    loop {}
+    #[cfg(target_os = "linux")]
+    linux::exit_group(code);
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        let _ = code;
+        loop {}
+    }
 }
 
 #[cfg(target_os = "windows")]
@@ -22,9 +32,12 @@ pub(crate) mod windows {
     pub type DWORD = u32;
     pub type BOOL = i32;
 
+    pub const STD_INPUT_HANDLE: DWORD = -10i32 as DWORD;
     pub const STD_OUTPUT_HANDLE: DWORD = -11i32 as DWORD;
     pub const INVALID_HANDLE_VALUE: HANDLE = -1isize as HANDLE;
     pub const GENERIC_READ: DWORD = 0x80000000;
+    pub const GENERIC_WRITE: DWORD = 0x40000000;
+    pub const CREATE_ALWAYS: DWORD = 2;
     pub const OPEN_EXISTING: DWORD = 3;
     pub const PAGE_READONLY: DWORD = 0x02;
     pub const FILE_MAP_READ: DWORD = 0x04;
@@ -55,6 +68,13 @@ pub(crate) mod windows {
             lpNumberOfBytesRead: *mut DWORD,
             lpOverlapped: *mut c_void,
         ) -> BOOL;
+        pub fn WriteFile(
+            hFile: HANDLE,
+            lpBuffer: *const u8,
+            nNumberOfBytesToWrite: DWORD,
+            lpNumberOfBytesWritten: *mut DWORD,
+            lpOverlapped: *mut c_void,
+        ) -> BOOL;
         pub fn CloseHandle(hObject: HANDLE) -> BOOL;
         pub fn GetFileSizeEx(hFile: HANDLE, lpFileSize: *mut i64) -> BOOL;
         pub fn CreateFileMappingA(
@@ -74,6 +94,8 @@ pub(crate) mod windows {
         ) -> *mut c_void;
         pub fn UnmapViewOfFile(lpBaseAddress: *const c_void) -> BOOL;
         pub fn GetCommandLineW() -> *const u16;
+        pub fn QueryPerformanceCounter(lpPerformanceCount: *mut i64) -> BOOL;
+        pub fn QueryPerformanceFrequency(lpFrequency: *mut i64) -> BOOL;
         pub fn CommandLineToArgvW(lpCmdLine: *const u16, pNumArgs: *mut i32) -> *mut *mut u16;
         pub fn LocalFree(hMem: *mut c_void) -> *mut c_void;
     }
@@ -93,6 +115,158 @@ pub(crate) mod windows {
     }
 }
 
+#[cfg(target_os = "linux")]
+pub(crate) mod linux {
+    use common::linux::syscall6;
+    pub use common::linux::{MAP_PRIVATE, PROT_READ, mmap, munmap};
+
+    #[cfg(target_arch = "x86_64")]
+    mod nr {
+        pub const READ: usize = 0;
+        pub const WRITE: usize = 1;
+        pub const CLOSE: usize = 3;
+        pub const FSTAT: usize = 5;
+        pub const CLOCK_GETTIME: usize = 228;
+        pub const EXIT_GROUP: usize = 231;
+        pub const OPENAT: usize = 257;
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    mod nr {
+        pub const OPENAT: usize = 56;
+        pub const CLOSE: usize = 57;
+        pub const READ: usize = 63;
+        pub const WRITE: usize = 64;
+        pub const FSTAT: usize = 80;
+        pub const EXIT_GROUP: usize = 94;
+        pub const CLOCK_GETTIME: usize = 113;
+    }
+
+    pub const STDIN_FILENO: i32 = 0;
+    pub const STDOUT_FILENO: i32 = 1;
+    pub const AT_FDCWD: i32 = -100;
+    pub const O_RDONLY: usize = 0;
+    pub const O_WRONLY: usize = 0o1;
+    pub const O_CREAT: usize = 0o100;
+    pub const O_TRUNC: usize = 0o1000;
+    pub const O_CLOEXEC: usize = 0o2000000;
+    pub const EINTR: isize = 4;
+    pub const CLOCK_MONOTONIC: usize = 1;
+
+    /// `struct stat` is 144 bytes on x86_64 and 128 on aarch64, with
+    /// `st_size` at the same offset on both.
+    #[repr(C, align(8))]
+    pub struct Stat([u8; 144]);
+
+    impl Stat {
+        pub const fn zeroed() -> Self {
+            Self([0; 144])
+        }
+
+        pub fn size(&self) -> i64 {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&self.0[48..56]);
+            i64::from_ne_bytes(bytes)
+        }
+    }
+
+    pub fn read(fd: i32, buf: &mut [u8]) -> isize {
+        unsafe { syscall6(nr::READ, fd as usize, buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0) }
+    }
+
+    pub fn write(fd: i32, buf: &[u8]) -> isize {
+        unsafe { syscall6(nr::WRITE, fd as usize, buf.as_ptr() as usize, buf.len(), 0, 0, 0) }
+    }
+
+    /// `path` must be NUL-terminated. `mode` is only used when `flags`
+    /// includes `O_CREAT`.
+    pub unsafe fn openat(dirfd: i32, path: *const u8, flags: usize, mode: usize) -> isize {
+        unsafe { syscall6(nr::OPENAT, dirfd as usize, path as usize, flags, mode, 0, 0) }
+    }
+
+    pub fn close(fd: i32) -> isize {
+        unsafe { syscall6(nr::CLOSE, fd as usize, 0, 0, 0, 0, 0) }
+    }
+
+    pub fn fstat(fd: i32, stat: &mut Stat) -> isize {
+        unsafe { syscall6(nr::FSTAT, fd as usize, stat as *mut Stat as usize, 0, 0, 0, 0) }
+    }
+
+    /// `[seconds, nanoseconds]`, the layout of `struct timespec`.
+    pub fn clock_gettime(clock: usize, time: &mut [i64; 2]) -> isize {
+        unsafe { syscall6(nr::CLOCK_GETTIME, clock, time as *mut [i64; 2] as usize, 0, 0, 0, 0) }
+    }
+
+    pub fn exit_group(code: i32) -> ! {
+        unsafe {
+            syscall6(nr::EXIT_GROUP, code as usize, 0, 0, 0, 0, 0);
+        }
+        loop {
+            core::hint::spin_loop();
+        }
+    }
+
+    pub fn write_stdout(mut s: &[u8]) {
+        while !s.is_empty() {
+            let written = write(STDOUT_FILENO, s);
+            if written == -EINTR {
+                continue;
+            }
+            if written <= 0 {
+                return;
+            }
+            s = &s[written as usize..];
+        }
+    }
+}
+
+/// Defines the `_start` entry point of a binary, which calls `$main`, a
+/// `fn() -> i32` whose result is the exit code.
+#[macro_export]
+macro_rules! entry {
+    ($main:path) => {
+        #[cfg(target_os = "windows")]
+        #[unsafe(no_mangle)]
+        pub extern "C" fn _start() -> i32 {
+            $main()
+        }
+
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        #[unsafe(naked)]
+        #[unsafe(no_mangle)]
+        unsafe extern "C" fn _start() -> ! {
+            core::arch::naked_asm!(
+                "xor rbp, rbp",
+                "mov rdi, rsp",
+                "and rsp, -16",
+                "call {entry}",
+                "ud2",
+                entry = sym linux_entry,
+            )
+        }
+
+        #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+        #[unsafe(naked)]
+        #[unsafe(no_mangle)]
+        unsafe extern "C" fn _start() -> ! {
+            core::arch::naked_asm!(
+                "mov x29, xzr",
+                "mov x30, xzr",
+                "mov x0, sp",
+                "bl {entry}",
+                "brk #0",
+                entry = sym linux_entry,
+            )
+        }
+
+        #[cfg(target_os = "linux")]
+        unsafe extern "C" fn linux_entry(stack: *const usize) -> ! {
+            unsafe { $crate::io::stdin::Args::init(stack) };
+            $crate::exit($main())
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => ({
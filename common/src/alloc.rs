@@ -129,15 +129,239 @@ mod windows {
 #[cfg(target_os = "windows")]
 use windows::{alloc_impl, dealloc_impl, realloc_impl};
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(target_os = "linux")]
+mod linux {
+    use core::alloc::Layout;
+    use core::cell::UnsafeCell;
+    use core::ptr;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::linux::{
+        MAP_ANONYMOUS, MAP_PRIVATE, MREMAP_MAYMOVE, PROT_READ, PROT_WRITE, mmap, mremap, munmap,
+    };
+
+    const PAGE_SIZE: usize = 4096;
+
+    /// Blocks of `MIN_CLASS << i` bytes for every class `i`. Chunks are
+    /// page-aligned and carved from their start, so every block is aligned
+    /// to its own size.
+    const MIN_CLASS: usize = 16;
+    const CLASS_COUNT: usize = 9;
+    const MAX_CLASS: usize = MIN_CLASS << (CLASS_COUNT - 1);
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    unsafe fn map_pages(len: usize) -> *mut u8 {
+        let flags = MAP_PRIVATE | MAP_ANONYMOUS;
+        let addr = unsafe { mmap(ptr::null_mut(), len, PROT_READ | PROT_WRITE, flags, -1, 0) };
+        if addr < 0 {
+            ptr::null_mut()
+        } else {
+            addr as *mut u8
+        }
+    }
+
+    unsafe fn unmap_pages(ptr: *mut u8, len: usize) {
+        unsafe { munmap(ptr, len) };
+    }
+
+    unsafe fn remap_pages(ptr: *mut u8, old_len: usize, new_len: usize, flags: usize) -> *mut u8 {
+        let addr = unsafe { mremap(ptr, old_len, new_len, flags) };
+        if addr < 0 {
+            ptr::null_mut()
+        } else {
+            addr as *mut u8
+        }
+    }
+
+    fn page_round(size: usize) -> Option<usize> {
+        size.checked_add(PAGE_SIZE - 1).map(|s| s & !(PAGE_SIZE - 1))
+    }
+
+    fn size_class(size: usize, align: usize) -> Option<usize> {
+        let block = size.max(align).max(MIN_CLASS).next_power_of_two();
+        if block > MAX_CLASS {
+            return None;
+        }
+        Some((block.trailing_zeros() - MIN_CLASS.trailing_zeros()) as usize)
+    }
+
+    fn class_size(class: usize) -> usize {
+        MIN_CLASS << class
+    }
+
+    struct FreeBlock {
+        next: *mut FreeBlock,
+    }
+
+    struct Heap {
+        locked: AtomicBool,
+        free_lists: UnsafeCell<[*mut FreeBlock; CLASS_COUNT]>,
+    }
+
+    unsafe impl Sync for Heap {}
+
+    static HEAP: Heap = Heap {
+        locked: AtomicBool::new(false),
+        free_lists: UnsafeCell::new([ptr::null_mut(); CLASS_COUNT]),
+    };
+
+    impl Heap {
+        fn with_lists<R>(&self, f: impl FnOnce(&mut [*mut FreeBlock; CLASS_COUNT]) -> R) -> R {
+            while self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                core::hint::spin_loop();
+            }
+            let result = f(unsafe { &mut *self.free_lists.get() });
+            self.locked.store(false, Ordering::Release);
+            result
+        }
+    }
+
+    unsafe fn alloc_small(class: usize) -> *mut u8 {
+        HEAP.with_lists(|lists| unsafe {
+            if lists[class].is_null() {
+                let chunk = map_pages(CHUNK_SIZE);
+                if chunk.is_null() {
+                    return ptr::null_mut();
+                }
+                let block_size = class_size(class);
+                let mut offset = CHUNK_SIZE;
+                while offset >= block_size {
+                    offset -= block_size;
+                    let block = chunk.add(offset) as *mut FreeBlock;
+                    (*block).next = lists[class];
+                    lists[class] = block;
+                }
+            }
+
+            let block = lists[class];
+            lists[class] = (*block).next;
+            block as *mut u8
+        })
+    }
+
+    unsafe fn dealloc_small(ptr: *mut u8, class: usize) {
+        HEAP.with_lists(|lists| unsafe {
+            let block = ptr as *mut FreeBlock;
+            (*block).next = lists[class];
+            lists[class] = block;
+        })
+    }
+
+    unsafe fn alloc_large(size: usize, align: usize) -> *mut u8 {
+        let Some(len) = page_round(size) else {
+            return ptr::null_mut();
+        };
+        if align <= PAGE_SIZE {
+            return unsafe { map_pages(len) };
+        }
+
+        // Over-map by the alignment and give back the slack on both sides.
+        let Some(total) = len.checked_add(align) else {
+            return ptr::null_mut();
+        };
+        unsafe {
+            let raw = map_pages(total);
+            if raw.is_null() {
+                return ptr::null_mut();
+            }
+            let start = raw as usize;
+            let aligned = (start + align - 1) & !(align - 1);
+            let head = aligned - start;
+            let tail = total - head - len;
+            if head > 0 {
+                unmap_pages(raw, head);
+            }
+            if tail > 0 {
+                unmap_pages((aligned + len) as *mut u8, tail);
+            }
+            aligned as *mut u8
+        }
+    }
+
+    pub unsafe fn alloc_impl(layout: Layout) -> *mut u8 {
+        match size_class(layout.size(), layout.align()) {
+            Some(class) => unsafe { alloc_small(class) },
+            None => unsafe { alloc_large(layout.size(), layout.align()) },
+        }
+    }
+
+    pub unsafe fn dealloc_impl(ptr: *mut u8, layout: Layout) {
+        if ptr.is_null() {
+            return;
+        }
+        match size_class(layout.size(), layout.align()) {
+            Some(class) => unsafe { dealloc_small(ptr, class) },
+            None => {
+                if let Some(len) = page_round(layout.size()) {
+                    unsafe { unmap_pages(ptr, len) };
+                }
+            }
+        }
+    }
+
+    pub unsafe fn realloc_impl(ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let align = layout.align();
+        let old_class = size_class(layout.size(), align);
+        let new_class = size_class(new_size, align);
+
+        match (old_class, new_class) {
+            (Some(old), Some(new)) if old == new => return ptr,
+            (None, None) => {
+                let (Some(old_len), Some(new_len)) = (page_round(layout.size()), page_round(new_size))
+                else {
+                    return ptr::null_mut();
+                };
+                if old_len == new_len {
+                    return ptr;
+                }
+                if new_len < old_len {
+                    unsafe { unmap_pages(ptr.add(new_len), old_len - new_len) };
+                    return ptr;
+                }
+                // Moving is only safe when the kernel's page alignment is enough.
+                let flags = if align <= PAGE_SIZE { MREMAP_MAYMOVE } else { 0 };
+                let remapped = unsafe { remap_pages(ptr, old_len, new_len, flags) };
+                if !remapped.is_null() {
+                    return remapped;
+                }
+            }
+            _ => {}
+        }
+
+        let new_layout = match Layout::from_size_align(new_size, align) {
+            Ok(l) => l,
+            Err(_) => return ptr::null_mut(),
+        };
+        let new_ptr = unsafe { alloc_impl(new_layout) };
+        if !new_ptr.is_null() {
+            let copy_size = if layout.size() < new_size {
+                layout.size()
+            } else {
+                new_size
+            };
+            unsafe { ptr::copy_nonoverlapping(ptr, new_ptr, copy_size) };
+            unsafe { dealloc_impl(ptr, layout) };
+        }
+        new_ptr
+    }
+}
+
+#[cfg(target_os = "linux")]
+use linux::{alloc_impl, dealloc_impl, realloc_impl};
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 unsafe fn alloc_impl(_layout: Layout) -> *mut u8 {
     core::ptr::null_mut()
 }
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 unsafe fn dealloc_impl(_ptr: *mut u8, _layout: Layout) {}
 
-#[cfg(not(target_os = "windows"))]
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
 unsafe fn realloc_impl(_ptr: *mut u8, _layout: Layout, _new_size: usize) -> *mut u8 {
     core::ptr::null_mut()
 }
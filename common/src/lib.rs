@@ -1,6 +1,8 @@
 #![no_std]
 
 pub mod alloc;
+#[cfg(target_os = "linux")]
+pub mod linux;
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn memcpy(dest: *mut u8, src: *const u8, n: usize) -> *mut u8 {
@@ -51,6 +53,13 @@ pub unsafe extern "C" fn memcmp(s1: *const u8, s2: *const u8, n: usize) -> i32 {
     }
 }
 
+/// Like `memcmp` but only tells whether the bytes differ, which LLVM calls
+/// for equality comparisons.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bcmp(s1: *const u8, s2: *const u8, n: usize) -> i32 {
+    unsafe { memcmp(s1, s2, n) }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn strlen(s: *const u8) -> usize {
     unsafe {
@@ -67,3 +76,14 @@ pub unsafe extern "C" fn strlen(s: *const u8) -> usize {
 pub extern "C" fn __CxxFrameHandler3() -> ! {
     loop {}
 }
+
+// The prebuilt core/alloc still reference the unwinder under panic=abort
+#[cfg(all(target_os = "linux", panic = "abort"))]
+#[unsafe(no_mangle)]
+pub extern "C" fn rust_eh_personality() {}
+
+#[cfg(all(target_os = "linux", panic = "abort"))]
+#[unsafe(no_mangle)]
+pub extern "C" fn _Unwind_Resume() -> ! {
+    loop {}
+}
@@ -0,0 +1,97 @@
+//! The raw Linux system call and the memory-mapping calls built on it, shared
+//! by the allocator here and by `api`, which adds the other calls it needs.
+
+#[cfg(target_arch = "x86_64")]
+mod nr {
+    pub const MMAP: usize = 9;
+    pub const MUNMAP: usize = 11;
+    pub const MREMAP: usize = 25;
+}
+
+#[cfg(target_arch = "aarch64")]
+mod nr {
+    pub const MUNMAP: usize = 215;
+    pub const MREMAP: usize = 216;
+    pub const MMAP: usize = 222;
+}
+
+pub const PROT_READ: usize = 0x1;
+pub const PROT_WRITE: usize = 0x2;
+pub const MAP_PRIVATE: usize = 0x02;
+pub const MAP_ANONYMOUS: usize = 0x20;
+pub const MREMAP_MAYMOVE: usize = 0x1;
+
+#[cfg(target_arch = "x86_64")]
+pub unsafe fn syscall6(
+    n: usize,
+    a1: usize,
+    a2: usize,
+    a3: usize,
+    a4: usize,
+    a5: usize,
+    a6: usize,
+) -> isize {
+    let ret: isize;
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            inlateout("rax") n as isize => ret,
+            in("rdi") a1,
+            in("rsi") a2,
+            in("rdx") a3,
+            in("r10") a4,
+            in("r8") a5,
+            in("r9") a6,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+#[cfg(target_arch = "aarch64")]
+pub unsafe fn syscall6(
+    n: usize,
+    a1: usize,
+    a2: usize,
+    a3: usize,
+    a4: usize,
+    a5: usize,
+    a6: usize,
+) -> isize {
+    let ret: isize;
+    unsafe {
+        core::arch::asm!(
+            "svc 0",
+            in("x8") n,
+            inlateout("x0") a1 as isize => ret,
+            in("x1") a2,
+            in("x2") a3,
+            in("x3") a4,
+            in("x4") a5,
+            in("x5") a6,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+pub unsafe fn mmap(
+    addr: *mut u8,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: i32,
+    offset: usize,
+) -> isize {
+    unsafe { syscall6(nr::MMAP, addr as usize, len, prot, flags, fd as usize, offset) }
+}
+
+pub unsafe fn munmap(addr: *const u8, len: usize) -> isize {
+    unsafe { syscall6(nr::MUNMAP, addr as usize, len, 0, 0, 0, 0) }
+}
+
+pub unsafe fn mremap(addr: *mut u8, old_len: usize, new_len: usize, flags: usize) -> isize {
+    unsafe { syscall6(nr::MREMAP, addr as usize, old_len, new_len, flags, 0, 0) }
+}
@@ -1,8 +1,14 @@
 fn main() {
-    if std::env::var("CARGO_CFG_TARGET_OS").unwrap() == "windows" {
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap();
+    if target_os == "windows" {
         println!("cargo:rustc-link-arg=/ENTRY:_start");
         println!("cargo:rustc-link-arg=/SUBSYSTEM:CONSOLE");
         println!("cargo:rustc-link-lib=kernel32");
         println!("cargo:rustc-link-lib=shell32");
+    } else if target_os == "linux" {
+        // `_start` comes from `api::entry!` and nothing links against libc.
+        println!("cargo:rustc-link-arg-bins=-nostartfiles");
+        println!("cargo:rustc-link-arg-bins=-nostdlib");
+        println!("cargo:rustc-link-arg-bins=-static");
     }
 }
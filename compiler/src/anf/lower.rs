@@ -0,0 +1,309 @@
+//! Normalizing the erased IR to A-normal form.
+//!
+//! Erasure has already dropped the irrelevant parameters of globals, so a
+//! global's function takes the parameters of its IR arity, and every call
+//! passes them all: a global applied to fewer arguments is eta-expanded
+//! into a closure first.
+
+use alloc::{
+    boxed::Box,
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+    vec,
+    vec::Vec,
+};
+use core::mem;
+
+use crate::{
+    anf::{Alt, Atom, Expr, Function, FunctionName, Program, Value, Var},
+    elaboration::Environment,
+    ir::{self, Global, lower::LowerError},
+    module::name::QualifiedName,
+};
+
+/// Erases `env` and lowers the result.
+pub fn lower_environment(env: &Environment) -> Result<Program, LowerError> {
+    Ok(lower_program(&ir::lower::lower_environment(env)?))
+}
+
+pub fn lower_program(program: &ir::Program) -> Program {
+    let mut functions = BTreeMap::new();
+    for (name, global) in &program.globals {
+        let mut lower = Lower {
+            program,
+            functions: &mut functions,
+            global: name.clone(),
+            lifted: 0,
+            scope: Scope::default(),
+        };
+        let function = lower.global(name, global);
+        functions.insert(FunctionName::Global(name.clone()), function);
+    }
+    Program { functions }
+}
+
+/// The function being lowered.
+#[derive(Default)]
+struct Scope {
+    /// What each variable of the IR stands for, innermost last.
+    locals: Vec<Atom>,
+    next_var: usize,
+    bindings: Vec<(Var, Value)>,
+}
+
+struct Lower<'a> {
+    program: &'a ir::Program,
+    functions: &'a mut BTreeMap<FunctionName, Function>,
+    /// The global being lowered, which names the functions lifted out of it.
+    global: QualifiedName,
+    lifted: usize,
+    scope: Scope,
+}
+
+impl Lower<'_> {
+    fn global(&mut self, name: &QualifiedName, global: &Global) -> Function {
+        let params = self.params(global.arity());
+        let atoms = |vars: &[Var]| -> Vec<Atom> { vars.iter().map(|var| Atom::Var(*var)).collect() };
+        match global {
+            Global::Def { body, .. } => {
+                self.scope.locals = atoms(&params);
+                let result = self.lower(lambda_body(body, params.len()));
+                self.function(params, result)
+            }
+            Global::Ctor { num_params, .. } => {
+                let result = self.bind(Value::Ctor(name.clone(), atoms(&params[*num_params..])));
+                self.function(params, result)
+            }
+            Global::Rec { num_prefix, rules, .. } => {
+                let mut alts = Vec::new();
+                for rule in rules {
+                    let fields = self.params(rule.num_fields);
+                    self.scope.locals = atoms(&params[..*num_prefix]);
+                    self.scope.locals.extend(atoms(&fields));
+                    let result = self.lower(lambda_body(&rule.rhs, num_prefix + rule.num_fields));
+                    alts.push(Alt {
+                        constructor: rule.constructor.clone(),
+                        fields,
+                        body: self.finish(result),
+                    });
+                }
+                let major = params.last().map_or(Atom::Erased, |var| Atom::Var(*var));
+                Function {
+                    captures: 0,
+                    params,
+                    body: Expr::Case(major, alts),
+                }
+            }
+            Global::Prim(intrinsic) => {
+                let result = self.bind(Value::Prim(intrinsic.clone(), atoms(&params)));
+                self.function(params, result)
+            }
+        }
+    }
+
+    fn params(&mut self, count: usize) -> Vec<Var> {
+        (0..count).map(|_| self.fresh()).collect()
+    }
+
+    fn lower(&mut self, expr: &ir::Expr) -> Atom {
+        match expr {
+            ir::Expr::Var(index) => self.scope.locals[self.scope.locals.len() - 1 - index].clone(),
+            ir::Expr::Global(_) | ir::Expr::App(..) => self.app(expr),
+            ir::Expr::Lam(_) => self.closure(expr),
+            ir::Expr::Let(value, body) => {
+                let value = self.lower(value);
+                self.scope.locals.push(value);
+                let result = self.lower(body);
+                self.scope.locals.pop();
+                result
+            }
+            ir::Expr::Nat(n) => Atom::Nat(*n),
+            ir::Expr::Str(s) => Atom::Str(s.clone()),
+            ir::Expr::Pair(fst, snd) => {
+                let fst = self.lower(fst);
+                let snd = self.lower(snd);
+                self.bind(Value::Pair(fst, snd))
+            }
+            ir::Expr::Fst(pair) => {
+                let pair = self.lower(pair);
+                self.bind(Value::Fst(pair))
+            }
+            ir::Expr::Snd(pair) => {
+                let pair = self.lower(pair);
+                self.bind(Value::Snd(pair))
+            }
+            ir::Expr::Erased => Atom::Erased,
+        }
+    }
+
+    fn app(&mut self, expr: &ir::Expr) -> Atom {
+        let mut head = expr;
+        let mut args = Vec::new();
+        while let ir::Expr::App(f, arg) = head {
+            args.push(&**arg);
+            head = f;
+        }
+        let mut args: Vec<Atom> = args.into_iter().rev().map(|arg| self.lower(arg)).collect();
+
+        let ir::Expr::Global(name) = head else {
+            let f = self.lower(head);
+            return self.bind(Value::Apply(f, args));
+        };
+        let callee = FunctionName::Global(name.clone());
+        let arity = self.program.globals[name].arity();
+        if args.len() < arity {
+            return self.eta_expand(callee, args, arity);
+        }
+        let rest = args.split_off(arity);
+        let result = self.bind(Value::Call(callee, args));
+        if rest.is_empty() {
+            return result;
+        }
+        self.bind(Value::Apply(result, rest))
+    }
+
+    /// A closure calling `callee` with `args` followed by parameters for the
+    /// rest of its `arity`.
+    fn eta_expand(&mut self, callee: FunctionName, args: Vec<Atom>, arity: usize) -> Atom {
+        let outer = mem::take(&mut self.scope);
+        let mut captured = Vec::new();
+        let mut params = Vec::new();
+        let mut call_args = Vec::new();
+        for arg in args {
+            call_args.push(match arg {
+                Atom::Var(var) => {
+                    let param = self.fresh();
+                    captured.push(Atom::Var(var));
+                    params.push(param);
+                    Atom::Var(param)
+                }
+                arg => arg,
+            });
+        }
+        let num_captures = params.len();
+        while call_args.len() < arity {
+            let param = self.fresh();
+            params.push(param);
+            call_args.push(Atom::Var(param));
+        }
+        let result = self.bind(Value::Call(callee, call_args));
+        let function = Function {
+            captures: num_captures,
+            params,
+            body: self.finish(result),
+        };
+        self.scope = outer;
+        let lifted = self.lift(function);
+        self.bind(Value::Closure(lifted, captured))
+    }
+
+    /// Lifts the lambdas `expr` starts with out, capturing the variables of
+    /// the function being lowered that they mention.
+    fn closure(&mut self, expr: &ir::Expr) -> Atom {
+        let mut free = BTreeSet::new();
+        free_vars(expr, 0, &mut free);
+        let outer = mem::take(&mut self.scope);
+        let mut captured = Vec::new();
+        let mut params = Vec::new();
+        self.scope.locals = vec![Atom::Erased; outer.locals.len()];
+        for index in free {
+            let position = outer.locals.len() - 1 - index;
+            self.scope.locals[position] = match &outer.locals[position] {
+                Atom::Var(var) => {
+                    let capture = self.fresh();
+                    captured.push(Atom::Var(*var));
+                    params.push(capture);
+                    Atom::Var(capture)
+                }
+                atom => atom.clone(),
+            };
+        }
+        let num_captures = params.len();
+        let mut body = expr;
+        while let ir::Expr::Lam(inner) = body {
+            let param = self.fresh();
+            params.push(param);
+            self.scope.locals.push(Atom::Var(param));
+            body = inner;
+        }
+        let result = self.lower(body);
+        let function = Function {
+            captures: num_captures,
+            params,
+            body: self.finish(result),
+        };
+        self.scope = outer;
+        let lifted = self.lift(function);
+        self.bind(Value::Closure(lifted, captured))
+    }
+
+    fn fresh(&mut self) -> Var {
+        let var = Var(self.scope.next_var);
+        self.scope.next_var += 1;
+        var
+    }
+
+    fn bind(&mut self, value: Value) -> Atom {
+        let var = self.fresh();
+        self.scope.bindings.push((var, value));
+        Atom::Var(var)
+    }
+
+    /// The bindings made so far, returning `result`.
+    fn finish(&mut self, result: Atom) -> Expr {
+        let mut expr = Expr::Return(result);
+        for (var, value) in mem::take(&mut self.scope.bindings).into_iter().rev() {
+            expr = Expr::Let(var, value, Box::new(expr));
+        }
+        expr
+    }
+
+    fn function(&mut self, params: Vec<Var>, result: Atom) -> Function {
+        Function {
+            captures: 0,
+            params,
+            body: self.finish(result),
+        }
+    }
+
+    fn lift(&mut self, function: Function) -> FunctionName {
+        let name = FunctionName::Lifted(self.global.clone(), self.lifted);
+        self.lifted += 1;
+        self.functions.insert(name.clone(), function);
+        name
+    }
+}
+
+/// The body under the `count` leading lambdas of `expr`.
+fn lambda_body(expr: &ir::Expr, count: usize) -> &ir::Expr {
+    let mut body = expr;
+    for _ in 0..count {
+        let ir::Expr::Lam(inner) = body else {
+            unreachable!("arities count the leading lambdas")
+        };
+        body = inner;
+    }
+    body
+}
+
+/// The variables `expr` mentions that are bound outside it, as indices
+/// from outside.
+fn free_vars(expr: &ir::Expr, depth: usize, free: &mut BTreeSet<usize>) {
+    match expr {
+        ir::Expr::Var(index) => {
+            if *index >= depth {
+                free.insert(index - depth);
+            }
+        }
+        ir::Expr::Lam(body) => free_vars(body, depth + 1, free),
+        ir::Expr::Let(value, body) => {
+            free_vars(value, depth, free);
+            free_vars(body, depth + 1, free);
+        }
+        ir::Expr::App(a, b) | ir::Expr::Pair(a, b) => {
+            free_vars(a, depth, free);
+            free_vars(b, depth, free);
+        }
+        ir::Expr::Fst(a) | ir::Expr::Snd(a) => free_vars(a, depth, free),
+        ir::Expr::Global(_) | ir::Expr::Nat(_) | ir::Expr::Str(_) | ir::Expr::Erased => {}
+    }
+}
@@ -0,0 +1,89 @@
+//! An untyped intermediate representation in A-normal form, the target of
+//! backends.
+//!
+//! It is lowered from the erased IR of `ir`, so types and the parameters
+//! and arguments that carry them are already gone, while instance arguments
+//! are dictionaries and stay. Every intermediate result is bound to a
+//! variable, every lambda is lifted to a top-level
+//! function taking its free variables first, and recursors become functions
+//! that `case` on their major premise, so a backend only needs calls,
+//! closures, constructors and primitives.
+//!
+//! Applying a closure passes it the arguments one at a time: once it has as
+//! many as its function takes, the function is called and the rest are
+//! passed to the result.
+
+pub mod lower;
+pub mod print;
+pub mod validate;
+
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, vec::Vec};
+
+use crate::module::name::{IntrinsicName, QualifiedName};
+
+/// A variable, numbered uniquely within its function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Var(pub usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FunctionName {
+    Global(QualifiedName),
+    /// The `n`th function lifted out of a global.
+    Lifted(QualifiedName, usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Atom {
+    Var(Var),
+    Nat(u64),
+    Str(Vec<u8>),
+    /// Something erased, which backends may represent by any value.
+    Erased,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Atom(Atom),
+    /// Calls a function with all its parameters, captured ones included.
+    Call(FunctionName, Vec<Atom>),
+    Apply(Atom, Vec<Atom>),
+    /// A closure of a function over values for its captured variables.
+    Closure(FunctionName, Vec<Atom>),
+    /// A constructor applied to its relevant fields.
+    Ctor(QualifiedName, Vec<Atom>),
+    /// A primitive applied to its relevant arguments.
+    Prim(IntrinsicName, Vec<Atom>),
+    Pair(Atom, Atom),
+    Fst(Atom),
+    Snd(Atom),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Let(Var, Value, Box<Expr>),
+    /// Continues with the alternative for the constructor the scrutinee was
+    /// built with, binding its fields.
+    Case(Atom, Vec<Alt>),
+    Return(Atom),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alt {
+    pub constructor: QualifiedName,
+    pub fields: Vec<Var>,
+    pub body: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// How many of the parameters are captured variables, which a `Closure`
+    /// of the function provides.
+    pub captures: usize,
+    pub params: Vec<Var>,
+    pub body: Expr,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub functions: BTreeMap<FunctionName, Function>,
+}
@@ -0,0 +1,114 @@
+//! The textual form of the IR, one function after another:
+//!
+//! ```text
+//! fn double#0[x0](x1) =
+//!   let x2 = call Nat.add(x0, x1)
+//!   ret x2
+//! ```
+//!
+//! The captured parameters of a function are in brackets.
+
+use core::fmt;
+
+use crate::anf::{Alt, Atom, Expr, Function, FunctionName, Program, Value, Var};
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, function) in &self.functions {
+            write_function(f, name, function)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_function(f: &mut fmt::Formatter<'_>, name: &FunctionName, function: &Function) -> fmt::Result {
+    let (captures, params) = function.params.split_at(function.captures);
+    write!(f, "fn {}", name)?;
+    if !captures.is_empty() {
+        write!(f, "[{}]", List(captures))?;
+    }
+    writeln!(f, "({}) =", List(params))?;
+    write_expr(f, &function.body, 1)
+}
+
+fn write_expr(f: &mut fmt::Formatter<'_>, expr: &Expr, depth: usize) -> fmt::Result {
+    let indent = depth * 2;
+    match expr {
+        Expr::Let(var, value, body) => {
+            writeln!(f, "{:indent$}let {} = {}", "", var, value)?;
+            write_expr(f, body, depth)
+        }
+        Expr::Case(scrutinee, alts) => {
+            writeln!(f, "{:indent$}case {} of", "", scrutinee)?;
+            for Alt {
+                constructor,
+                fields,
+                body,
+            } in alts
+            {
+                let name = constructor.display().unwrap_or("_");
+                writeln!(f, "{:indent$}  {}({}) =>", "", name, List(fields))?;
+                write_expr(f, body, depth + 2)?;
+            }
+            Ok(())
+        }
+        Expr::Return(atom) => writeln!(f, "{:indent$}ret {}", "", atom),
+    }
+}
+
+/// Comma separated items.
+struct List<'a, T>(&'a [T]);
+
+impl<T: fmt::Display> fmt::Display for List<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "x{}", self.0)
+    }
+}
+
+impl fmt::Display for FunctionName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FunctionName::Global(name) => write!(f, "{}", name.display().unwrap_or("_")),
+            FunctionName::Lifted(name, n) => write!(f, "{}#{}", name.display().unwrap_or("_"), n),
+        }
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Atom::Var(var) => write!(f, "{}", var),
+            Atom::Nat(n) => write!(f, "{}", n),
+            Atom::Str(s) => write!(f, "{:?}", alloc::string::String::from_utf8_lossy(s)),
+            Atom::Erased => write!(f, "_"),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Atom(atom) => write!(f, "{}", atom),
+            Value::Call(name, args) => write!(f, "call {}({})", name, List(args)),
+            Value::Apply(closure, args) => write!(f, "apply {}({})", closure, List(args)),
+            Value::Closure(name, captured) => write!(f, "closure {}[{}]", name, List(captured)),
+            Value::Ctor(name, fields) => write!(f, "ctor {}({})", name.display().unwrap_or("_"), List(fields)),
+            Value::Prim(intrinsic, args) => write!(f, "prim {}({})", intrinsic.name(), List(args)),
+            Value::Pair(fst, snd) => write!(f, "pair({}, {})", fst, snd),
+            Value::Fst(pair) => write!(f, "fst {}", pair),
+            Value::Snd(pair) => write!(f, "snd {}", pair),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Checks the invariants backends rely on: variables are bound once and
+//! before they are used, functions are called with all their parameters,
+//! closures are made of functions with parameters left over their captures,
+//! and a `case` has at most one alternative per constructor.
+
+use alloc::{collections::btree_set::BTreeSet, vec::Vec};
+
+use thiserror::Error;
+
+use crate::{
+    anf::{Atom, Expr, Function, FunctionName, Program, Value, Var},
+    module::name::QualifiedName,
+};
+
+#[derive(Debug, Error)]
+pub enum ValidationError {
+    #[error("`{function}` uses `{var}`, which isn't bound there")]
+    UnboundVar { function: FunctionName, var: Var },
+    #[error("`{function}` binds `{var}` more than once")]
+    Rebound { function: FunctionName, var: Var },
+    #[error("`{function}` refers to `{callee}`, which doesn't exist")]
+    UnknownFunction { function: FunctionName, callee: FunctionName },
+    #[error("`{function}` calls `{callee}` with {found} arguments, but it takes {expected}")]
+    CallArity {
+        function: FunctionName,
+        callee: FunctionName,
+        expected: usize,
+        found: usize,
+    },
+    #[error("`{function}` makes a closure of `{callee}` over {found} values, but it captures {expected}")]
+    ClosureCaptures {
+        function: FunctionName,
+        callee: FunctionName,
+        expected: usize,
+        found: usize,
+    },
+    #[error("`{function}` makes a closure of `{callee}`, which takes no parameters besides its captures")]
+    ClosureWithoutParams { function: FunctionName, callee: FunctionName },
+    #[error("`{function}` has more than one alternative for `{}`", constructor.display().unwrap_or("_"))]
+    DuplicateAlt {
+        function: FunctionName,
+        constructor: QualifiedName,
+    },
+}
+
+/// Every violation of the invariants in `program`.
+pub fn validate(program: &Program) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    for (name, function) in &program.functions {
+        let mut validator = Validator {
+            program,
+            function: name,
+            bound: BTreeSet::new(),
+            scope: Vec::new(),
+            errors: &mut errors,
+        };
+        validator.function(function);
+    }
+    errors
+}
+
+struct Validator<'a> {
+    program: &'a Program,
+    function: &'a FunctionName,
+    /// Every variable bound in the function so far.
+    bound: BTreeSet<Var>,
+    /// The variables in scope.
+    scope: Vec<Var>,
+    errors: &'a mut Vec<ValidationError>,
+}
+
+impl<'a> Validator<'a> {
+    fn function(&mut self, function: &Function) {
+        for param in &function.params {
+            self.bind(*param);
+        }
+        self.expr(&function.body);
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Let(var, value, body) => {
+                self.value(value);
+                self.bind(*var);
+                self.expr(body);
+            }
+            Expr::Case(scrutinee, alts) => {
+                self.atom(scrutinee);
+                let mut constructors = BTreeSet::new();
+                for alt in alts {
+                    if !constructors.insert(&alt.constructor) {
+                        self.errors.push(ValidationError::DuplicateAlt {
+                            function: self.function.clone(),
+                            constructor: alt.constructor.clone(),
+                        });
+                    }
+                    let scope_len = self.scope.len();
+                    for field in &alt.fields {
+                        self.bind(*field);
+                    }
+                    self.expr(&alt.body);
+                    self.scope.truncate(scope_len);
+                }
+            }
+            Expr::Return(atom) => self.atom(atom),
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Atom(atom) | Value::Fst(atom) | Value::Snd(atom) => self.atom(atom),
+            Value::Call(callee, args) => {
+                self.atoms(args);
+                if let Some(function) = self.callee(callee)
+                    && function.params.len() != args.len()
+                {
+                    self.errors.push(ValidationError::CallArity {
+                        function: self.function.clone(),
+                        callee: callee.clone(),
+                        expected: function.params.len(),
+                        found: args.len(),
+                    });
+                }
+            }
+            Value::Apply(closure, args) => {
+                self.atom(closure);
+                self.atoms(args);
+            }
+            Value::Closure(callee, captured) => {
+                self.atoms(captured);
+                let Some(function) = self.callee(callee) else {
+                    return;
+                };
+                if function.captures != captured.len() {
+                    self.errors.push(ValidationError::ClosureCaptures {
+                        function: self.function.clone(),
+                        callee: callee.clone(),
+                        expected: function.captures,
+                        found: captured.len(),
+                    });
+                } else if function.params.len() == function.captures {
+                    self.errors.push(ValidationError::ClosureWithoutParams {
+                        function: self.function.clone(),
+                        callee: callee.clone(),
+                    });
+                }
+            }
+            Value::Ctor(_, args) | Value::Prim(_, args) => self.atoms(args),
+            Value::Pair(fst, snd) => {
+                self.atom(fst);
+                self.atom(snd);
+            }
+        }
+    }
+
+    fn callee(&mut self, callee: &FunctionName) -> Option<&'a Function> {
+        let function = self.program.functions.get(callee);
+        if function.is_none() {
+            self.errors.push(ValidationError::UnknownFunction {
+                function: self.function.clone(),
+                callee: callee.clone(),
+            });
+        }
+        function
+    }
+
+    fn atoms(&mut self, atoms: &[Atom]) {
+        for atom in atoms {
+            self.atom(atom);
+        }
+    }
+
+    fn atom(&mut self, atom: &Atom) {
+        if let Atom::Var(var) = atom
+            && !self.scope.contains(var)
+        {
+            self.errors.push(ValidationError::UnboundVar {
+                function: self.function.clone(),
+                var: *var,
+            });
+        }
+    }
+
+    fn bind(&mut self, var: Var) {
+        if !self.bound.insert(var) {
+            self.errors.push(ValidationError::Rebound {
+                function: self.function.clone(),
+                var,
+            });
+        }
+        self.scope.push(var);
+    }
+}
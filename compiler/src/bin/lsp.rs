@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+use alloc::{boxed::Box, string::String};
+use api::{
+    io::{fs::MappedFile, stdin},
+    print,
+};
+use compiler::{
+    driver::Files,
+    lsp::{MessageReader, Server, frame},
+};
+
+extern crate alloc;
+extern crate common;
+extern crate runtime;
+
+api::entry!(main);
+
+struct FileSystem;
+
+impl Files for FileSystem {
+    fn read(&mut self, path: &str) -> Option<String> {
+        let file = MappedFile::open(path)?;
+        Some(String::from_utf8_lossy(file.as_bytes()).into_owned())
+    }
+}
+
+/// Serves one editor over standard input and output until it asks the
+/// server to exit or closes the input.
+fn main() -> i32 {
+    let mut server = Server::new(Box::new(FileSystem));
+    let mut reader = MessageReader::default();
+    let mut buf = [0; 4096];
+    loop {
+        while let Some(message) = reader.next_message() {
+            for reply in server.handle(&message) {
+                print!("{}", frame(&reply));
+            }
+            if let Some(code) = server.exit_code() {
+                return code;
+            }
+        }
+        let read = stdin::read(&mut buf);
+        if read == 0 {
+            return 1;
+        }
+        reader.push(&buf[..read]);
+    }
+}
@@ -0,0 +1,302 @@
+use alloc::{collections::btree_map::BTreeMap, string::String, vec::Vec};
+
+use crate::{
+    elaboration::{ElabState, Environment, incremental::CommandReport, info::Info},
+    module::{
+        ModuleId,
+        object::{self, Object, ObjectError},
+    },
+    syntax::{
+        SourceFile, Span,
+        error::{LexError, ParseError},
+        lexer::Lexer,
+        parser::{parse, parse_expr},
+        token::Token,
+        tree::{SyntaxExpr, SyntaxExprKind},
+    },
+};
+
+pub struct Parsed {
+    pub ast: Option<SyntaxExpr>,
+    pub lex_errors: Vec<LexError>,
+    pub parse_errors: Vec<ParseError>,
+}
+
+pub fn parse_source(source_file: &SourceFile) -> Parsed {
+    parse_with(source_file, parse)
+}
+
+/// Parses `source_file` as a lone expression instead of a module.
+pub fn parse_expr_source(source_file: &SourceFile) -> Parsed {
+    parse_with(source_file, parse_expr)
+}
+
+type ParseFn = for<'a> fn(&'a [(Token<'a>, Span)], Span) -> (Option<SyntaxExpr>, Vec<ParseError>);
+
+fn parse_with(source_file: &SourceFile, parser: ParseFn) -> Parsed {
+    let mut lexer = Lexer::new(source_file);
+
+    let mut tokens = Vec::new();
+    let mut lex_errors = Vec::new();
+    for result in &mut lexer {
+        match result {
+            Ok(token) => tokens.push((token, token.span)),
+            Err(err) => lex_errors.push(err),
+        }
+    }
+
+    let eoi_span = lexer.eoi_span();
+    let (ast, parse_errors) = parser(&tokens, eoi_span);
+
+    Parsed {
+        ast,
+        lex_errors,
+        parse_errors,
+    }
+}
+
+/// Finds the source of imported modules, such as on a search path, and
+/// stores their object files.
+pub trait ModuleLoader {
+    fn load(&mut self, module: &str) -> Option<String>;
+
+    /// The object file last stored for `module`.
+    fn load_object(&mut self, _module: &str) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn store_object(&mut self, _module: &str, _object: &[u8]) {}
+
+    /// The clock commands are timed with, in microseconds.
+    fn now_micros(&self) -> u64 {
+        0
+    }
+
+    /// Whether to record what elaborating `module` finds out about its
+    /// syntax, as editors need.
+    fn records_info(&self, _module: &str) -> bool {
+        false
+    }
+}
+
+/// Reads files by path, for what names a file rather than a module, such as
+/// an editor's documents.
+pub trait Files {
+    fn read(&mut self, path: &str) -> Option<String>;
+}
+
+pub struct CompiledModule {
+    pub module_id: ModuleId,
+    pub source: String,
+    /// Empty when the module was read from its object file.
+    pub parsed: Parsed,
+    pub state: ElabState,
+    /// How each top-level command was elaborated. Empty when the module was
+    /// read from its object file.
+    pub commands: Vec<CommandReport>,
+    /// Whether the environment was read from the module's object file
+    /// instead of elaborated.
+    pub from_object: bool,
+    /// Why the module's object file couldn't be read, if it had one.
+    pub object_error: Option<ObjectError>,
+}
+
+/// Elaborates `source` as module `module_id`, after the modules it imports.
+/// Returns every module compiled, each after the modules it imports, so the
+/// one given comes last.
+pub fn compile(loader: &mut dyn ModuleLoader, module_id: ModuleId, source: String) -> Vec<CompiledModule> {
+    let mut compiler = Compiler {
+        loader,
+        modules: Vec::new(),
+        in_progress: Vec::new(),
+        object_hashes: BTreeMap::new(),
+    };
+    let (old, object_error) = compiler.old_object(&module_id);
+    compiler.compile(module_id, source, old.map(|(object, _)| object));
+    if let Some(compiled) = compiler.modules.last_mut() {
+        compiled.object_error = object_error;
+    }
+    compiler.modules
+}
+
+/// Compiles `modules` and the modules they import, for a client that
+/// elaborates code importing them itself, such as the REPL. Returns the
+/// environment of each of `modules` that exists, and every module compiled.
+pub fn compile_imports(
+    loader: &mut dyn ModuleLoader,
+    modules: &[ModuleId],
+) -> (BTreeMap<ModuleId, Option<Environment>>, Vec<CompiledModule>) {
+    let mut compiler = Compiler {
+        loader,
+        modules: Vec::new(),
+        in_progress: Vec::new(),
+        object_hashes: BTreeMap::new(),
+    };
+    let environments = modules
+        .iter()
+        .filter_map(|module| Some((module.clone(), compiler.environment(module)?)))
+        .collect();
+    (environments, compiler.modules)
+}
+
+struct Compiler<'a> {
+    loader: &'a mut dyn ModuleLoader,
+    modules: Vec<CompiledModule>,
+    /// The modules being compiled, each imported by the one before it.
+    in_progress: Vec<ModuleId>,
+    /// The hash of the object file of each module that has one.
+    object_hashes: BTreeMap<ModuleId, u64>,
+}
+
+impl Compiler<'_> {
+    /// Compiles `module_id`, reusing the commands cached in `old`, its last
+    /// object file, if it imported the same modules as now.
+    fn compile(&mut self, module_id: ModuleId, source: String, old: Option<Object>) {
+        let parsed = parse_source(&SourceFile {
+            id: self.modules.len(),
+            name: &module_id,
+            source: source.as_bytes(),
+            package: None,
+        });
+
+        self.in_progress.push(module_id.clone());
+        let mut state = ElabState::pre_loaded(module_id.clone());
+        if self.loader.records_info(&module_id) {
+            state.info = Some(Info::default());
+        }
+        let mut imports = Vec::new();
+        let mut commands = Vec::new();
+        if let Some(ast) = &parsed.ast {
+            collect_imports(ast, &mut imports);
+            for import in &imports {
+                if !state.imports.contains_key(import)
+                    && let Some(env) = self.environment(import)
+                {
+                    state.imports.insert(import.clone(), env);
+                }
+            }
+            let import_hashes = self.import_hashes(&imports);
+            let cache = match old {
+                Some(old) if Some(&old.imports) == import_hashes.as_ref() => old.commands,
+                _ => Vec::new(),
+            };
+            let loader = &*self.loader;
+            match state.elaborate_root_cached(ast, &cache, &|| loader.now_micros()) {
+                Ok(reports) => commands = reports,
+                Err(err) => state.errors.push(err),
+            }
+        }
+        self.in_progress.pop();
+
+        let succeeded = parsed.ast.is_some()
+            && parsed.lex_errors.is_empty()
+            && parsed.parse_errors.is_empty()
+            && state.errors.is_empty();
+        if succeeded && let Some(imports) = self.import_hashes(&imports) {
+            let bytes = object::write_object(&Object {
+                source_hash: object::hash(source.as_bytes()),
+                imports,
+                env: state.env.clone(),
+                commands: commands.iter().filter_map(|report| report.cached.clone()).collect(),
+            });
+            self.object_hashes.insert(module_id.clone(), object::hash(&bytes));
+            self.loader.store_object(&module_id, &bytes);
+        }
+
+        self.modules.push(CompiledModule {
+            module_id,
+            source,
+            parsed,
+            state,
+            commands,
+            from_object: false,
+            object_error: None,
+        });
+    }
+
+    /// The hashes of the object files of `imports`, if they all have one.
+    fn import_hashes(&self, imports: &[ModuleId]) -> Option<Vec<(ModuleId, u64)>> {
+        imports
+            .iter()
+            .map(|import| Some((import.clone(), *self.object_hashes.get(import)?)))
+            .collect()
+    }
+
+    /// The last object file stored for `module` and its hash.
+    fn old_object(&mut self, module: &ModuleId) -> (Option<(Object, u64)>, Option<ObjectError>) {
+        let Some(bytes) = self.loader.load_object(module) else {
+            return (None, None);
+        };
+        match object::read_object(&bytes) {
+            Ok(object) => (Some((object, object::hash(&bytes))), None),
+            Err(err) => (None, Some(err)),
+        }
+    }
+
+    /// Whether `object` was written for `source` and the object files of
+    /// its imports haven't changed since.
+    fn is_current(&mut self, module: &ModuleId, object: &Object, source: &str) -> bool {
+        if object.source_hash != object::hash(source.as_bytes()) {
+            return false;
+        }
+        self.in_progress.push(module.clone());
+        let mut current = true;
+        for (import, hash) in &object.imports {
+            self.environment(import);
+            current &= self.object_hashes.get(import) == Some(hash);
+        }
+        self.in_progress.pop();
+        current
+    }
+
+    /// The environment of `module`, compiling it first if needed. `None` if
+    /// there is no such module, `Some(None)` if it is still being compiled.
+    fn environment(&mut self, module: &ModuleId) -> Option<Option<Environment>> {
+        if self.in_progress.contains(module) {
+            return Some(None);
+        }
+        if let Some(compiled) = self.modules.iter().find(|compiled| compiled.module_id == *module) {
+            return Some(Some(compiled.state.env.clone()));
+        }
+        let source = self.loader.load(module)?;
+        let (old, object_error) = self.old_object(module);
+        if let Some((object, hash)) = &old
+            && self.is_current(module, object, &source)
+        {
+            self.object_hashes.insert(module.clone(), *hash);
+            let mut state = ElabState::new(module.clone());
+            state.env = object.env.clone();
+            self.modules.push(CompiledModule {
+                module_id: module.clone(),
+                source,
+                parsed: Parsed {
+                    ast: None,
+                    lex_errors: Vec::new(),
+                    parse_errors: Vec::new(),
+                },
+                state,
+                commands: Vec::new(),
+                from_object: true,
+                object_error: None,
+            });
+            return Some(Some(object.env.clone()));
+        }
+        self.compile(module.clone(), source, old.map(|(object, _)| object));
+        let compiled = self.modules.last_mut()?;
+        compiled.object_error = object_error;
+        Some(Some(compiled.state.env.clone()))
+    }
+}
+
+/// Adds the modules `syntax` imports to `imports`, in order.
+pub fn collect_imports(syntax: &SyntaxExpr, imports: &mut Vec<ModuleId>) {
+    match &syntax.kind {
+        SyntaxExprKind::Import(module) => imports.push(module.clone()),
+        SyntaxExprKind::Root(commands) | SyntaxExprKind::Namespace { commands, .. } => {
+            for command in commands {
+                collect_imports(command, imports);
+            }
+        }
+        _ => (),
+    }
+}
@@ -1,12 +1,15 @@
 use alloc::{collections::btree_map::BTreeMap, string::String, vec::Vec};
 
-use crate::{module::unique::{Unique, UniqueGen}, spine::Term};
+use crate::{module::unique::{Unique, UniqueGen}, spine::{Level, Term}, syntax::Span};
 
 #[derive(Debug, Clone)]
 pub struct LocalDecl {
     pub fvar: Unique,
     pub type_: Term,
     pub value: Option<Term>,
+    /// Whether names in the source can refer to it. The locals elaboration
+    /// introduces itself can't be.
+    pub accessible: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +28,19 @@ impl LocalContext {
             fvar: fvar.clone(),
             type_,
             value: None,
+            accessible: true,
+        });
+        fvar
+    }
+
+    /// Like `push_binder`, but `lookup_name` doesn't find the local.
+    pub fn push_internal(&mut self, name: String, type_: Term, gen_: &mut UniqueGen) -> Unique {
+        let fvar = gen_.fresh(name);
+        self.decls.push(LocalDecl {
+            fvar: fvar.clone(),
+            type_,
+            value: None,
+            accessible: false,
         });
         fvar
     }
@@ -35,6 +51,7 @@ impl LocalContext {
             fvar: fvar.clone(),
             type_,
             value: Some(value),
+            accessible: true,
         });
         fvar
     }    
@@ -44,7 +61,7 @@ impl LocalContext {
     }
     
     pub fn lookup_name(&self, name: &str) -> Option<&LocalDecl> {
-        self.decls.iter().rev().find(|d| d.fvar.display_name.as_deref() == Some(name))
+        self.decls.iter().rev().find(|d| d.accessible && d.fvar.display_name.as_deref() == Some(name))
     }
 }
 
@@ -53,12 +70,15 @@ pub struct MetavarDecl {
     pub mvar: Unique,
     pub type_: Term,
     pub lctx: LocalContext,
+    /// The syntax the metavariable was created for.
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MetavarContext {
     pub decls: Vec<MetavarDecl>,
     pub assignments: BTreeMap<Unique, Term>,
+    pub level_assignments: BTreeMap<Unique, Level>,
 }
 
 impl MetavarContext {
@@ -66,15 +86,36 @@ impl MetavarContext {
         Self {
             decls: Vec::new(),
             assignments: BTreeMap::new(),
+            level_assignments: BTreeMap::new(),
         }
     }
 
-    pub fn fresh_mvar(&mut self, type_: Term, lctx: &LocalContext, gen_: &mut UniqueGen) -> Unique {
+    pub fn fresh_mvar(&mut self, type_: Term, lctx: &LocalContext, span: Span, gen_: &mut UniqueGen) -> Unique {
         let mvar = gen_.fresh_unnamed();
         self.decls.push(MetavarDecl {
             mvar: mvar.clone(),
             type_,
             lctx: lctx.clone(),
+            span,
+        });
+        mvar
+    }
+
+    /// A metavariable for a `?name` hole. Unification never assigns it.
+    pub fn fresh_named_mvar(
+        &mut self,
+        name: String,
+        type_: Term,
+        lctx: &LocalContext,
+        span: Span,
+        gen_: &mut UniqueGen,
+    ) -> Unique {
+        let mvar = gen_.fresh(name);
+        self.decls.push(MetavarDecl {
+            mvar: mvar.clone(),
+            type_,
+            lctx: lctx.clone(),
+            span,
         });
         mvar
     }
@@ -92,6 +133,19 @@ impl MetavarContext {
         self.assignments.get(&mvar)
     }
 
+    pub fn fresh_level_mvar(&mut self, gen_: &mut UniqueGen) -> Level {
+        Level::MVar(gen_.fresh_unnamed())
+    }
+
+    pub fn assign_level(&mut self, mvar: Unique, level: Level) {
+        assert!(!self.level_assignments.contains_key(&mvar), "level mvar already assigned");
+        self.level_assignments.insert(mvar, level);
+    }
+
+    pub fn get_level_assignment(&self, mvar: &Unique) -> Option<&Level> {
+        self.level_assignments.get(mvar)
+    }
+
     pub fn lookup_decl(&self, mvar: Unique) -> Option<&MetavarDecl> {
         self.decls.iter().find(|d| d.mvar == mvar)
     }
@@ -0,0 +1,293 @@
+//! Reading terms back into surface syntax, so they can be shown the way
+//! users write them.
+//!
+//! Bound variables keep the names their binders were written with, unless
+//! that would capture a free variable, a constant or an enclosing binder the
+//! body refers to. Those, and binders without a name, are named afresh: `x`,
+//! `y`, `z`, `w`, then `x1` and so on.
+
+use alloc::{
+    boxed::Box,
+    collections::btree_set::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    elaboration::{Environment, subst},
+    module::{
+        name::QualifiedName,
+        prim::{PRIM_ARRAY_CONS, PRIM_ARRAY_NIL},
+    },
+    spine::{BinderInfo, BinderName, Level, Term},
+    syntax::{
+        Span,
+        tree::{SyntaxBinder, SyntaxExpr, SyntaxExprKind, SyntaxLevel},
+    },
+};
+
+/// Delaborates `term`. Implicit arguments of constants are left out unless
+/// `show_implicit` is set, which needs `env` to know which they are.
+pub fn delaborate(term: &Term, env: Option<&Environment>, show_implicit: bool) -> SyntaxExpr {
+    Delaborator::new(env, show_implicit, &[term]).delab(term)
+}
+
+/// Delaborates several terms that are shown together, such as the type and
+/// value of a definition, so that their bound variables are named alike.
+pub fn delaborate_all(terms: &[&Term], env: Option<&Environment>, show_implicit: bool) -> Vec<SyntaxExpr> {
+    let mut delaborator = Delaborator::new(env, show_implicit, terms);
+    terms.iter().map(|term| delaborator.delab(term)).collect()
+}
+
+pub fn delaborate_level(level: &Level) -> SyntaxLevel {
+    if let Some(n) = level.to_nat() {
+        return SyntaxLevel::Nat(n);
+    }
+    match level {
+        Level::Succ(_) => {
+            let mut base = level;
+            let mut offset = 0;
+            while let Level::Succ(inner) = base {
+                base = inner;
+                offset += 1;
+            }
+            SyntaxLevel::Add(Box::new(delaborate_level(base)), offset)
+        }
+        Level::Max(a, b) => SyntaxLevel::Max(Box::new(delaborate_level(a)), Box::new(delaborate_level(b))),
+        Level::IMax(a, b) => SyntaxLevel::IMax(Box::new(delaborate_level(a)), Box::new(delaborate_level(b))),
+        Level::Param(name) => SyntaxLevel::Param(name.clone()),
+        Level::MVar(unique) => SyntaxLevel::Param(format!("?u{}", unique.id)),
+        Level::Zero => SyntaxLevel::Nat(0),
+    }
+}
+
+/// The name a constant is written with.
+pub fn const_name(name: &QualifiedName) -> String {
+    match (name.display(), name) {
+        (Some(display), _) => display.to_string(),
+        (None, QualifiedName::User(unique)) => format!("_c{}", unique.id),
+        (None, QualifiedName::Intrinsic(_)) => unreachable!("intrinsics are always named"),
+    }
+}
+
+struct Delaborator<'a> {
+    env: Option<&'a Environment>,
+    show_implicit: bool,
+    /// Names of the bound variables in scope, innermost last.
+    bound: Vec<String>,
+    /// Names of the free variables and constants in the delaborated terms.
+    reserved: BTreeSet<String>,
+}
+
+impl<'a> Delaborator<'a> {
+    fn new(env: Option<&'a Environment>, show_implicit: bool, terms: &[&Term]) -> Self {
+        let mut reserved = BTreeSet::new();
+        for term in terms {
+            subst::for_each_fvar(term, &mut |fvar| {
+                reserved.extend(fvar.display_name.clone());
+            });
+            subst::for_each_const(term, &mut |name| {
+                reserved.insert(const_name(name));
+            });
+        }
+        Self {
+            env,
+            show_implicit,
+            bound: Vec::new(),
+            reserved,
+        }
+    }
+
+    /// The name of a binder over `body`, written with `hint`.
+    fn binder_name(&self, hint: &BinderName, body: &Term) -> String {
+        match &hint.0 {
+            Some(name) if !self.reserved.contains(name) && !self.captures(name, body) => name.clone(),
+            _ => self.fresh_name(),
+        }
+    }
+
+    /// Whether naming a binder over `body` `name` would capture an
+    /// enclosing binder `body` refers to.
+    fn captures(&self, name: &str, body: &Term) -> bool {
+        self.bound
+            .iter()
+            .rev()
+            .enumerate()
+            .any(|(index, bound)| bound == name && subst::has_loose_bvar(body, index + 1))
+    }
+
+    fn fresh_name(&self) -> String {
+        (0..)
+            .flat_map(|suffix| {
+                ["x", "y", "z", "w"].map(|base| match suffix {
+                    0 => base.to_string(),
+                    n => format!("{}{}", base, n),
+                })
+            })
+            .find(|name| !self.reserved.contains(name) && !self.bound.contains(name))
+            .unwrap()
+    }
+
+    fn delab(&mut self, term: &Term) -> SyntaxExpr {
+        let kind = match term {
+            Term::BVar(index) => match self.bound.len().checked_sub(index + 1) {
+                Some(level) => SyntaxExprKind::Var(self.bound[level].clone()),
+                None => SyntaxExprKind::Var(format!("#{}", index)),
+            },
+            Term::FVar(unique) => match &unique.display_name {
+                Some(name) => SyntaxExprKind::Var(name.clone()),
+                None => SyntaxExprKind::Var(format!("_f{}", unique.id)),
+            },
+            Term::MVar(unique) => match &unique.display_name {
+                Some(name) => SyntaxExprKind::NamedHole(name.clone()),
+                None => SyntaxExprKind::NamedHole(format!("m{}", unique.id)),
+            },
+            Term::Const(name, _) => return path(&const_name(name)),
+            Term::Sort(Level::Zero) => SyntaxExprKind::Sort(None),
+            Term::Sort(level) => SyntaxExprKind::Sort(Some(delaborate_level(level))),
+            Term::Lit(lit) => SyntaxExprKind::Lit(lit.clone()),
+            Term::App(..) => return self.delab_app(term),
+            Term::Pi(_, BinderInfo::Explicit, domain, body) if !subst::has_loose_bvar(body, 0) => {
+                let domain = self.delab(domain);
+                let body = self.under_binder(String::from("_"), |this| this.delab(body));
+                SyntaxExprKind::Arrow(Box::new(domain), Box::new(body))
+            }
+            Term::Sigma(_, BinderInfo::Explicit, domain, body) if !subst::has_loose_bvar(body, 0) => {
+                let domain = self.delab(domain);
+                let body = self.under_binder(String::from("_"), |this| this.delab(body));
+                SyntaxExprKind::Sigma(SyntaxBinder::Explicit(String::from("_"), Box::new(domain)), Box::new(body))
+            }
+            Term::Pi(hint, info, domain, body) | Term::Sigma(hint, info, domain, body) => {
+                let name = self.binder_name(hint, body);
+                let binder = binder(info, name.clone(), self.delab(domain));
+                let body = Box::new(self.under_binder(name, |this| this.delab(body)));
+                match term {
+                    Term::Pi(..) => SyntaxExprKind::Pi(binder, body),
+                    _ => SyntaxExprKind::Sigma(binder, body),
+                }
+            }
+            Term::Lam(..) => {
+                let mut binders = Vec::new();
+                let mut body = term;
+                let scope = self.bound.len();
+                while let Term::Lam(hint, info, domain, inner) = body {
+                    let name = self.binder_name(hint, inner);
+                    binders.push(binder(info, name.clone(), self.delab(domain)));
+                    self.bound.push(name);
+                    body = inner;
+                }
+                let body = Box::new(self.delab(body));
+                self.bound.truncate(scope);
+                SyntaxExprKind::Lambda { binders, body }
+            }
+            Term::Let(hint, type_, value, body) => {
+                let name = self.binder_name(hint, body);
+                let type_ann = Some(Box::new(self.delab(type_)));
+                let value = Box::new(self.delab(value));
+                let body = Box::new(self.under_binder(name.clone(), |this| this.delab(body)));
+                SyntaxExprKind::Let {
+                    name,
+                    type_ann,
+                    value,
+                    body,
+                }
+            }
+            Term::Pair(_, fst, snd) => {
+                let mut items = alloc::vec![self.delab(fst)];
+                let mut rest = &**snd;
+                while let Term::Pair(_, fst, snd) = rest {
+                    items.push(self.delab(fst));
+                    rest = snd;
+                }
+                items.push(self.delab(rest));
+                SyntaxExprKind::Tuple(items)
+            }
+            Term::Fst(pair) => SyntaxExprKind::Proj(Box::new(self.delab(pair)), String::from("fst")),
+            Term::Snd(pair) => SyntaxExprKind::Proj(Box::new(self.delab(pair)), String::from("snd")),
+        };
+        syntax(kind)
+    }
+
+    fn delab_app(&mut self, term: &Term) -> SyntaxExpr {
+        if let Some(elems) = array_elems(term) {
+            let elems = elems.into_iter().map(|elem| self.delab(elem)).collect();
+            return syntax(SyntaxExprKind::Array(elems));
+        }
+
+        let head = term.app_head();
+        let mut args = term.app_args();
+        if !self.show_implicit
+            && let Term::Const(name, _) = head
+            && let Some(decl) = self.env.and_then(|env| env.lookup(name))
+        {
+            let mut type_ = decl.type_();
+            let mut explicit = Vec::new();
+            for arg in args {
+                match type_ {
+                    Term::Pi(_, info, _, body) => {
+                        if *info == BinderInfo::Explicit {
+                            explicit.push(arg);
+                        }
+                        type_ = body;
+                    }
+                    _ => explicit.push(arg),
+                }
+            }
+            args = explicit;
+        }
+        let head = self.delab(head);
+        args.into_iter().fold(head, |fun, arg| {
+            let arg = self.delab(arg);
+            syntax(SyntaxExprKind::App(Box::new(fun), Box::new(arg)))
+        })
+    }
+
+    fn under_binder(&mut self, name: String, f: impl FnOnce(&mut Self) -> SyntaxExpr) -> SyntaxExpr {
+        self.bound.push(name);
+        let syntax = f(self);
+        self.bound.pop();
+        syntax
+    }
+}
+
+/// The elements of a chain of `Array.cons` ending in `Array.nil`.
+fn array_elems(term: &Term) -> Option<Vec<&Term>> {
+    let mut elems = Vec::new();
+    let mut current = term;
+    loop {
+        let args = current.app_args();
+        match (current.app_head(), args.as_slice()) {
+            (Term::Const(name, _), [_]) if *name == PRIM_ARRAY_NIL => return Some(elems),
+            (Term::Const(name, _), [_, _, head, tail]) if *name == PRIM_ARRAY_CONS => {
+                elems.push(*head);
+                current = tail;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn syntax(kind: SyntaxExprKind) -> SyntaxExpr {
+    SyntaxExpr::new(kind, Span::empty(0, 0))
+}
+
+/// `List.cons` as the constructor `List` projected, the way it parses.
+fn path(name: &str) -> SyntaxExpr {
+    let mut segments = name.split('.');
+    let head = segments.next().unwrap_or_default();
+    if !head.starts_with(char::is_uppercase) {
+        return syntax(SyntaxExprKind::Var(name.to_string()));
+    }
+    segments.fold(syntax(SyntaxExprKind::Constructor(head.to_string())), |head, field| {
+        syntax(SyntaxExprKind::Proj(Box::new(head), field.to_string()))
+    })
+}
+
+fn binder(info: &BinderInfo, name: String, type_: SyntaxExpr) -> SyntaxBinder {
+    match info {
+        BinderInfo::Explicit => SyntaxBinder::Explicit(name, Box::new(type_)),
+        BinderInfo::Implicit | BinderInfo::StrictImplicit => SyntaxBinder::Implicit(name, Box::new(type_)),
+        BinderInfo::InstanceImplicit => SyntaxBinder::Instance(name, Box::new(type_)),
+    }
+}
@@ -1,8 +1,21 @@
-use alloc::string::String;
+use alloc::{boxed::Box, string::String};
+use core::fmt;
+
+use miette::{Diagnostic, LabeledSpan, Severity, StdError};
 use thiserror::Error;
 
+use crate::syntax::Span;
+
+/// An elaboration error, located at the syntax being elaborated when it was
+/// reported.
+#[derive(Debug)]
+pub struct ElabError {
+    pub kind: ElabErrorKind,
+    pub span: Span,
+}
+
 #[derive(Debug, Error)]
-pub enum ElabError {
+pub enum ElabErrorKind {
     #[error("expected root")]
     ExpectedRoot,
     #[error("undefined variable `{0}`")]
@@ -11,8 +24,187 @@ pub enum ElabError {
     UndefinedConstructor(String),
     #[error("type mismatch: expected `{expected}`, found `{found}`")]
     TypeMismatch { expected: crate::spine::Term, found: crate::spine::Term },
-    #[error("unsupported syntax: `{0:?}`")]
-    UnsupportedSyntax(crate::syntax::tree::SyntaxExpr),
+    #[error("unsupported syntax")]
+    UnsupportedSyntax,
     #[error("can't apply to non-function type `{0}`")]
     NotAFunction(crate::spine::Term),
-}
\ No newline at end of file
+    #[error("expected a type, found term of type `{0}`")]
+    ExpectedType(crate::spine::Term),
+    #[error("invalid type for inductive `{name}`: `{type_}` is not an arity")]
+    InvalidInductiveType { name: String, type_: crate::spine::Term },
+    #[error("constructor `{name}` must return `{inductive}` applied to its parameters, found `{found}`")]
+    InvalidConstructorType { name: String, inductive: String, found: crate::spine::Term },
+    #[error("`{inductive}` occurs in a non-positive position in constructor `{name}`")]
+    NonPositiveOccurrence { name: String, inductive: String },
+    #[error("field of constructor `{name}` lives in a universe larger than `{inductive}`")]
+    UniverseTooLarge { name: String, inductive: String },
+    #[error("universe level mismatch: expected `{expected}`, found `{found}`")]
+    LevelMismatch { expected: crate::spine::Level, found: crate::spine::Level },
+    #[error("expected a structure, found term of type `{0}`")]
+    NotAStructure(crate::spine::Term),
+    #[error("structure `{structure}` has no field `{field}`")]
+    UnknownField { structure: String, field: String },
+    #[error("anonymous constructor needs an expected type with exactly one constructor, found `{0}`")]
+    InvalidAnonymousConstructor(crate::spine::Term),
+    #[error("cannot infer the type of an anonymous constructor")]
+    AnonymousConstructorWithoutType,
+    #[error("anonymous constructor expects {expected} arguments, found {found}")]
+    AnonymousConstructorArity { expected: usize, found: usize },
+    #[error("non-exhaustive match{}", .0.as_ref().map(|c| alloc::format!(": missing case for `{}`", c)).unwrap_or_default())]
+    NonExhaustiveMatch(Option<String>),
+    #[error("match arm {0} is redundant")]
+    RedundantMatchArm(usize),
+    #[error("cannot match on a value of non-inductive type `{0}`")]
+    NotAnInductive(crate::spine::Term),
+    #[error("unknown constructor `{0}` in pattern")]
+    UnknownPatternConstructor(String),
+    #[error("constructor `{constructor}` expects {expected} pattern arguments, found {found}")]
+    PatternArity { constructor: String, expected: usize, found: usize },
+    #[error("recursive call `{call}` to `{function}` is not structurally decreasing")]
+    NonDecreasingCall { function: String, call: crate::spine::Term },
+    #[error("`{0}` is recursive but has no argument of an inductive type to recurse on")]
+    NoStructuralArgument(String),
+    #[error("kernel rejected `{name}`: {error}")]
+    KernelRejected { name: String, error: crate::kernel::err::KernelError },
+    #[error("mutual definition `{0}` must recurse on the same inductive type as the rest of its block, without fixed parameters")]
+    UnsupportedMutualRecursion(String),
+    #[error("`{0}` is not a class, so it can't have instances")]
+    NotAClass(crate::spine::Term),
+    #[error("failed to synthesize instance `{goal}`{}{}", tried_instances(.tried), .cycle.as_ref().map(|c| alloc::format!("; resolution cycles through `{}`", c)).unwrap_or_default())]
+    NoInstance { goal: crate::spine::Term, tried: alloc::vec::Vec<String>, cycle: Option<crate::spine::Term> },
+    #[error("unsolved goal `{goal}` : `{type_}`{}", crate::log::pretty::pretty_local_context(.context))]
+    UnsolvedGoal { goal: crate::spine::Term, type_: crate::spine::Term, context: crate::elaboration::ctx::LocalContext },
+    #[error("cannot unify `{lhs}` with `{rhs}`")]
+    CannotUnify { lhs: crate::spine::Term, rhs: crate::spine::Term },
+    #[error("cannot solve `{lhs} =?= {rhs}`: it is stuck on an unassigned metavariable")]
+    StuckUnification { lhs: crate::spine::Term, rhs: crate::spine::Term },
+    #[error("`{name}` is ambiguous: it could refer to {}", quoted(.candidates))]
+    AmbiguousName { name: String, candidates: alloc::vec::Vec<String> },
+    #[error("module `{0}` not found")]
+    UnknownModule(String),
+    #[error("importing `{0}` forms a cycle of imports")]
+    ImportCycle(String),
+    #[error("unknown namespace `{0}`")]
+    UnknownNamespace(String),
+    #[error("`{name}` is declared by both this module and `{module}`")]
+    DuplicateDeclaration { name: String, module: String },
+}
+
+fn tried_instances(tried: &[String]) -> String {
+    if tried.is_empty() {
+        return String::from("; the class has no instances in scope");
+    }
+    alloc::format!("; tried {}", quoted(tried))
+}
+
+fn quoted(names: &[String]) -> String {
+    let names: alloc::vec::Vec<String> = names.iter().map(|name| alloc::format!("`{}`", name)).collect();
+    names.join(", ")
+}
+
+impl fmt::Display for ElabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl StdError for ElabError {}
+
+impl Diagnostic for ElabError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let code = match &self.kind {
+            ElabErrorKind::ExpectedRoot => "E0200",
+            ElabErrorKind::UndefinedVariable(_) => "E0201",
+            ElabErrorKind::UndefinedConstructor(_) => "E0202",
+            ElabErrorKind::TypeMismatch { .. } => "E0203",
+            ElabErrorKind::UnsupportedSyntax => "E0204",
+            ElabErrorKind::NotAFunction(_) => "E0205",
+            ElabErrorKind::ExpectedType(_) => "E0206",
+            ElabErrorKind::InvalidInductiveType { .. } => "E0207",
+            ElabErrorKind::InvalidConstructorType { .. } => "E0208",
+            ElabErrorKind::NonPositiveOccurrence { .. } => "E0209",
+            ElabErrorKind::UniverseTooLarge { .. } => "E0210",
+            ElabErrorKind::LevelMismatch { .. } => "E0211",
+            ElabErrorKind::NotAStructure(_) => "E0212",
+            ElabErrorKind::UnknownField { .. } => "E0213",
+            ElabErrorKind::InvalidAnonymousConstructor(_) => "E0214",
+            ElabErrorKind::AnonymousConstructorWithoutType => "E0215",
+            ElabErrorKind::AnonymousConstructorArity { .. } => "E0216",
+            ElabErrorKind::NonExhaustiveMatch(_) => "E0217",
+            ElabErrorKind::RedundantMatchArm(_) => "E0218",
+            ElabErrorKind::NotAnInductive(_) => "E0219",
+            ElabErrorKind::UnknownPatternConstructor(_) => "E0220",
+            ElabErrorKind::PatternArity { .. } => "E0221",
+            ElabErrorKind::NonDecreasingCall { .. } => "E0222",
+            ElabErrorKind::NoStructuralArgument(_) => "E0223",
+            ElabErrorKind::KernelRejected { .. } => "E0224",
+            ElabErrorKind::UnsupportedMutualRecursion(_) => "E0225",
+            ElabErrorKind::NotAClass(_) => "E0226",
+            ElabErrorKind::NoInstance { .. } => "E0227",
+            ElabErrorKind::UnsolvedGoal { .. } => "E0228",
+            ElabErrorKind::CannotUnify { .. } => "E0229",
+            ElabErrorKind::StuckUnification { .. } => "E0230",
+            ElabErrorKind::AmbiguousName { .. } => "E0231",
+            ElabErrorKind::UnknownModule(_) => "E0232",
+            ElabErrorKind::ImportCycle(_) => "E0233",
+            ElabErrorKind::UnknownNamespace(_) => "E0234",
+            ElabErrorKind::DuplicateDeclaration { .. } => "E0235",
+        };
+        Some(Box::new(code))
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        Some(Severity::Error)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = match &self.kind {
+            ElabErrorKind::UndefinedVariable(_) | ElabErrorKind::UndefinedConstructor(_) => {
+                "check the spelling, or define it before this point"
+            }
+            ElabErrorKind::TypeMismatch { .. } | ElabErrorKind::CannotUnify { .. } => {
+                "add a type annotation to find out where the types first differ"
+            }
+            ElabErrorKind::NotAFunction(_) => "this term is applied to more arguments than its type takes",
+            ElabErrorKind::NonPositiveOccurrence { .. } => {
+                "the inductive may only appear to the right of arrows in its constructors' fields"
+            }
+            ElabErrorKind::AnonymousConstructorWithoutType => "annotate the expected type, e.g. `(⟨a, b⟩ : T)`",
+            ElabErrorKind::NonExhaustiveMatch(_) => "add an arm for the missing case, or a catch-all `_` arm",
+            ElabErrorKind::RedundantMatchArm(_) => "earlier arms already cover every value this arm matches",
+            ElabErrorKind::NonDecreasingCall { .. } | ElabErrorKind::NoStructuralArgument(_) => {
+                "recursive calls must be on a variable bound by matching on the argument being recursed on"
+            }
+            ElabErrorKind::NoInstance { .. } => "declare an instance of the class for this type",
+            ElabErrorKind::UnsolvedGoal { .. } => "replace the hole with a term of the type shown",
+            ElabErrorKind::StuckUnification { .. } => "add type annotations or explicit arguments",
+            ElabErrorKind::AmbiguousName { .. } => "write the full name of the declaration you mean",
+            ElabErrorKind::UnknownModule(_) => "modules are looked up below the directories given with `-I`",
+            ElabErrorKind::ImportCycle(_) => "move the declarations both modules need into a third module",
+            _ => return None,
+        };
+        Some(Box::new(help))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let label = match &self.kind {
+            ElabErrorKind::UndefinedVariable(_) | ElabErrorKind::UndefinedConstructor(_) => "not found in scope",
+            ElabErrorKind::TypeMismatch { expected, .. } => {
+                return Some(labelled(self.span, alloc::format!("expected `{}`", expected)));
+            }
+            ElabErrorKind::NotAFunction(_) => "applied here",
+            ElabErrorKind::ExpectedType(_) => "expected a type",
+            ElabErrorKind::UnsolvedGoal { type_, .. } => return Some(labelled(self.span, alloc::format!("`{}`", type_))),
+            ElabErrorKind::NoInstance { .. } => "instance needed here",
+            ElabErrorKind::KernelRejected { .. } => "in this definition",
+            ElabErrorKind::NonDecreasingCall { .. } => "not structurally decreasing",
+            ElabErrorKind::UnknownModule(_) | ElabErrorKind::ImportCycle(_) => "imported here",
+            _ => "here",
+        };
+        Some(labelled(self.span, String::from(label)))
+    }
+}
+
+fn labelled(span: Span, text: String) -> Box<dyn Iterator<Item = LabeledSpan>> {
+    Box::new(core::iter::once(LabeledSpan::new(Some(text), span.start, span.end - span.start)))
+}
@@ -0,0 +1,210 @@
+//! Reusing what top-level commands declared in an earlier compilation.
+//!
+//! A command is identified by a hash of its syntax, printed without spans,
+//! and of the namespaces opened where it is elaborated. Its cached
+//! declarations are reused when the hash is unchanged, each declaration of an
+//! earlier command that they refer to is still the one its name resolves to,
+//! and each global name the command mentions, imported ones included, still
+//! resolves to what it did. A command that is elaborated again declares new
+//! uniques, so every command that depends on it is elaborated again too.
+
+use alloc::{
+    collections::btree_set::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    elaboration::{Declaration, ElabState, err::ElabError, err::ElabErrorKind, subst},
+    module::{
+        name::QualifiedName,
+        object::{self, CachedCommand},
+    },
+    spine::Term,
+    syntax::tree::{SyntaxExpr, SyntaxExprKind},
+};
+
+/// How a top-level command was elaborated.
+pub struct CommandReport {
+    /// The command's keyword and name, such as `def map`.
+    pub summary: String,
+    pub reused: bool,
+    pub micros: u64,
+    /// What to cache for the command, if it declared something without
+    /// errors.
+    pub cached: Option<CachedCommand>,
+}
+
+impl ElabState {
+    /// Elaborates the commands of `root` like `elaborate_root`, reusing the
+    /// commands in `cache` that are still valid. `now_micros` is the clock
+    /// commands are timed with.
+    pub fn elaborate_root_cached(
+        &mut self,
+        root: &SyntaxExpr,
+        cache: &[CachedCommand],
+        now_micros: &dyn Fn() -> u64,
+    ) -> Result<Vec<CommandReport>, ElabError> {
+        let SyntaxExprKind::Root(commands) = &root.kind else {
+            return Err(ElabError {
+                kind: ElabErrorKind::ExpectedRoot,
+                span: root.span,
+            });
+        };
+
+        for decl in cache.iter().flat_map(|command| &command.decls) {
+            if let QualifiedName::User(unique) = decl.name()
+                && unique.module_id == self.env.module_id
+            {
+                self.gen_.skip_past(unique.id);
+            }
+        }
+
+        let mut reports = Vec::new();
+        for cmd in commands {
+            let start = now_micros();
+            let mut report = CommandReport {
+                summary: summary(cmd),
+                reused: false,
+                micros: 0,
+                cached: None,
+            };
+            if declares(cmd) {
+                let hash = self.command_hash(cmd);
+                match cache.iter().find(|cached| cached.hash == hash && self.is_current(cached)) {
+                    Some(cached) => {
+                        self.span = cmd.span;
+                        self.reuse(cached);
+                        report.reused = true;
+                        report.cached = Some(cached.clone());
+                    }
+                    None => report.cached = self.elaborate_cacheable(cmd, hash),
+                }
+            } else {
+                self.elaborate_command(cmd);
+            }
+            report.micros = now_micros().saturating_sub(start);
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    fn command_hash(&self, cmd: &SyntaxExpr) -> u64 {
+        let opens: Vec<String> = self.opens.iter().map(|open| open.to_string()).collect();
+        let key = format!("{}\n{}\n{}", self.namespace, opens.join(" "), cmd);
+        object::hash(key.as_bytes())
+    }
+
+    fn is_current(&self, cached: &CachedCommand) -> bool {
+        let dependencies_current = cached.dependencies.iter().all(|dependency| {
+            self.env.lookup(dependency).is_some()
+                && dependency
+                    .path()
+                    .is_none_or(|path| self.env.lookup_name(&path) == Some(dependency))
+        });
+        dependencies_current
+            && cached.resolutions.iter().all(|(name, resolved)| {
+                matches!(&self.global_candidates(name)[..], [found] if found == resolved)
+            })
+    }
+
+    fn reuse(&mut self, cached: &CachedCommand) {
+        for decl in &cached.decls {
+            self.declare(decl.clone());
+        }
+        self.env.classes.extend(cached.classes.iter().cloned());
+        self.env.instances.extend(cached.instances.iter().cloned());
+    }
+
+    fn elaborate_cacheable(&mut self, cmd: &SyntaxExpr, hash: u64) -> Option<CachedCommand> {
+        let errors_before = self.errors.len();
+        let decls_before: BTreeSet<QualifiedName> = self.env.decls.keys().cloned().collect();
+        let classes_before = self.env.classes.clone();
+        let instances_before = self.env.instances.len();
+        self.resolutions = Some(Vec::new());
+        self.elaborate_command(cmd);
+        let resolutions = self.resolutions.take().unwrap_or_default();
+        if self.errors.len() != errors_before {
+            return None;
+        }
+
+        let decls: Vec<Declaration> = self
+            .env
+            .decls
+            .iter()
+            .filter(|(name, _)| !decls_before.contains(name))
+            .map(|(_, decl)| decl.clone())
+            .collect();
+        let mut dependencies = Vec::new();
+        for term in decls.iter().flat_map(decl_terms) {
+            subst::for_each_const(term, &mut |name| {
+                if let QualifiedName::User(unique) = name
+                    && unique.module_id == self.env.module_id
+                    && decls.iter().all(|decl| decl.name() != name)
+                    && !dependencies.contains(name)
+                {
+                    dependencies.push(name.clone());
+                }
+            });
+        }
+        // Names of the command's own declarations resolve to them whenever
+        // they are reused.
+        let resolutions = resolutions
+            .into_iter()
+            .filter(|(_, resolved)| decls.iter().all(|decl| decl.name() != resolved))
+            .collect();
+        Some(CachedCommand {
+            hash,
+            decls,
+            classes: self.env.classes.difference(&classes_before).cloned().collect(),
+            instances: self.env.instances[instances_before..].to_vec(),
+            dependencies,
+            resolutions,
+        })
+    }
+}
+
+/// Whether `cmd` only adds declarations, so that its result can be cached.
+/// Evaluations are always run again, and the other commands change the scope
+/// of those after them.
+fn declares(cmd: &SyntaxExpr) -> bool {
+    matches!(
+        cmd.kind,
+        SyntaxExprKind::Def { .. }
+            | SyntaxExprKind::Inductive { .. }
+            | SyntaxExprKind::Struct { .. }
+            | SyntaxExprKind::Class { .. }
+            | SyntaxExprKind::Instance { .. }
+            | SyntaxExprKind::Mutual(_)
+    )
+}
+
+fn summary(cmd: &SyntaxExpr) -> String {
+    match &cmd.kind {
+        SyntaxExprKind::Def { name, .. } => format!("def {}", name),
+        SyntaxExprKind::Inductive { name, .. } => format!("inductive {}", name),
+        SyntaxExprKind::Struct { name, .. } => format!("struct {}", name),
+        SyntaxExprKind::Class { name, .. } => format!("class {}", name),
+        SyntaxExprKind::Instance { name, .. } => format!("instance {}", name),
+        SyntaxExprKind::Mutual(defs) => {
+            let names: Vec<String> = defs.iter().map(summary).collect();
+            format!("mutual {}", names.join(", "))
+        }
+        SyntaxExprKind::Eval(_) => String::from("eval"),
+        SyntaxExprKind::Import(module) => format!("import {}", module),
+        SyntaxExprKind::Namespace { name, .. } => format!("namespace {}", name),
+        SyntaxExprKind::Open(paths) => format!("open {}", paths.join(" ")),
+        _ => String::from("command"),
+    }
+}
+
+fn decl_terms(decl: &Declaration) -> Vec<&Term> {
+    let mut terms = alloc::vec![decl.type_()];
+    match decl {
+        Declaration::Definition { value, .. } => terms.push(value),
+        Declaration::Recursor { rules, .. } => terms.extend(rules.iter().map(|rule| &rule.rhs)),
+        _ => {}
+    }
+    terms
+}
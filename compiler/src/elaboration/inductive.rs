@@ -0,0 +1,435 @@
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{
+    elaboration::{
+        Binder, Declaration, ElabState, RecursorRule, err::ElabErrorKind, reduce, subst, unify,
+    },
+    module::name::QualifiedName,
+    spine::{BinderInfo, Level, Term},
+    syntax::tree::{SyntaxBinder, SyntaxConstructor, SyntaxExpr},
+};
+
+/// A constructor field whose type ends in the inductive being defined,
+/// `(ys : Bs) -> I params indices`.
+struct RecursiveField {
+    field: usize,
+    binders: Vec<Binder>,
+    indices: Vec<Term>,
+}
+
+struct CheckedConstructor {
+    name: QualifiedName,
+    fields: Vec<Binder>,
+    indices: Vec<Term>,
+    recursive: Vec<RecursiveField>,
+}
+
+impl ElabState {
+    pub(super) fn elaborate_inductive(
+        &mut self,
+        name: &str,
+        binders: &[SyntaxBinder],
+        type_: Option<&SyntaxExpr>,
+        constructors: &[SyntaxConstructor],
+    ) -> Option<QualifiedName> {
+        let name = &self.qualify(name);
+        let saved_lctx = self.lctx.clone();
+        let params = self.elaborate_binders(binders);
+        let arity = match type_ {
+            Some(type_) => self.elaborate_type(type_).0,
+            None => Term::Sort(Level::Zero),
+        };
+        let arity = unify::instantiate_mvars(self, &arity);
+
+        let (indices, level) = self.open_pi(&arity, "i");
+        let Term::Sort(level) = level else {
+            self.report(ElabErrorKind::InvalidInductiveType {
+                name: name.into(),
+                type_: arity,
+            });
+            self.lctx = saved_lctx;
+            return None;
+        };
+
+        let ind_name = QualifiedName::User(self.gen_.fresh(name.into()));
+        let rec_name = QualifiedName::User(self.gen_.fresh(format!("{}.rec", name)));
+        self.declare(Declaration::Inductive {
+            name: ind_name.clone(),
+            level_params: self.level_params.clone(),
+            type_: subst::mk_pi(&params, arity.clone()),
+            num_params: params.len(),
+            num_indices: indices.len(),
+            constructors: Vec::new(),
+            recursor: rec_name.clone(),
+        });
+
+        let mut checked = Vec::new();
+        for ctor in constructors {
+            let ctor_lctx = self.lctx.clone();
+            if let Some(ctor) = self.check_constructor(name, &ind_name, &params, &level, ctor) {
+                checked.push(ctor);
+            }
+            self.lctx = ctor_lctx;
+        }
+
+        // Parameters may only be bound in the header, where the inductive's
+        // references to itself have already been given its levels.
+        self.solve_level_constraints();
+        let ind_type = self.instantiate_for_env(&subst::mk_pi(&params, arity));
+        if let Some(Declaration::Inductive { type_, constructors, .. }) = self.env.decls.get_mut(&ind_name) {
+            *type_ = ind_type;
+            *constructors = checked.iter().map(|ctor| ctor.name.clone()).collect();
+        }
+
+        let implicit_params: Vec<Binder> = params
+            .iter()
+            .map(|(fvar, _, ty)| (fvar.clone(), BinderInfo::Implicit, ty.clone()))
+            .collect();
+        for ctor in &checked {
+            let result = self.mk_inductive_app(&ind_name, &params, &ctor.indices);
+            let type_ = subst::mk_pi(&implicit_params, subst::mk_pi(&ctor.fields, result));
+            let type_ = self.instantiate_for_env(&type_);
+            self.declare(Declaration::Intro {
+                name: ctor.name.clone(),
+                level_params: self.level_params.clone(),
+                type_,
+                inductive: ind_name.clone(),
+                num_params: params.len(),
+                num_fields: ctor.fields.len(),
+            });
+        }
+
+        self.declare_recursor(&ind_name, rec_name, &implicit_params, &indices, &checked);
+        self.lctx = saved_lctx;
+        Some(ind_name)
+    }
+
+    fn check_constructor(
+        &mut self,
+        ind_display: &str,
+        ind_name: &QualifiedName,
+        params: &[Binder],
+        ind_level: &Level,
+        ctor: &SyntaxConstructor,
+    ) -> Option<CheckedConstructor> {
+        let ctor_display = format!("{}.{}", ind_display, ctor.name);
+        let mut fields = Vec::new();
+        let mut levels = Vec::new();
+        for binder in &ctor.binders {
+            let (binder, level) = self.elaborate_binder(binder);
+            fields.push(binder);
+            levels.push(level);
+        }
+
+        let type_ = match &ctor.type_ {
+            Some(type_) => {
+                let (type_, level) = self.elaborate_type(type_);
+                levels.push(level);
+                unify::instantiate_mvars(self, &type_)
+            }
+            None => self.mk_inductive_app(ind_name, params, &[]),
+        };
+        let (opened, result) = self.open_pi(&type_, "a");
+        fields.extend(opened);
+        for (_, _, field_type) in &fields {
+            self.unify_self_levels(field_type, ind_name);
+        }
+        self.unify_self_levels(&result, ind_name);
+
+        let args = result.app_args();
+        let well_formed = result.app_head().const_name() == Some(ind_name)
+            && args.len() >= params.len()
+            && params
+                .iter()
+                .zip(&args)
+                .all(|((fvar, ..), arg)| **arg == Term::FVar(fvar.clone()));
+        let indices: Vec<Term> = args.iter().skip(params.len()).map(|arg| (*arg).clone()).collect();
+        let expected_indices = match self.env.lookup(ind_name) {
+            Some(Declaration::Inductive { num_indices, .. }) => *num_indices,
+            _ => 0,
+        };
+        if !well_formed || indices.len() != expected_indices {
+            self.report(ElabErrorKind::InvalidConstructorType {
+                name: ctor_display,
+                inductive: ind_display.into(),
+                found: result,
+            });
+            return None;
+        }
+        if indices.iter().any(|index| mentions(index, ind_name)) {
+            self.report(ElabErrorKind::NonPositiveOccurrence {
+                name: ctor_display,
+                inductive: ind_display.into(),
+            });
+            return None;
+        }
+
+        let too_large = levels.iter().any(|level| {
+            let level = unify::instantiate_mvars_level(self, level);
+            !level.has_mvar() && !ind_level.geq(&level)
+        });
+        if too_large {
+            self.report(ElabErrorKind::UniverseTooLarge {
+                name: ctor_display,
+                inductive: ind_display.into(),
+            });
+            return None;
+        }
+
+        let mut recursive = Vec::new();
+        for (field, (_, _, field_type)) in fields.iter().enumerate() {
+            if !mentions(field_type, ind_name) {
+                continue;
+            }
+            match self.check_positive(field_type, ind_name, params) {
+                Some((binders, indices)) => recursive.push(RecursiveField {
+                    field,
+                    binders,
+                    indices,
+                }),
+                None => {
+                    self.report(ElabErrorKind::NonPositiveOccurrence {
+                        name: ctor_display,
+                        inductive: ind_display.into(),
+                    });
+                    return None;
+                }
+            }
+        }
+
+        Some(CheckedConstructor {
+            name: QualifiedName::User(self.gen_.fresh(ctor_display)),
+            fields,
+            indices,
+            recursive,
+        })
+    }
+
+    /// Strict positivity: the inductive may only appear as the result of a
+    /// field's telescope, applied to the same parameters.
+    fn check_positive(
+        &mut self,
+        field_type: &Term,
+        ind_name: &QualifiedName,
+        params: &[Binder],
+    ) -> Option<(Vec<Binder>, Vec<Term>)> {
+        let (binders, result) = self.open_pi(field_type, "y");
+        if binders.iter().any(|(_, _, ty)| mentions(ty, ind_name)) {
+            return None;
+        }
+        if result.app_head().const_name() != Some(ind_name) {
+            return None;
+        }
+        let args = result.app_args();
+        if args.len() < params.len()
+            || !params
+                .iter()
+                .zip(&args)
+                .all(|((fvar, ..), arg)| **arg == Term::FVar(fvar.clone()))
+        {
+            return None;
+        }
+        let indices: Vec<Term> = args[params.len()..].iter().map(|arg| (*arg).clone()).collect();
+        if indices.iter().any(|index| mentions(index, ind_name)) {
+            return None;
+        }
+        Some((binders, indices))
+    }
+
+    /// `I.rec.{v, us} {params} (motive : (is : Is) -> I params is -> Type v)
+    ///   (minors...) {is} (t : I params is) : motive is t`
+    fn declare_recursor(
+        &mut self,
+        ind_name: &QualifiedName,
+        rec_name: QualifiedName,
+        params: &[Binder],
+        indices: &[Binder],
+        ctors: &[CheckedConstructor],
+    ) {
+        let param_terms: Vec<Term> = params.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+        let index_terms: Vec<Term> = indices.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+
+        let mut motive_level = String::from("v");
+        while self.level_params.contains(&motive_level) {
+            motive_level.push('\'');
+        }
+        let mut level_params = alloc::vec![motive_level.clone()];
+        level_params.extend(self.level_params.iter().cloned());
+        let rec_levels: Vec<Level> = level_params.iter().cloned().map(Level::Param).collect();
+
+        let major_type = self.mk_inductive_app(ind_name, params, &index_terms);
+        let (major, major_term) = self.fresh_internal_fvar("t".into(), major_type.clone());
+        let motive_type = subst::mk_pi(
+            indices,
+            subst::mk_pi(
+                &[(major.clone(), BinderInfo::Explicit, major_type.clone())],
+                Term::Sort(Level::Param(motive_level)),
+            ),
+        );
+        let (motive, motive_term) = self.fresh_internal_fvar("motive".into(), motive_type.clone());
+
+        let mut minors = Vec::new();
+        for ctor in ctors {
+            let field_terms: Vec<Term> = ctor.fields.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+            let mut ihs = Vec::new();
+            for rec in &ctor.recursive {
+                let ys: Vec<Term> = rec.binders.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+                let applied = Term::mk_apps(field_terms[rec.field].clone(), ys);
+                let target = Term::mk_apps(motive_term.clone(), rec.indices.iter().cloned().chain([applied]));
+                let ih_type = subst::mk_pi(&rec.binders, target);
+                let (ih, _) = self.fresh_internal_fvar("ih".into(), ih_type.clone());
+                ihs.push((ih, BinderInfo::Explicit, ih_type));
+            }
+            let intro = Term::mk_apps(Term::Const(ctor.name.clone(), self.inductive_levels()), param_terms.iter().cloned().chain(field_terms));
+            let target = Term::mk_apps(motive_term.clone(), ctor.indices.iter().cloned().chain([intro]));
+            let minor_type = subst::mk_pi(&ctor.fields, subst::mk_pi(&ihs, target));
+            let (minor, _) = self.fresh_internal_fvar("minor".into(), minor_type.clone());
+            minors.push((minor, BinderInfo::Explicit, minor_type));
+        }
+
+        let mut binders: Vec<Binder> = params.to_vec();
+        binders.push((motive.clone(), BinderInfo::Explicit, motive_type));
+        binders.extend(minors.iter().cloned());
+        let prefix_len = binders.len();
+        binders.extend(
+            indices
+                .iter()
+                .map(|(fvar, _, ty)| (fvar.clone(), BinderInfo::Implicit, ty.clone())),
+        );
+        binders.push((major, BinderInfo::Explicit, major_type));
+        let result = Term::mk_apps(motive_term.clone(), index_terms.into_iter().chain([major_term]));
+        let type_ = subst::mk_pi(&binders, result);
+
+        let prefix = &binders[..prefix_len];
+        let prefix_terms: Vec<Term> = prefix.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+        let mut rules = Vec::new();
+        for (ctor, (minor, ..)) in ctors.iter().zip(&minors) {
+            let field_terms: Vec<Term> = ctor.fields.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+            let ih_values = ctor.recursive.iter().map(|rec| {
+                let ys: Vec<Term> = rec.binders.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+                let applied = Term::mk_apps(field_terms[rec.field].clone(), ys);
+                let call = Term::mk_apps(
+                    Term::Const(rec_name.clone(), rec_levels.clone()),
+                    prefix_terms.iter().cloned().chain(rec.indices.iter().cloned()).chain([applied]),
+                );
+                subst::mk_lambda(&rec.binders, call)
+            });
+            let body = Term::mk_apps(Term::FVar(minor.clone()), field_terms.iter().cloned().chain(ih_values));
+            let mut rhs_binders = prefix.to_vec();
+            rhs_binders.extend(ctor.fields.iter().cloned());
+            rules.push(RecursorRule {
+                constructor: ctor.name.clone(),
+                num_fields: ctor.fields.len(),
+                rhs: self.instantiate_for_env(&subst::mk_lambda(&rhs_binders, body)),
+            });
+        }
+        let type_ = self.instantiate_for_env(&type_);
+
+        self.declare(Declaration::Recursor {
+            name: rec_name,
+            level_params,
+            type_,
+            inductive: ind_name.clone(),
+            num_params: params.len(),
+            num_indices: indices.len(),
+            num_minors: minors.len(),
+            rules,
+        });
+    }
+
+    fn mk_inductive_app(&self, ind_name: &QualifiedName, params: &[Binder], indices: &[Term]) -> Term {
+        Term::mk_apps(
+            Term::Const(ind_name.clone(), self.inductive_levels()),
+            params
+                .iter()
+                .map(|(fvar, ..)| Term::FVar(fvar.clone()))
+                .chain(indices.iter().cloned()),
+        )
+    }
+
+    /// References to the inductive inside its own constructors are at the
+    /// levels it is being declared at.
+    fn unify_self_levels(&mut self, term: &Term, ind_name: &QualifiedName) {
+        match term {
+            Term::Const(name, levels) if name == ind_name => {
+                for (level, param) in levels.iter().zip(self.inductive_levels()) {
+                    self.unify_levels(level, &param);
+                }
+            }
+            Term::BVar(_) | Term::FVar(_) | Term::MVar(_) | Term::Sort(_) | Term::Lit(_) | Term::Const(..) => (),
+            Term::App(a, b) | Term::Lam(_, _, a, b) | Term::Pi(_, _, a, b) | Term::Sigma(_, _, a, b) => {
+                self.unify_self_levels(a, ind_name);
+                self.unify_self_levels(b, ind_name);
+            }
+            Term::Let(_, a, b, c) | Term::Pair(a, b, c) => {
+                self.unify_self_levels(a, ind_name);
+                self.unify_self_levels(b, ind_name);
+                self.unify_self_levels(c, ind_name);
+            }
+            Term::Fst(p) | Term::Snd(p) => self.unify_self_levels(p, ind_name),
+        }
+    }
+
+    /// The universe parameters of the command, as the levels an inductive
+    /// being declared refers to itself and its constructors at.
+    pub(super) fn inductive_levels(&self) -> Vec<Level> {
+        self.level_params.iter().cloned().map(Level::Param).collect()
+    }
+
+    /// Instantiates the leading Pi binders of `type_` with fresh locals,
+    /// named after their binders or, for binders without a name, `name`.
+    pub(super) fn open_pi(&mut self, type_: &Term, name: &str) -> (Vec<Binder>, Term) {
+        self.open_pi_n(type_, name, usize::MAX)
+    }
+
+    /// Like `open_pi`, but stops after `count` binders.
+    pub(super) fn open_pi_n(&mut self, type_: &Term, name: &str, count: usize) -> (Vec<Binder>, Term) {
+        let mut binders = Vec::new();
+        let mut current = type_.clone();
+        while binders.len() < count {
+            let Term::Pi(hint, info, domain, body) = reduce::whnf(self, &current) else {
+                break;
+            };
+            let name = hint.0.unwrap_or_else(|| String::from(name));
+            let (fvar, fvar_term) = self.fresh_internal_fvar(name, *domain.clone());
+            binders.push((fvar, info, *domain));
+            current = subst::instantiate(&body, &fvar_term);
+        }
+        (binders, current)
+    }
+
+    /// Opens a minor premise of `inductive`'s recursor into its fields and
+    /// one induction hypothesis per recursive field. The target is left
+    /// unreduced, so a motive returning a Pi type is not opened as well.
+    pub(super) fn open_minor(
+        &mut self,
+        minor_type: &Term,
+        inductive: &QualifiedName,
+        num_fields: usize,
+    ) -> (Vec<Binder>, Vec<Binder>, Term) {
+        let (fields, rest) = self.open_pi_n(minor_type, "x", num_fields);
+        let num_ihs = fields
+            .iter()
+            .filter(|(_, _, field_type)| {
+                let (_, result) = self.open_pi(field_type, "y");
+                result.app_head().const_name() == Some(inductive)
+            })
+            .count();
+        let (ihs, target) = self.open_pi_n(&rest, "ih", num_ihs);
+        (fields, ihs, target)
+    }
+}
+
+fn mentions(term: &Term, name: &QualifiedName) -> bool {
+    match term {
+        Term::Const(n, _) => n == name,
+        Term::BVar(_) | Term::FVar(_) | Term::MVar(_) | Term::Sort(_) | Term::Lit(_) => false,
+        Term::App(a, b) | Term::Lam(_, _, a, b) | Term::Pi(_, _, a, b) | Term::Sigma(_, _, a, b) => {
+            mentions(a, name) || mentions(b, name)
+        }
+        Term::Let(_, a, b, c) | Term::Pair(a, b, c) => {
+            mentions(a, name) || mentions(b, name) || mentions(c, name)
+        }
+        Term::Fst(p) | Term::Snd(p) => mentions(p, name),
+    }
+}
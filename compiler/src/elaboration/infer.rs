@@ -0,0 +1,86 @@
+//! Type inference for terms the elaborator has already built, used where a
+//! term is assembled directly rather than elaborated from syntax, such as the
+//! motive of a recursor application.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use crate::{
+    elaboration::{ElabState, reduce, subst},
+    module::prim::{PRIM_NAT, PRIM_STRING},
+    spine::{Level, Literal, Term},
+};
+
+impl ElabState {
+    /// The type of a well-typed `term`. Ill-typed terms have already been
+    /// reported and get a fresh metavariable.
+    pub(super) fn infer_type(&mut self, term: &Term) -> Term {
+        match term {
+            Term::FVar(fvar) => match self.lctx.lookup(fvar.clone()) {
+                Some(decl) => decl.type_.clone(),
+                None => self.erroneous_term(),
+            },
+            Term::MVar(mvar) => match self.mctx.lookup_decl(mvar.clone()) {
+                Some(decl) => decl.type_.clone(),
+                None => self.erroneous_term(),
+            },
+            Term::Const(name, levels) => match self.env.lookup(name) {
+                Some(decl) => decl.instantiate_type(levels),
+                None => self.erroneous_term(),
+            },
+            Term::Sort(level) => Term::Sort(level.clone().succ()),
+            Term::Lit(Literal::Nat(_)) => Term::Const(PRIM_NAT, Vec::new()),
+            Term::Lit(Literal::Str(_)) => Term::Const(PRIM_STRING, Vec::new()),
+            Term::App(f, arg) => {
+                let f_type = self.infer_type(f);
+                match reduce::whnf(self, &f_type) {
+                    Term::Pi(_, _, _, body) => subst::instantiate(&body, arg),
+                    _ => self.erroneous_term(),
+                }
+            }
+            Term::Lam(name, info, domain, body) => {
+                let saved_lctx = self.lctx.clone();
+                let (fvar, x) = self.fresh_internal_fvar(String::from("x"), (**domain).clone());
+                let body_type = self.infer_type(&subst::instantiate(body, &x));
+                self.lctx = saved_lctx;
+                Term::Pi(
+                    name.clone(),
+                    info.clone(),
+                    domain.clone(),
+                    Box::new(subst::abstract_fvar(&body_type, fvar)),
+                )
+            }
+            Term::Pi(_, _, domain, body) | Term::Sigma(_, _, domain, body) => {
+                let domain_level = self.infer_level(domain);
+                let saved_lctx = self.lctx.clone();
+                let (_, x) = self.fresh_internal_fvar(String::from("x"), (**domain).clone());
+                let body_level = self.infer_level(&subst::instantiate(body, &x));
+                self.lctx = saved_lctx;
+                Term::Sort(Level::max(domain_level, body_level))
+            }
+            Term::Let(_, _, value, body) => self.infer_type(&subst::instantiate(body, value)),
+            Term::Pair(sigma, ..) => (**sigma).clone(),
+            Term::Fst(pair) | Term::Snd(pair) => {
+                let pair_type = self.infer_type(pair);
+                match (term, reduce::whnf(self, &pair_type)) {
+                    (Term::Fst(_), Term::Sigma(_, _, fst_type, _)) => *fst_type,
+                    (_, Term::Sigma(_, _, _, snd_type)) => subst::instantiate(&snd_type, &Term::Fst(pair.clone())),
+                    _ => self.erroneous_term(),
+                }
+            }
+            Term::BVar(_) => self.erroneous_term(),
+        }
+    }
+
+    /// The universe `type_` lives in.
+    pub(super) fn infer_level(&mut self, type_: &Term) -> Level {
+        let sort = self.infer_type(type_);
+        match reduce::whnf(self, &sort) {
+            Term::Sort(level) => level,
+            other => {
+                let level = self.fresh_level_mvar();
+                self.unify(&other, &Term::Sort(level.clone()));
+                level
+            }
+        }
+    }
+}
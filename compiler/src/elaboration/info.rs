@@ -0,0 +1,105 @@
+//! What elaboration finds out about the syntax of a module, recorded for
+//! editors: the term and type each expression elaborated to, the locals in
+//! scope at each name and where each declaration comes from. Nothing is
+//! recorded unless `ElabState::info` is set.
+
+use alloc::{collections::btree_map::BTreeMap, string::String, vec::Vec};
+
+use crate::{
+    elaboration::{Declaration, ElabState, unify},
+    module::name::QualifiedName,
+    spine::Term,
+    syntax::Span,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Info {
+    /// In the order elaboration finished with them, so an expression comes
+    /// after the expressions inside it.
+    pub terms: Vec<TermInfo>,
+    pub scopes: Vec<ScopeInfo>,
+    /// The command each declaration of the module was declared by.
+    pub decls: BTreeMap<QualifiedName, Span>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TermInfo {
+    pub span: Span,
+    pub term: Term,
+    /// May mention metavariables that were assigned later on.
+    pub type_: Term,
+}
+
+/// The locals in scope at a name, innermost last.
+#[derive(Debug, Clone)]
+pub struct ScopeInfo {
+    pub span: Span,
+    pub locals: Vec<String>,
+}
+
+impl ElabState {
+    pub(super) fn record_term(&mut self, span: Span, term: &Term, type_: &Term) {
+        if let Some(info) = &mut self.info {
+            info.terms.push(TermInfo {
+                span,
+                term: term.clone(),
+                type_: type_.clone(),
+            });
+        }
+    }
+
+    pub(super) fn record_scope(&mut self, span: Span) {
+        if let Some(info) = &mut self.info {
+            let locals = self.lctx.decls.iter().filter_map(|decl| decl.fvar.display_name.clone()).collect();
+            info.scopes.push(ScopeInfo { span, locals });
+        }
+    }
+
+    /// Adds `decl` to the environment, declared by the command being
+    /// elaborated.
+    pub(super) fn declare(&mut self, decl: Declaration) {
+        if let Some(info) = &mut self.info {
+            info.decls.insert(decl.name().clone(), self.span);
+        }
+        self.env.insert(decl);
+    }
+
+    /// The innermost expression around byte `offset`, with its term and type
+    /// as far as elaboration solved them.
+    pub fn term_at(&self, offset: usize) -> Option<(Span, Term, Term)> {
+        let info = self.info.as_ref()?;
+        let found = innermost(&info.terms, |term| term.span, offset)?;
+        Some((
+            found.span,
+            unify::instantiate_mvars(self, &found.term),
+            unify::instantiate_mvars(self, &found.type_),
+        ))
+    }
+
+    /// The names of the locals in scope at the name around byte `offset`.
+    pub fn locals_at(&self, offset: usize) -> Vec<String> {
+        let Some(scope) = self.info.as_ref().and_then(|info| innermost(&info.scopes, |scope| scope.span, offset)) else {
+            return Vec::new();
+        };
+        let mut locals = Vec::new();
+        for local in scope.locals.iter().rev() {
+            if !locals.contains(local) {
+                locals.push(local.clone());
+            }
+        }
+        locals
+    }
+
+    pub fn decl_span(&self, name: &QualifiedName) -> Option<Span> {
+        self.info.as_ref()?.decls.get(name).copied()
+    }
+}
+
+/// The first of the shortest spans that contain `offset`, counting the
+/// offset just past the end, where a cursor after a name is.
+fn innermost<T>(items: &[T], span: impl Fn(&T) -> Span, offset: usize) -> Option<&T> {
+    items
+        .iter()
+        .filter(|item| span(item).start <= offset && offset <= span(item).end)
+        .min_by_key(|item| span(item).end - span(item).start)
+}
@@ -0,0 +1,270 @@
+//! Classes, instances and instance resolution.
+//!
+//! Arguments for instance-implicit binders are metavariables that are solved
+//! by searching the local context and the declared instances for a term of
+//! the class type. Instances may have instance-implicit binders of their own,
+//! which become subgoals. Answers to goals are tabled for the duration of a
+//! search, and a goal that recurs while it is being solved fails instead of
+//! looping.
+
+use alloc::{string::ToString, vec::Vec};
+
+use crate::{
+    elaboration::{ElabState, err::ElabErrorKind, recursion::DefSyntax, reduce, subst, unify},
+    module::{name::QualifiedName, unique::Unique},
+    spine::{BinderInfo, Term},
+    syntax::tree::{SyntaxBinder, SyntaxExpr},
+};
+
+/// Subgoals nested deeper than this fail, so that instances producing ever
+/// larger goals terminate.
+const MAX_DEPTH: usize = 32;
+
+#[derive(Default)]
+struct Resolution {
+    /// Answers found so far, keyed by goal with unassigned metavariables
+    /// numbered in order of occurrence. Only answers without metavariables
+    /// are tabled.
+    answers: Vec<(Term, Term)>,
+    /// Keys of the goals currently being solved.
+    stack: Vec<Term>,
+    /// The instances tried for the top-level goal.
+    tried: Vec<QualifiedName>,
+    /// The first goal found to depend on itself.
+    cycle: Option<Term>,
+}
+
+impl ElabState {
+    pub(super) fn elaborate_class(&mut self, name: &str, binders: &[SyntaxBinder], fields: &[SyntaxBinder]) {
+        if let Some(class) = self.elaborate_struct(name, binders, fields, BinderInfo::InstanceImplicit) {
+            self.env.classes.insert(class);
+        }
+    }
+
+    pub(super) fn elaborate_instance(
+        &mut self,
+        name: &str,
+        binders: &[SyntaxBinder],
+        type_: &SyntaxExpr,
+        body: &SyntaxExpr,
+    ) {
+        let names = self.elaborate_defs(&[DefSyntax {
+            name,
+            binders,
+            return_type: type_,
+            body,
+        }]);
+        for name in names {
+            let type_ = self.env.lookup(&name).unwrap().type_().clone();
+            let saved_lctx = self.lctx.clone();
+            let (_, class_type) = self.open_pi(&type_, "x");
+            self.lctx = saved_lctx;
+            if self.class_of(&class_type).is_some() {
+                self.env.instances.push(name);
+            } else {
+                self.report(ElabErrorKind::NotAClass(class_type));
+            }
+        }
+    }
+
+    /// The class `type_` is an instance of, if any.
+    fn class_of(&self, type_: &Term) -> Option<QualifiedName> {
+        let name = reduce::whnf(self, type_).app_head().const_name()?.clone();
+        self.env.classes.contains(&name).then_some(name)
+    }
+
+    /// A metavariable for an implicit argument of type `type_`. Instance
+    /// arguments are synthesized right away when their type is known, and
+    /// otherwise once the surrounding definition has been elaborated.
+    pub(super) fn fresh_implicit_arg(&mut self, info: &BinderInfo, type_: Term) -> Term {
+        let mvar = self.fresh_mvar(type_.clone());
+        if *info == BinderInfo::InstanceImplicit
+            && let Term::MVar(u) = &mvar
+        {
+            let type_ = unify::instantiate_mvars(self, &type_);
+            if has_unassigned_mvar(self, &type_) {
+                self.pending_instances.push(u.clone());
+            } else {
+                self.synthesize_instance(u.clone());
+            }
+        }
+        mvar
+    }
+
+    /// Synthesizes the pending instance arguments, first those whose type
+    /// has become known, then the rest as they are, which may solve
+    /// metavariables in their types.
+    pub(super) fn synthesize_pending_instances(&mut self) {
+        loop {
+            let pending = core::mem::take(&mut self.pending_instances);
+            let (ready, waiting): (Vec<Unique>, Vec<Unique>) = pending.into_iter().partition(|mvar| {
+                let type_ = self.mctx.lookup_decl(mvar.clone()).map(|decl| decl.type_.clone());
+                type_.is_some_and(|type_| {
+                    let type_ = unify::instantiate_mvars(self, &type_);
+                    !has_unassigned_mvar(self, &type_)
+                })
+            });
+            self.pending_instances = waiting;
+            if ready.is_empty() {
+                break;
+            }
+            for mvar in ready {
+                self.synthesize_instance(mvar);
+            }
+        }
+        for mvar in core::mem::take(&mut self.pending_instances) {
+            self.synthesize_instance(mvar);
+        }
+    }
+
+    fn synthesize_instance(&mut self, mvar: Unique) {
+        if self.mctx.is_assigned(mvar.clone()) {
+            return;
+        }
+        let Some(decl) = self.mctx.lookup_decl(mvar.clone()).cloned() else {
+            return;
+        };
+        let saved_lctx = core::mem::replace(&mut self.lctx, decl.lctx);
+        let goal = unify::instantiate_mvars(self, &decl.type_);
+        let mut resolution = Resolution::default();
+        let answer = self.resolve(&goal, &mut resolution, 0);
+        self.lctx = saved_lctx;
+
+        match answer {
+            Some(answer) => {
+                self.unify(&Term::MVar(mvar), &answer);
+            }
+            None => {
+                let kind = ElabErrorKind::NoInstance {
+                    goal: unify::instantiate_mvars(self, &goal),
+                    tried: resolution
+                        .tried
+                        .iter()
+                        .map(|name| name.display().unwrap_or_default().to_string())
+                        .collect(),
+                    cycle: resolution.cycle,
+                };
+                self.report_at(decl.span, kind);
+            }
+        }
+    }
+
+    fn resolve(&mut self, goal: &Term, resolution: &mut Resolution, depth: usize) -> Option<Term> {
+        let goal = unify::instantiate_mvars(self, goal);
+        let key = goal_key(self, &goal);
+        if let Some((_, answer)) = resolution.answers.iter().find(|(k, _)| *k == key) {
+            let answer = answer.clone();
+            let answer_type = self.infer_type(&answer);
+            return self.unify(&goal, &answer_type).then_some(answer);
+        }
+        if resolution.stack.contains(&key) {
+            resolution.cycle.get_or_insert(goal);
+            return None;
+        }
+        let class = self.class_of(&goal)?;
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        resolution.stack.push(key.clone());
+        let mut candidates: Vec<(Option<QualifiedName>, Term, Term)> = Vec::new();
+        for decl in self.lctx.decls.iter().rev() {
+            if self.class_of(&decl.type_).as_ref() == Some(&class) {
+                candidates.push((None, Term::FVar(decl.fvar.clone()), decl.type_.clone()));
+            }
+        }
+        for name in self.env.instances.clone().iter().rev() {
+            let type_ = self.env.lookup(name).unwrap().type_().clone();
+            let saved_lctx = self.lctx.clone();
+            let (_, instance_class) = self.open_pi(&type_, "x");
+            self.lctx = saved_lctx;
+            if self.class_of(&instance_class).as_ref() == Some(&class) {
+                let (term, type_) = self.mk_const(name).unwrap();
+                candidates.push((Some(name.clone()), term, type_));
+            }
+        }
+
+        let mut answer = None;
+        for (name, term, type_) in candidates {
+            if depth == 0
+                && let Some(name) = name
+            {
+                resolution.tried.push(name);
+            }
+            let snapshot = self.snapshot();
+            if let Some(found) = self.try_instance(term, type_, &goal, resolution, depth) {
+                answer = Some(found);
+                break;
+            }
+            self.restore(snapshot);
+        }
+        resolution.stack.pop();
+
+        if let Some(answer) = &answer
+            && !has_unassigned_mvar(self, answer)
+        {
+            resolution.answers.push((key, answer.clone()));
+        }
+        answer
+    }
+
+    /// Applies a candidate to metavariables for its arguments, unifies its
+    /// type with the goal and resolves its instance arguments as subgoals.
+    /// A candidate whose unification with the goal gets postponed doesn't
+    /// match.
+    fn try_instance(
+        &mut self,
+        mut term: Term,
+        mut type_: Term,
+        goal: &Term,
+        resolution: &mut Resolution,
+        depth: usize,
+    ) -> Option<Term> {
+        let mut subgoals = Vec::new();
+        while let Term::Pi(_, info, domain, body) = reduce::whnf(self, &type_) {
+            let arg = self.fresh_mvar((*domain).clone());
+            if info == BinderInfo::InstanceImplicit {
+                subgoals.push((arg.clone(), *domain));
+            }
+            type_ = subst::instantiate(&body, &arg);
+            term = Term::mk_app(term, arg);
+        }
+        let postponed = self.postponed.len();
+        if !self.unify(&type_, goal) || self.postponed.len() > postponed {
+            return None;
+        }
+        for (arg, subgoal) in subgoals {
+            if let Term::MVar(u) = &arg
+                && self.mctx.is_assigned(u.clone())
+            {
+                continue;
+            }
+            let answer = self.resolve(&subgoal, resolution, depth + 1)?;
+            if !self.unify(&arg, &answer) {
+                return None;
+            }
+        }
+        Some(unify::instantiate_mvars(self, &term))
+    }
+}
+
+fn has_unassigned_mvar(state: &ElabState, term: &Term) -> bool {
+    let mut found = false;
+    subst::for_each_mvar(term, &mut |mvar| found |= !state.mctx.is_assigned(mvar.clone()));
+    found
+}
+
+/// `goal` with its unassigned metavariables renamed in order of occurrence,
+/// so that goals differing only in their metavariables share a key.
+fn goal_key(state: &ElabState, goal: &Term) -> Term {
+    let mut seen: Vec<Unique> = Vec::new();
+    subst::for_each_mvar(goal, &mut |mvar| {
+        if !state.mctx.is_assigned(mvar.clone()) && !seen.contains(mvar) {
+            seen.push(mvar.clone());
+        }
+    });
+    subst::replace_mvars(goal, &|mvar| {
+        let i = seen.iter().position(|seen| seen == mvar)?;
+        Some(Term::MVar(Unique::unnamed(i, mvar.module_id.clone())))
+    })
+}
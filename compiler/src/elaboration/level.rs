@@ -0,0 +1,147 @@
+//! Universe levels: written levels are elaborated against the parameters of
+//! the current command, and equations between levels that unification cannot
+//! decide on the spot are collected and solved once the command is done.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    elaboration::{ElabState, err::ElabErrorKind, unify},
+    module::name::QualifiedName,
+    spine::{Level, Term},
+    syntax::tree::SyntaxLevel,
+};
+
+impl ElabState {
+    /// Lowercase names in a level are universe parameters of the command
+    /// being elaborated, bound in order of first use.
+    pub(super) fn elaborate_level(&mut self, level: &SyntaxLevel) -> Level {
+        match level {
+            SyntaxLevel::Nat(n) => (0..*n).fold(Level::Zero, |level, _| level.succ()),
+            SyntaxLevel::Param(name) => {
+                if !self.level_params.contains(name) {
+                    self.level_params.push(name.clone());
+                }
+                Level::Param(name.clone())
+            }
+            SyntaxLevel::Add(level, n) => (0..*n).fold(self.elaborate_level(level), |level, _| level.succ()),
+            SyntaxLevel::Max(a, b) => Level::max(self.elaborate_level(a), self.elaborate_level(b)),
+            SyntaxLevel::IMax(a, b) => Level::IMax(Box::new(self.elaborate_level(a)), Box::new(self.elaborate_level(b))),
+        }
+    }
+
+    pub fn fresh_level_mvar(&mut self) -> Level {
+        self.mctx.fresh_level_mvar(&mut self.gen_)
+    }
+
+    /// A reference to a global constant, with a fresh level metavariable for
+    /// each of its universe parameters, and its type at those levels.
+    pub(super) fn mk_const(&mut self, name: &QualifiedName) -> Option<(Term, Term)> {
+        let num_params = self.env.lookup(name)?.level_params().len();
+        let levels: Vec<Level> = (0..num_params).map(|_| self.fresh_level_mvar()).collect();
+        let type_ = self.env.lookup(name)?.instantiate_type(&levels);
+        Some((Term::Const(name.clone(), levels), type_))
+    }
+
+    /// Solves `a = b` when one side is a metavariable or both have the same
+    /// shape. Equations that still mention metavariables otherwise, such as
+    /// `max ?u ?v = 1`, are postponed until the end of the command.
+    pub fn unify_levels(&mut self, a: &Level, b: &Level) -> bool {
+        let a = unify::instantiate_mvars_level(self, a).normalize();
+        let b = unify::instantiate_mvars_level(self, b).normalize();
+        if a == b {
+            return true;
+        }
+        match (&a, &b) {
+            (Level::MVar(u), other) | (other, Level::MVar(u)) if !unify::occurs_in_level(u.clone(), other) => {
+                self.mctx.assign_level(u.clone(), other.clone());
+                true
+            }
+            (Level::Succ(a), Level::Succ(b)) => self.unify_levels(a, b),
+            _ if a.has_mvar() || b.has_mvar() => {
+                self.level_constraints.push((a, b));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Retries postponed level equations until no more progress is made,
+    /// then sets the remaining metavariables to zero and reports the
+    /// equations that still fail.
+    pub(super) fn solve_level_constraints(&mut self) {
+        loop {
+            let pending = core::mem::take(&mut self.level_constraints);
+            let num_pending = pending.len();
+            let num_assigned = self.mctx.level_assignments.len();
+            for (a, b) in pending {
+                if !self.unify_levels(&a, &b) {
+                    self.report_level_mismatch(&a, &b);
+                }
+            }
+            let stuck = self.level_constraints.len() == num_pending
+                && self.mctx.level_assignments.len() == num_assigned;
+            if self.level_constraints.is_empty() || stuck {
+                break;
+            }
+        }
+
+        for (a, b) in core::mem::take(&mut self.level_constraints) {
+            self.default_level_mvars(&a);
+            self.default_level_mvars(&b);
+            if !self.unify_levels(&a, &b) {
+                self.report_level_mismatch(&a, &b);
+            }
+        }
+    }
+
+    /// Instantiates the assigned metavariables in a term about to be added
+    /// to the environment, setting its unassigned level metavariables to zero.
+    pub(super) fn instantiate_for_env(&mut self, term: &Term) -> Term {
+        let term = unify::instantiate_mvars(self, term);
+        self.default_level_mvars_in(&term);
+        unify::instantiate_mvars(self, &term)
+    }
+
+    /// Assigns zero to the unassigned metavariables in `level`.
+    fn default_level_mvars(&mut self, level: &Level) {
+        match unify::instantiate_mvars_level(self, level) {
+            Level::MVar(u) => self.mctx.assign_level(u, Level::Zero),
+            Level::Succ(l) => self.default_level_mvars(&l),
+            Level::Max(a, b) | Level::IMax(a, b) => {
+                self.default_level_mvars(&a);
+                self.default_level_mvars(&b);
+            }
+            Level::Zero | Level::Param(_) => (),
+        }
+    }
+
+    /// Assigns zero to the unassigned level metavariables in `term`.
+    fn default_level_mvars_in(&mut self, term: &Term) {
+        match term {
+            Term::Sort(level) => self.default_level_mvars(level),
+            Term::Const(_, levels) => {
+                for level in levels {
+                    self.default_level_mvars(level);
+                }
+            }
+            Term::BVar(_) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) => (),
+            Term::App(a, b) | Term::Lam(_, _, a, b) | Term::Pi(_, _, a, b) | Term::Sigma(_, _, a, b) => {
+                self.default_level_mvars_in(a);
+                self.default_level_mvars_in(b);
+            }
+            Term::Let(_, a, b, c) | Term::Pair(a, b, c) => {
+                self.default_level_mvars_in(a);
+                self.default_level_mvars_in(b);
+                self.default_level_mvars_in(c);
+            }
+            Term::Fst(p) | Term::Snd(p) => self.default_level_mvars_in(p),
+        }
+    }
+
+    fn report_level_mismatch(&mut self, a: &Level, b: &Level) {
+        self.report(ElabErrorKind::LevelMismatch {
+            expected: unify::instantiate_mvars_level(self, a).normalize(),
+            found: unify::instantiate_mvars_level(self, b).normalize(),
+        });
+    }
+}
@@ -0,0 +1,377 @@
+use alloc::{format, string::String, vec, vec::Vec};
+
+use crate::{
+    elaboration::{Binder, Declaration, ElabState, err::ElabErrorKind, reduce, subst, unify},
+    module::{name::QualifiedName, unique::Unique},
+    spine::{BinderInfo, Level, Term},
+    syntax::tree::{SyntaxExpr, SyntaxMatchArm, SyntaxPattern, SyntaxPatternKind},
+};
+
+/// One row of the clause matrix: a pattern per remaining column, the
+/// variables bound so far and the arm it came from.
+#[derive(Clone)]
+struct Row<'s> {
+    patterns: Vec<SyntaxPattern>,
+    bindings: Vec<(String, Term, Term)>,
+    arm: usize,
+    body: &'s SyntaxExpr,
+}
+
+struct Column {
+    term: Term,
+    type_: Term,
+}
+
+pub(super) struct InductiveInfo {
+    pub(super) name: QualifiedName,
+    pub(super) levels: Vec<Level>,
+    pub(super) params: Vec<Term>,
+    pub(super) indices: Vec<Term>,
+    pub(super) constructors: Vec<QualifiedName>,
+    pub(super) recursor: QualifiedName,
+}
+
+impl InductiveInfo {
+    /// The recursor's levels for a motive into `Type motive_level`.
+    pub(super) fn recursor_levels(&self, motive_level: Level) -> Vec<Level> {
+        let mut levels = vec![motive_level];
+        levels.extend(self.levels.iter().cloned());
+        levels
+    }
+}
+
+struct MatchState {
+    used: Vec<bool>,
+    invalid: Vec<bool>,
+}
+
+impl ElabState {
+    /// Compiles `match` into nested recursor applications, splitting on the
+    /// first column that some row matches against a constructor.
+    pub(super) fn elaborate_match(
+        &mut self,
+        scrutinee: &SyntaxExpr,
+        arms: &[SyntaxMatchArm],
+        expected_type: Option<&Term>,
+    ) -> (Term, Term) {
+        let (scrutinee, scrutinee_type) = self.elaborate_term_inner(scrutinee);
+        let expected = match expected_type {
+            Some(expected) => expected.clone(),
+            None => {
+                let sort = Term::Sort(Level::Zero);
+                self.fresh_mvar(sort)
+            }
+        };
+
+        let saved_lctx = self.lctx.clone();
+        let (column, bound) = match &scrutinee {
+            Term::FVar(_) => (scrutinee.clone(), None),
+            _ => {
+                let (fvar, fvar_term) = self.fresh_internal_fvar("x".into(), scrutinee_type.clone());
+                (fvar_term, Some(fvar))
+            }
+        };
+
+        let rows = arms
+            .iter()
+            .enumerate()
+            .map(|(arm, SyntaxMatchArm { pattern, body, .. })| Row {
+                patterns: vec![pattern.clone()],
+                bindings: Vec::new(),
+                arm,
+                body,
+            })
+            .collect();
+        let mut state = MatchState {
+            used: vec![false; arms.len()],
+            invalid: vec![false; arms.len()],
+        };
+        let columns = vec![Column {
+            term: column,
+            type_: scrutinee_type.clone(),
+        }];
+        let tree = self.compile_match(columns, rows, &expected, None, &mut state);
+        self.lctx = saved_lctx;
+
+        for (arm, (used, invalid)) in state.used.iter().zip(&state.invalid).enumerate() {
+            if !used && !invalid {
+                self.report_at(arms[arm].pattern.span, ElabErrorKind::RedundantMatchArm(arm + 1));
+            }
+        }
+
+        let term = match bound {
+            Some(fvar) => Term::mk_app(
+                subst::mk_lambda(&[(fvar, BinderInfo::Explicit, scrutinee_type)], tree),
+                scrutinee,
+            ),
+            None => tree,
+        };
+        (term, expected)
+    }
+
+    fn compile_match(
+        &mut self,
+        columns: Vec<Column>,
+        mut rows: Vec<Row>,
+        expected: &Term,
+        case: Option<&QualifiedName>,
+        state: &mut MatchState,
+    ) -> Term {
+        loop {
+            let Some(first) = rows.first() else {
+                // A column of a type without constructors needs no arms.
+                let empty = columns.iter().enumerate().find_map(|(i, column)| {
+                    let info = self.inductive_info(&column.type_)?;
+                    info.constructors.is_empty().then_some((i, info))
+                });
+                if let Some((split, info)) = empty {
+                    return self.split_column(columns, rows, split, info, expected, state);
+                }
+                self.report(ElabErrorKind::NonExhaustiveMatch(
+                    case.and_then(|name| name.display()).map(String::from),
+                ));
+                return self.erroneous_term();
+            };
+
+            let split = columns.iter().zip(&first.patterns).position(|(column, pattern)| {
+                self.constructor_pattern(pattern, &column.type_).is_some()
+                    || matches!(pattern.kind, SyntaxPatternKind::Constructor(..))
+            });
+            let Some(split) = split else {
+                let row = rows.swap_remove(0);
+                state.used[row.arm] = true;
+                return self.elaborate_arm(&columns, row, expected);
+            };
+
+            match self.inductive_info(&columns[split].type_) {
+                Some(info) => return self.split_column(columns, rows, split, info, expected, state),
+                None => {
+                    let row = rows.remove(0);
+                    if !state.invalid[row.arm] {
+                        state.invalid[row.arm] = true;
+                        let type_ = reduce::whnf(self, &columns[split].type_);
+                        self.report_at(row.patterns[split].span, ElabErrorKind::NotAnInductive(type_));
+                    }
+                }
+            }
+        }
+    }
+
+    fn split_column(
+        &mut self,
+        columns: Vec<Column>,
+        rows: Vec<Row>,
+        split: usize,
+        info: InductiveInfo,
+        expected: &Term,
+        state: &mut MatchState,
+    ) -> Term {
+        let saved_lctx = self.lctx.clone();
+        let scrutinee = columns[split].term.clone();
+        let motive = self.match_motive(&info, &scrutinee, expected);
+        let rec_levels = info.recursor_levels(self.infer_level(expected));
+
+        let mut rec_type = self.env.lookup(&info.recursor).map(|decl| decl.instantiate_type(&rec_levels)).unwrap();
+        for arg in info.params.iter().chain([&motive]) {
+            rec_type = self.instantiate_pi(&rec_type, arg);
+        }
+
+        let mut minors = Vec::new();
+        for ctor in &info.constructors {
+            let num_fields = match self.env.lookup(ctor) {
+                Some(Declaration::Intro { num_fields, .. }) => *num_fields,
+                _ => 0,
+            };
+            let Term::Pi(_, _, minor_type, _) = reduce::whnf(self, &rec_type) else {
+                break;
+            };
+
+            let case_lctx = self.lctx.clone();
+            let (fields, ihs, target) = self.open_minor(&minor_type, &info.name, num_fields);
+            self.record_fields(&scrutinee, &info.name, &fields);
+            let binders: Vec<Binder> = fields.iter().chain(&ihs).cloned().collect();
+            let fields = &fields[..];
+            let field_terms: Vec<Term> = fields.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+            let ctor_app = Term::mk_apps(
+                Term::Const(ctor.clone(), info.levels.clone()),
+                info.params.iter().cloned().chain(field_terms.iter().cloned()),
+            );
+
+            let mut case_rows = Vec::new();
+            for row in &rows {
+                let pattern = &row.patterns[split];
+                let mut row = row.clone();
+                let args = match self.constructor_pattern(pattern, &columns[split].type_) {
+                    Some(resolved) if resolved == *ctor => match &pattern.kind {
+                        SyntaxPatternKind::Constructor(_, args) => args.clone(),
+                        _ => Vec::new(),
+                    },
+                    Some(_) => continue,
+                    None => {
+                        if let SyntaxPatternKind::Constructor(name, _) = &pattern.kind {
+                            if !state.invalid[row.arm] {
+                                state.invalid[row.arm] = true;
+                                self.report_at(pattern.span, ElabErrorKind::UnknownPatternConstructor(name.clone()));
+                            }
+                            continue;
+                        }
+                        if let SyntaxPatternKind::Var(name) = &pattern.kind {
+                            row.bindings.push((name.clone(), ctor_app.clone(), columns[split].type_.clone()));
+                        }
+                        vec![SyntaxPattern::new(SyntaxPatternKind::Wildcard, pattern.span); fields.len()]
+                    }
+                };
+                if args.len() != fields.len() {
+                    if !state.invalid[row.arm] {
+                        state.invalid[row.arm] = true;
+                        self.report_at(pattern.span, ElabErrorKind::PatternArity {
+                            constructor: ctor.display().unwrap_or_default().into(),
+                            expected: fields.len(),
+                            found: args.len(),
+                        });
+                    }
+                    continue;
+                }
+                row.patterns.splice(split..=split, args);
+                case_rows.push(row);
+            }
+
+            let mut case_columns = Vec::new();
+            for (i, column) in columns.iter().enumerate() {
+                if i == split {
+                    case_columns.extend(fields.iter().map(|(fvar, _, type_)| Column {
+                        term: Term::FVar(fvar.clone()),
+                        type_: type_.clone(),
+                    }));
+                } else {
+                    case_columns.push(Column {
+                        term: column.term.clone(),
+                        type_: column.type_.clone(),
+                    });
+                }
+            }
+
+            let case_expected = reduce::whnf(self, &target);
+            let tree = self.compile_match(case_columns, case_rows, &case_expected, Some(ctor), state);
+            let minor = subst::mk_lambda(&binders, tree);
+            rec_type = self.instantiate_pi(&rec_type, &minor);
+            minors.push(minor);
+            self.lctx = case_lctx;
+        }
+
+        self.lctx = saved_lctx;
+        Term::mk_apps(
+            Term::Const(info.recursor, rec_levels),
+            info.params
+                .into_iter()
+                .chain([motive])
+                .chain(minors)
+                .chain(info.indices)
+                .chain([scrutinee]),
+        )
+    }
+
+    /// `λ is (t : I params is). expected`, abstracting the scrutinee and any
+    /// of its indices that are distinct local variables.
+    fn match_motive(&mut self, info: &InductiveInfo, scrutinee: &Term, expected: &Term) -> Term {
+        let mut ind_type = self.env.lookup(&info.name).map(|decl| decl.instantiate_type(&info.levels)).unwrap();
+        for param in &info.params {
+            ind_type = self.instantiate_pi(&ind_type, param);
+        }
+        let (index_binders, _) = self.open_pi(&ind_type, "i");
+        let index_terms: Vec<Term> = index_binders.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+        let major_type = Term::mk_apps(
+            Term::Const(info.name.clone(), info.levels.clone()),
+            info.params.iter().cloned().chain(index_terms.iter().cloned()),
+        );
+        let (major, major_term) = self.fresh_internal_fvar("t".into(), major_type.clone());
+
+        let mut body = unify::instantiate_mvars(self, expected);
+        let mut abstracted: Vec<Unique> = Vec::new();
+        for (index, index_term) in info.indices.iter().zip(&index_terms) {
+            if let Term::FVar(fvar) = index
+                && !abstracted.contains(fvar)
+            {
+                body = subst::replace_fvar(&body, fvar.clone(), index_term);
+                abstracted.push(fvar.clone());
+            }
+        }
+        if let Term::FVar(fvar) = scrutinee {
+            body = subst::replace_fvar(&body, fvar.clone(), &major_term);
+        }
+
+        let mut binders: Vec<Binder> = index_binders;
+        binders.push((major, BinderInfo::Explicit, major_type));
+        subst::mk_lambda(&binders, body)
+    }
+
+    fn elaborate_arm(&mut self, columns: &[Column], row: Row, expected: &Term) -> Term {
+        let saved_lctx = self.lctx.clone();
+        let mut bindings = row.bindings;
+        for (column, pattern) in columns.iter().zip(&row.patterns) {
+            if let SyntaxPatternKind::Var(name) = &pattern.kind {
+                bindings.push((name.clone(), column.term.clone(), column.type_.clone()));
+            }
+        }
+
+        let mut lets = Vec::new();
+        for (name, value, type_) in bindings {
+            let fvar = self.lctx.push_let(name, type_, value.clone(), &mut self.gen_);
+            self.record_alias(&fvar, &value);
+            lets.push((fvar, value));
+        }
+        let mut body = self.elaborate_term(row.body, Some(expected));
+        for (fvar, value) in lets.into_iter().rev() {
+            body = subst::replace_fvar(&body, fvar, &value);
+        }
+        self.lctx = saved_lctx;
+        body
+    }
+
+    /// The constructor a pattern refers to when matched against `type_`.
+    fn constructor_pattern(&self, pattern: &SyntaxPattern, type_: &Term) -> Option<QualifiedName> {
+        let name = match &pattern.kind {
+            SyntaxPatternKind::Wildcard => return None,
+            SyntaxPatternKind::Var(name) | SyntaxPatternKind::Constructor(name, _) => name,
+        };
+        let info = self.inductive_info(type_)?;
+        let suffix = format!(".{}", name);
+        info.constructors.into_iter().find(|ctor| {
+            ctor.display()
+                .is_some_and(|display| display == name || display.ends_with(&suffix))
+        })
+    }
+
+    pub(super) fn inductive_info(&self, type_: &Term) -> Option<InductiveInfo> {
+        let type_ = reduce::whnf(self, type_);
+        let Term::Const(name, levels) = type_.app_head() else {
+            return None;
+        };
+        let Some(Declaration::Inductive {
+            num_params,
+            constructors,
+            recursor,
+            ..
+        }) = self.env.lookup(name)
+        else {
+            return None;
+        };
+        let args: Vec<Term> = type_.app_args().into_iter().cloned().collect();
+        let (params, indices) = args.split_at((*num_params).min(args.len()));
+        Some(InductiveInfo {
+            name: name.clone(),
+            levels: levels.clone(),
+            params: params.to_vec(),
+            indices: indices.to_vec(),
+            constructors: constructors.clone(),
+            recursor: recursor.clone(),
+        })
+    }
+
+    pub(super) fn instantiate_pi(&self, type_: &Term, arg: &Term) -> Term {
+        match reduce::whnf(self, type_) {
+            Term::Pi(_, _, _, body) => subst::instantiate(&body, arg),
+            other => other,
+        }
+    }
+}
@@ -1,40 +1,74 @@
 pub mod ctx;
+pub mod delab;
 pub mod err;
+pub mod incremental;
+pub mod inductive;
+pub mod infer;
+pub mod info;
+pub mod instance;
+pub mod level;
+pub mod matching;
+pub mod namespace;
+pub mod nbe;
+pub mod pattern;
+pub mod postpone;
+pub mod recursion;
 pub mod reduce;
+pub mod structure;
 pub mod subst;
 pub mod unify;
+pub mod unsolved;
 
 use alloc::{
     boxed::Box,
-    collections::btree_map::BTreeMap,
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet},
     string::{String, ToString},
     vec::Vec,
 };
-use api::println;
 
 use crate::{
     elaboration::{
         ctx::{LocalContext, MetavarContext},
-        err::ElabError,
-    }, log::pretty::pretty_term, module::{
-        ModuleId, name::QualifiedName, prim::{PRIM_ARRAY, PRIM_ARRAY_CONS, PRIM_ARRAY_NIL, PRIM_FIN, PRIM_NAT, PRIM_STRING}, unique::{Unique, UniqueGen}
-    }, spine::{BinderInfo, Level, Literal, Term}, syntax::tree::{SyntaxBinder, SyntaxExpr}
+        err::{ElabError, ElabErrorKind},
+    }, kernel, module::{
+        ModuleId, name::{Name, QualifiedName}, prim::{PRIM_ARRAY, PRIM_ARRAY_CONS, PRIM_ARRAY_NIL, PRIM_FIN, PRIM_IO, PRIM_IO_BIND, PRIM_IO_PRINT, PRIM_IO_PRINTLN, PRIM_IO_PURE, PRIM_IO_READ_LINE, PRIM_NAT, PRIM_NAT_BINOPS, PRIM_NAT_TO_STR, PRIM_STRING, PRIM_STR_APPEND, PRIM_UNIT, PRIM_UNIT_UNIT}, unique::{Unique, UniqueGen}
+    }, spine::{BinderInfo, BinderName, Level, Literal, Term}, syntax::{Span, tree::{SyntaxBinder, SyntaxExpr, SyntaxExprKind}}
 };
 
+pub type Binder = (Unique, BinderInfo, Term);
+
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub module_id: ModuleId,
     pub decls: BTreeMap<QualifiedName, Declaration>,
+    /// The declaration each full name refers to.
+    pub names: BTreeMap<Name, QualifiedName>,
+    /// Structures declared with `class`.
+    pub classes: BTreeSet<QualifiedName>,
+    /// Instances in declaration order; resolution tries the latest first.
+    pub instances: Vec<QualifiedName>,
 }
 
 impl Environment {
+    pub fn empty(module_id: ModuleId) -> Self {
+        Self {
+            module_id,
+            decls: BTreeMap::new(),
+            names: BTreeMap::new(),
+            classes: BTreeSet::new(),
+            instances: Vec::new(),
+        }
+    }
+
     // todo: remove
     pub fn pre_loaded(module_id: ModuleId) -> Self {
         let mut decls = BTreeMap::new();
+        let nat = || Box::new(Term::Const(PRIM_NAT, Vec::new()));
         decls.insert(
             PRIM_NAT,
             Declaration::Constructor {
                 name: PRIM_NAT,
+                level_params: Vec::new(),
                 type_: Term::Sort(Level::Zero),
             },
         );
@@ -42,6 +76,7 @@ impl Environment {
             PRIM_STRING,
             Declaration::Constructor {
                 name: PRIM_STRING,
+                level_params: Vec::new(),
                 type_: Term::Sort(Level::Zero),
             },
         );
@@ -49,44 +84,138 @@ impl Environment {
             PRIM_FIN,
             Declaration::Constructor {
                 name: PRIM_FIN,
-                type_: Term::Pi(
-                    BinderInfo::Explicit,
-                    Box::new(Term::Const(PRIM_NAT)),
-                    Box::new(Term::Sort(Level::Zero)),
-                ),
+                level_params: Vec::new(),
+                type_: Term::Pi(BinderName::default(), BinderInfo::Explicit, nat(), Box::new(Term::Sort(Level::Zero))),
             },
         );
+        let u = || Box::new(Term::Sort(Level::Param("u".into())));
         decls.insert(
             PRIM_ARRAY,
             Declaration::Constructor {
                 name: PRIM_ARRAY,
+                level_params: alloc::vec!["u".into()],
                 type_: Term::Pi(
+                    BinderName::default(),
                     BinderInfo::Explicit,
-                    Box::new(Term::Sort(Level::Zero)),
-                    Box::new(Term::Pi(
-                        BinderInfo::Explicit,
-                        Box::new(Term::Const(PRIM_NAT)),
-                        Box::new(Term::Sort(Level::Zero)),
-                    )),
+                    u(),
+                    Box::new(Term::Pi(BinderName::default(), BinderInfo::Explicit, nat(), u())),
                 ),
             },
         );
-        Self { module_id, decls }
+        for name in PRIM_NAT_BINOPS {
+            decls.insert(
+                name.clone(),
+                Declaration::Constructor {
+                    name,
+                    level_params: Vec::new(),
+                    type_: Term::Pi(
+                        BinderName::default(),
+                        BinderInfo::Explicit,
+                        nat(),
+                        Box::new(Term::Pi(BinderName::default(), BinderInfo::Explicit, nat(), nat())),
+                    ),
+                },
+            );
+        }
+        // What programs run by the interpreter can do.
+        let type_ = || Box::new(Term::Sort(Level::Zero));
+        let str_ = || Box::new(Term::Const(PRIM_STRING, Vec::new()));
+        let io = |a: Term| Box::new(Term::mk_app(Term::Const(PRIM_IO, Vec::new()), a));
+        let io_unit = || io(Term::Const(PRIM_UNIT, Vec::new()));
+        let arrow = |a: Box<Term>, b: Box<Term>| Term::Pi(BinderName::default(), BinderInfo::Explicit, a, b);
+        let implicit =
+            |name: &str, body: Term| Term::Pi(BinderName(Some(name.into())), BinderInfo::Implicit, type_(), Box::new(body));
+        let prims = [
+            (PRIM_UNIT, Term::Sort(Level::Zero)),
+            (PRIM_UNIT_UNIT, Term::Const(PRIM_UNIT, Vec::new())),
+            (PRIM_IO, arrow(type_(), type_())),
+            // `{A : Type} -> A -> IO A`
+            (PRIM_IO_PURE, implicit("A", arrow(Box::new(Term::BVar(0)), io(Term::BVar(1))))),
+            // `{A : Type} -> {B : Type} -> IO A -> (A -> IO B) -> IO B`
+            (
+                PRIM_IO_BIND,
+                implicit("A", implicit("B", arrow(
+                    io(Term::BVar(1)),
+                    Box::new(arrow(Box::new(arrow(Box::new(Term::BVar(2)), io(Term::BVar(2)))), io(Term::BVar(2)))),
+                ))),
+            ),
+            (PRIM_IO_PRINT, arrow(str_(), io_unit())),
+            (PRIM_IO_PRINTLN, arrow(str_(), io_unit())),
+            (PRIM_IO_READ_LINE, *io(Term::Const(PRIM_STRING, Vec::new()))),
+            (PRIM_NAT_TO_STR, arrow(nat(), str_())),
+            (PRIM_STR_APPEND, arrow(str_(), Box::new(arrow(str_(), str_())))),
+        ];
+        for (name, type_) in prims {
+            decls.insert(
+                name.clone(),
+                Declaration::Constructor {
+                    name,
+                    level_params: Vec::new(),
+                    type_,
+                },
+            );
+        }
+        let names = decls.keys().filter_map(|name| Some((name.path()?, name.clone()))).collect();
+        Self {
+            module_id,
+            decls,
+            names,
+            classes: BTreeSet::new(),
+            instances: Vec::new(),
+        }
+    }
+
+    /// Adds `decl`, which shadows any earlier declaration of the same name.
+    pub fn insert(&mut self, decl: Declaration) {
+        let name = decl.name().clone();
+        if let Some(path) = name.path() {
+            self.names.insert(path, name.clone());
+        }
+        self.decls.insert(name, decl);
     }
 
     pub fn lookup(&self, name: &QualifiedName) -> Option<&Declaration> {
         self.decls.get(name)
     }
 
+    pub fn lookup_name(&self, name: &Name) -> Option<&QualifiedName> {
+        self.names.get(name)
+    }
+
+    /// Looks a declaration up by its full dotted name.
     pub fn lookup_string(&self, name: &str) -> Option<&Declaration> {
-        self.decls.values().find(|decl| match decl {
-            Declaration::Definition {
-                name: decl_name, ..
-            } => decl_name.display() == Some(name),
-            Declaration::Constructor {
-                name: decl_name, ..
-            } => decl_name.display() == Some(name),
-        })
+        self.lookup(self.lookup_name(&Name::parse(name))?)
+    }
+
+    /// Whether some declaration's name is inside namespace `namespace`.
+    pub fn has_namespace(&self, namespace: &Name) -> bool {
+        self.names
+            .range(namespace.clone()..)
+            .find(|(name, _)| *name != namespace)
+            .is_some_and(|(name, _)| name.starts_with(namespace))
+    }
+
+    /// Adds the declarations of an imported module. Returns the names both
+    /// environments declare differently, which keep their existing meaning.
+    pub fn merge(&mut self, other: &Environment) -> Vec<Name> {
+        let mut conflicts = Vec::new();
+        for (name, decl) in &other.decls {
+            if let Some(path) = name.path()
+                && let Some(existing) = self.names.get(&path)
+                && existing != name
+            {
+                conflicts.push(path);
+                continue;
+            }
+            self.insert(decl.clone());
+        }
+        self.classes.extend(other.classes.iter().cloned());
+        for instance in &other.instances {
+            if !self.instances.contains(instance) {
+                self.instances.push(instance.clone());
+            }
+        }
+        conflicts
     }
 }
 
@@ -94,27 +223,88 @@ impl Environment {
 pub enum Declaration {
     Definition {
         name: QualifiedName,
+        level_params: Vec<String>,
         type_: Term,
         value: Term,
     },
     Constructor {
         name: QualifiedName,
+        level_params: Vec<String>,
+        type_: Term,
+    },
+    Inductive {
+        name: QualifiedName,
+        level_params: Vec<String>,
         type_: Term,
+        num_params: usize,
+        num_indices: usize,
+        constructors: Vec<QualifiedName>,
+        recursor: QualifiedName,
+    },
+    Intro {
+        name: QualifiedName,
+        level_params: Vec<String>,
+        type_: Term,
+        inductive: QualifiedName,
+        num_params: usize,
+        num_fields: usize,
+    },
+    /// The first level parameter is the universe the motive eliminates
+    /// into, followed by the inductive's.
+    Recursor {
+        name: QualifiedName,
+        level_params: Vec<String>,
+        type_: Term,
+        inductive: QualifiedName,
+        num_params: usize,
+        num_indices: usize,
+        num_minors: usize,
+        rules: Vec<RecursorRule>,
     },
 }
 
+/// `rhs` abstracts over the recursor's parameters, motive and minor premises
+/// followed by the constructor's fields.
+#[derive(Debug, Clone)]
+pub struct RecursorRule {
+    pub constructor: QualifiedName,
+    pub num_fields: usize,
+    pub rhs: Term,
+}
+
 impl Declaration {
     pub fn name(&self) -> &QualifiedName {
         match self {
             Declaration::Definition { name, .. } => name,
             Declaration::Constructor { name, .. } => name,
+            Declaration::Inductive { name, .. } => name,
+            Declaration::Intro { name, .. } => name,
+            Declaration::Recursor { name, .. } => name,
+        }
+    }
+
+    pub fn level_params(&self) -> &[String] {
+        match self {
+            Declaration::Definition { level_params, .. } => level_params,
+            Declaration::Constructor { level_params, .. } => level_params,
+            Declaration::Inductive { level_params, .. } => level_params,
+            Declaration::Intro { level_params, .. } => level_params,
+            Declaration::Recursor { level_params, .. } => level_params,
         }
     }
 
+    /// The declaration's type with its level parameters replaced by `levels`.
+    pub fn instantiate_type(&self, levels: &[Level]) -> Term {
+        subst::instantiate_level_params(self.type_(), self.level_params(), levels)
+    }
+
     pub fn type_(&self) -> &Term {
         match self {
             Declaration::Definition { type_, .. } => type_,
             Declaration::Constructor { type_, .. } => type_,
+            Declaration::Inductive { type_, .. } => type_,
+            Declaration::Intro { type_, .. } => type_,
+            Declaration::Recursor { type_, .. } => type_,
         }
     }
 }
@@ -125,19 +315,53 @@ pub struct ElabState {
     pub mctx: MetavarContext,
     pub lctx: LocalContext,
     pub errors: Vec<ElabError>,
+    pub evaluations: Vec<Term>,
+    /// Universe parameters bound so far by the command being elaborated.
+    pub level_params: Vec<String>,
+    /// Level equations postponed by unification, see `unify_levels`.
+    pub level_constraints: Vec<(Level, Level)>,
+    /// Instance arguments waiting for their type to be known.
+    pub pending_instances: Vec<Unique>,
+    /// Problems waiting for metavariables to be assigned.
+    pub postponed: Vec<postpone::Postponed>,
+    /// The syntax being elaborated, where errors are reported.
+    pub span: Span,
+    /// The namespace declarations are currently made in.
+    pub namespace: Name,
+    /// Namespaces opened by `open`, searched when resolving names.
+    pub opens: Vec<Name>,
+    /// The environments of the modules this one may import, provided by the
+    /// driver. `None` marks a module whose import would form a cycle.
+    pub imports: BTreeMap<ModuleId, Option<Environment>>,
+    /// What elaboration finds out about the syntax, recorded only when set.
+    pub info: Option<info::Info>,
+    /// Set while the bodies of a block of definitions are elaborated.
+    pub recursive_calls: Option<recursion::RecursiveCalls>,
+    /// The global names resolved so far and what each resolved to, recorded
+    /// only when set.
+    pub resolutions: Option<Vec<(String, QualifiedName)>>,
 }
 
 impl ElabState {
     pub fn new(module: ModuleId) -> Self {
         Self {
-            env: Environment {
-                module_id: module.clone(),
-                decls: BTreeMap::new(),
-            },
+            env: Environment::empty(module.clone()),
             gen_: UniqueGen::new(module),
             mctx: MetavarContext::new(),
             lctx: LocalContext { decls: Vec::new() },
             errors: Vec::new(),
+            evaluations: Vec::new(),
+            level_params: Vec::new(),
+            level_constraints: Vec::new(),
+            pending_instances: Vec::new(),
+            postponed: Vec::new(),
+            span: Span::empty(0, 0),
+            namespace: Name::root(),
+            opens: Vec::new(),
+            imports: BTreeMap::new(),
+            info: None,
+            recursive_calls: None,
+            resolutions: None,
         }
     }
 
@@ -148,7 +372,12 @@ impl ElabState {
     }
 
     pub fn fresh_mvar(&mut self, type_: Term) -> Term {
-        let u = self.mctx.fresh_mvar(type_, &self.lctx, &mut self.gen_);
+        let u = self.mctx.fresh_mvar(type_, &self.lctx, self.span, &mut self.gen_);
+        Term::MVar(u)
+    }
+
+    pub fn fresh_named_mvar(&mut self, name: String, type_: Term) -> Term {
+        let u = self.mctx.fresh_named_mvar(name, type_, &self.lctx, self.span, &mut self.gen_);
         Term::MVar(u)
     }
 
@@ -157,26 +386,157 @@ impl ElabState {
         (u.clone(), Term::FVar(u))
     }
 
+    /// A local of elaboration's own, which names in the source can't refer
+    /// to even when they match its display name.
+    pub fn fresh_internal_fvar(&mut self, name: String, type_: Term) -> (Unique, Term) {
+        let u = self.lctx.push_internal(name, type_, &mut self.gen_);
+        (u.clone(), Term::FVar(u))
+    }
+
+    /// Admits a definition once the kernel has re-checked it. When `checked`
+    /// is false its elaboration already reported errors, and it is admitted
+    /// as is so later uses don't cascade into more errors.
+    fn add_definition(&mut self, name: QualifiedName, type_: &Term, value: &Term, checked: bool) {
+        self.solve_pending();
+        self.solve_level_constraints();
+        let checked = !self.report_unsolved(&[type_, value], checked) && checked;
+        let decl = Declaration::Definition {
+            name: name.clone(),
+            level_params: self.level_params.clone(),
+            type_: self.instantiate_for_env(type_),
+            value: self.instantiate_for_env(value),
+        };
+        if checked && let Err(error) = kernel::check_declaration(&self.env, &mut self.gen_, &decl) {
+            self.report(ElabErrorKind::KernelRejected {
+                name: name.display().unwrap_or_default().to_string(),
+                error,
+            });
+            return;
+        }
+        self.declare(decl);
+    }
+
+    pub(super) fn report(&mut self, kind: ElabErrorKind) {
+        self.report_at(self.span, kind);
+    }
+
+    pub(super) fn report_at(&mut self, span: Span, kind: ElabErrorKind) {
+        self.errors.push(ElabError { kind, span });
+    }
+
+    /// Runs `f` with errors reported at `span`.
+    fn at_span<T>(&mut self, span: Span, f: impl FnOnce(&mut Self) -> T) -> T {
+        let saved_span = core::mem::replace(&mut self.span, span);
+        let result = f(self);
+        self.span = saved_span;
+        result
+    }
+
     fn erroneous_term(&mut self) -> Term {
         Term::MVar(self.gen_.fresh_unnamed())
     }
 
+    pub fn elaborate_root(&mut self, root: &SyntaxExpr) -> Result<(), ElabError> {
+        match &root.kind {
+            SyntaxExprKind::Root(commands) => {
+                for cmd in commands {
+                    self.elaborate_command(cmd);
+                }
+                Ok(())
+            }
+            _ => Err(ElabError {
+                kind: ElabErrorKind::ExpectedRoot,
+                span: root.span,
+            }),
+        }
+    }
+
     pub fn elaborate_command(&mut self, cmd: &SyntaxExpr) {
-        match cmd {
-            SyntaxExpr::Def {
+        self.level_params.clear();
+        self.level_constraints.clear();
+        self.postponed.clear();
+        self.span = cmd.span;
+        match &cmd.kind {
+            SyntaxExprKind::Def {
                 name,
                 binders,
                 return_type,
                 body,
             } => self.elaborate_def(name, binders, return_type, body),
-            SyntaxExpr::Eval(expr) => {
+            SyntaxExprKind::Eval(expr) => {
+                let errors_before = self.errors.len();
                 let term = self.elaborate_term(expr, None);
-                println!("Evaluated term: {:#?}", pretty_term(&term));
+                self.solve_pending();
+                self.report_unsolved(&[&term], self.errors.len() == errors_before);
+                self.evaluations.push(nbe::normalize(self, &term));
             }
+            SyntaxExprKind::Inductive {
+                name,
+                binders,
+                type_,
+                constructors,
+            } => {
+                self.elaborate_inductive(name, binders, type_.as_deref(), constructors);
+            }
+            SyntaxExprKind::Struct {
+                name,
+                binders,
+                fields,
+            } => {
+                self.elaborate_struct(name, binders, fields, BinderInfo::Explicit);
+            }
+            SyntaxExprKind::Class {
+                name,
+                binders,
+                fields,
+            } => self.elaborate_class(name, binders, fields),
+            SyntaxExprKind::Instance {
+                name,
+                binders,
+                type_,
+                body,
+            } => self.elaborate_instance(name, binders, type_, body),
+            SyntaxExprKind::Mutual(defs) => {
+                let defs: Vec<_> = defs
+                    .iter()
+                    .filter_map(|def| match &def.kind {
+                        SyntaxExprKind::Def {
+                            name,
+                            binders,
+                            return_type,
+                            body,
+                        } => Some(recursion::DefSyntax {
+                            name,
+                            binders,
+                            return_type,
+                            body,
+                        }),
+                        _ => None,
+                    })
+                    .collect();
+                self.elaborate_defs(&defs);
+            }
+            SyntaxExprKind::Import(module) => self.import_module(module),
+            SyntaxExprKind::Namespace { name, commands } => self.elaborate_namespace(name, commands),
+            SyntaxExprKind::Open(paths) => self.open_namespaces(paths),
             _ => (),
         }
     }
 
+    /// Elaborates a lone expression, such as one typed at the REPL, and
+    /// returns it with its type.
+    pub fn elaborate_expr(&mut self, syntax: &SyntaxExpr) -> (Term, Term) {
+        self.level_params.clear();
+        self.level_constraints.clear();
+        self.postponed.clear();
+        self.span = syntax.span;
+        let errors_before = self.errors.len();
+        let (term, type_) = self.elaborate_term_inner(syntax);
+        self.solve_pending();
+        self.report_unsolved(&[&term], self.errors.len() == errors_before);
+        (unify::instantiate_mvars(self, &term), unify::instantiate_mvars(self, &type_))
+    }
+
     fn elaborate_def(
         &mut self,
         name: &str,
@@ -184,51 +544,92 @@ impl ElabState {
         return_type: &SyntaxExpr,
         body: &SyntaxExpr,
     ) {
-        let def_name = QualifiedName::User(self.gen_.fresh(name.to_string()));
-
-        let saved_lctx = self.lctx.clone();
-        let mut binder_fvars: Vec<(Unique, BinderInfo, Term)> = Vec::new();
+        self.elaborate_defs(&[recursion::DefSyntax {
+            name,
+            binders,
+            return_type,
+            body,
+        }]);
+    }
 
-        for binder in binders {
-            let (binder_name, binder_type_syntax, info) = match binder {
-                SyntaxBinder::Explicit(n, ty) => (n, ty, BinderInfo::Explicit),
-                SyntaxBinder::Implicit(n, ty) => (n, ty, BinderInfo::Implicit),
-                SyntaxBinder::Instance(n, ty) => (n, ty, BinderInfo::InstanceImplicit),
-            };
-            let elaborated_type = self.elaborate_term(binder_type_syntax, None);
-            let (fvar, _) = self.fresh_fvar(binder_name.clone(), elaborated_type.clone());
-            binder_fvars.push((fvar, info, elaborated_type));
-        }
-        let elaborated_return_type = self.elaborate_term(return_type, None);
-        let elaborated_body = self.elaborate_term(body, Some(&elaborated_return_type));
-
-        let mut pi_type = elaborated_return_type;
-        let mut value = elaborated_body;
-        for (fvar, info, ty) in binder_fvars.into_iter().rev() {
-            pi_type = subst::abstract_fvar(&pi_type, fvar.clone());
-            value = subst::abstract_fvar(&value, fvar);
-            
-            pi_type = Term::Pi(info, Box::new(ty), Box::new(pi_type));
-        }
+    /// Pushes a local for every binder; callers restore `lctx` afterwards.
+    fn elaborate_binders(&mut self, binders: &[SyntaxBinder]) -> Vec<Binder> {
+        binders
+            .iter()
+            .map(|binder| self.elaborate_binder(binder).0)
+            .collect()
+    }
 
-        self.env.decls.insert(
-            def_name.clone(),
-            Declaration::Definition {
-                name: def_name,
-                type_: pi_type,
-                value,
-            },
-        );
+    fn elaborate_binder(&mut self, binder: &SyntaxBinder) -> (Binder, Level) {
+        let (binder_name, binder_type_syntax, info) = binder_parts(binder);
+        let (elaborated_type, level) = self.elaborate_type(binder_type_syntax);
+        let (fvar, _) = self.fresh_fvar(binder_name.clone(), elaborated_type.clone());
+        ((fvar, info, elaborated_type), level)
+    }
 
-        self.lctx = saved_lctx;
+    fn elaborate_type(&mut self, syntax: &SyntaxExpr) -> (Term, Level) {
+        let (term, inferred_type) = self.elaborate_term_inner(syntax);
+        match reduce::whnf(self, &inferred_type) {
+            Term::Sort(level) => (term, level),
+            Term::MVar(_) => {
+                let level = self.fresh_level_mvar();
+                self.unify(&inferred_type, &Term::Sort(level.clone()));
+                (term, level)
+            }
+            other => {
+                self.report(ElabErrorKind::ExpectedType(other));
+                (term, Level::Zero)
+            }
+        }
     }
 
     fn elaborate_term(&mut self, syntax: &SyntaxExpr, expected_type: Option<&Term>) -> Term {
-        let (term, inferred_type) = self.elaborate_term_inner(syntax);
+        self.at_span(syntax.span, |this| {
+            let term = this.elaborate_term_here(syntax, expected_type);
+            if let Some(expected) = expected_type {
+                this.record_term(syntax.span, &term, expected);
+            }
+            term
+        })
+    }
+
+    fn elaborate_term_here(&mut self, syntax: &SyntaxExpr, expected_type: Option<&Term>) -> Term {
+        if let Some(expected) = expected_type
+            && let Some(mvar) = self.try_postpone_elaboration(syntax, expected)
+        {
+            return mvar;
+        }
+        let (mut term, mut inferred_type) = match (&syntax.kind, expected_type) {
+            (SyntaxExprKind::AnonymousConstructor(args), Some(expected)) => {
+                return self.elaborate_anonymous_constructor(args, expected);
+            }
+            (SyntaxExprKind::Hole, Some(expected)) => return self.fresh_mvar(expected.clone()),
+            (SyntaxExprKind::NamedHole(name), Some(expected)) => {
+                return self.fresh_named_mvar(name.clone(), expected.clone());
+            }
+            (SyntaxExprKind::Lambda { binders, body }, _) => {
+                self.elaborate_lambda(binders, body, expected_type)
+            }
+            (SyntaxExprKind::Tuple(items), _) => self.elaborate_tuple(items, expected_type),
+            (SyntaxExprKind::Match { scrutinee, arms }, _) => {
+                self.elaborate_match(scrutinee, arms, expected_type)
+            }
+            (
+                SyntaxExprKind::Let {
+                    name,
+                    type_ann,
+                    value,
+                    body,
+                },
+                _,
+            ) => self.elaborate_let(name, type_ann.as_deref(), value, body, expected_type),
+            _ => self.elaborate_term_inner(syntax),
+        };
 
         if let Some(expected) = expected_type {
+            (term, inferred_type) = self.instantiate_implicits(term, inferred_type, expected);
             if !self.unify(&inferred_type, expected) {
-                self.errors.push(ElabError::TypeMismatch {
+                self.report(ElabErrorKind::TypeMismatch {
                     expected: expected.clone(),
                     found: inferred_type,
                 });
@@ -238,83 +639,173 @@ impl ElabState {
         term
     }
 
+    /// Applies leading implicit arguments of `type_` to fresh metavariables,
+    /// unless the expected type is itself waiting for that implicit binder.
+    fn instantiate_implicits(&mut self, mut term: Term, mut type_: Term, expected: &Term) -> (Term, Term) {
+        let expected_info = match reduce::whnf(self, expected) {
+            Term::Pi(_, info, _, _) => Some(info),
+            _ => None,
+        };
+        loop {
+            match reduce::whnf(self, &type_) {
+                Term::Pi(_, info, param_ty, body_ty)
+                    if info != BinderInfo::Explicit && Some(&info) != expected_info.as_ref() =>
+                {
+                    let mvar = self.fresh_implicit_arg(&info, *param_ty);
+                    type_ = subst::instantiate(&body_ty, &mvar);
+                    term = Term::App(Box::new(term), Box::new(mvar));
+                }
+                _ => return (term, type_),
+            }
+        }
+    }
+
     fn elaborate_term_inner(&mut self, syntax: &SyntaxExpr) -> (Term, Term) {
-        match syntax {
-            SyntaxExpr::Var(name) => {
+        self.at_span(syntax.span, |this| {
+            let (term, type_) = this.elaborate_term_inner_here(syntax);
+            this.record_term(syntax.span, &term, &type_);
+            this.record_call(syntax.span, &term);
+            (term, type_)
+        })
+    }
+
+    fn elaborate_term_inner_here(&mut self, syntax: &SyntaxExpr) -> (Term, Term) {
+        match &syntax.kind {
+            SyntaxExprKind::Var(name) => {
+                self.record_scope(syntax.span);
                 if let Some(decl) = self.lctx.lookup_name(name) {
                     return (Term::FVar(decl.fvar.clone()), decl.type_.clone());
                 }
 
-                if let Some(decl) = self.env.lookup_string(name) {
-                    match decl {
-                        Declaration::Definition { type_, .. } => {
-                            return (Term::Const(decl.name().clone()), type_.clone());
-                        }
-                        _ => panic!(
-                            "unexpected non-definition declaration for variable {}",
-                            name
-                        ),
-                    }
+                if let Some(name) = self.resolve_global(name) {
+                    return self.mk_const(&name).unwrap();
                 }
 
-                self.errors.push(ElabError::UndefinedVariable(name.clone()));
+                self.report(ElabErrorKind::UndefinedVariable(name.clone()));
                 (self.erroneous_term(), self.erroneous_term())
             }
-            SyntaxExpr::Constructor(name) if name == "Type" => (
-                Term::Sort(Level::Zero),
-                Term::Sort(Level::Succ(Box::new(Level::Zero))),
-            ),
-            SyntaxExpr::Constructor(name) => {
-                if let Some(decl) = self.env.lookup_string(name) {
-                    match decl {
-                        Declaration::Constructor { type_, .. } => {
-                            return (Term::Const(decl.name().clone()), type_.clone());
-                        }
-                        _ => panic!(
-                            "unexpected non-constructor declaration for constructor {}",
-                            name
-                        ),
-                    }
+            SyntaxExprKind::Sort(level) => {
+                let level = match level {
+                    Some(level) => self.elaborate_level(level),
+                    None => Level::Zero,
+                };
+                (Term::Sort(level.clone()), Term::Sort(level.succ()))
+            }
+            SyntaxExprKind::Constructor(name) => {
+                if let Some(name) = self.resolve_global(name) {
+                    return self.mk_const(&name).unwrap();
+                }
+
+                self.report(ElabErrorKind::UndefinedConstructor(name.clone()));
+                (self.erroneous_term(), self.erroneous_term())
+            }
+            SyntaxExprKind::Proj(..) if syntax_path(syntax).is_some() => {
+                let path = syntax_path(syntax).unwrap();
+                if let Some(name) = self.resolve_global(&path) {
+                    return self.mk_const(&name).unwrap();
                 }
 
-                self.errors
-                    .push(ElabError::UndefinedConstructor(name.clone()));
+                self.report(ElabErrorKind::UndefinedConstructor(path));
                 (self.erroneous_term(), self.erroneous_term())
             }
-            SyntaxExpr::Lit(lit) => {
+            SyntaxExprKind::Proj(head, field) => self.elaborate_proj(head, field),
+            SyntaxExprKind::Lambda { binders, body } => self.elaborate_lambda(binders, body, None),
+            SyntaxExprKind::Tuple(items) => self.elaborate_tuple(items, None),
+            SyntaxExprKind::Match { scrutinee, arms } => self.elaborate_match(scrutinee, arms, None),
+            SyntaxExprKind::Let {
+                name,
+                type_ann,
+                value,
+                body,
+            } => self.elaborate_let(name, type_ann.as_deref(), value, body, None),
+            SyntaxExprKind::Hole => {
+                let level = self.fresh_level_mvar();
+                let type_ = self.fresh_mvar(Term::Sort(level));
+                (self.fresh_mvar(type_.clone()), type_)
+            }
+            SyntaxExprKind::NamedHole(name) => {
+                let level = self.fresh_level_mvar();
+                let type_ = self.fresh_mvar(Term::Sort(level));
+                (self.fresh_named_mvar(name.clone(), type_.clone()), type_)
+            }
+            SyntaxExprKind::AnonymousConstructor(_) => {
+                self.report(ElabErrorKind::AnonymousConstructorWithoutType);
+                (self.erroneous_term(), self.erroneous_term())
+            }
+            SyntaxExprKind::Arrow(domain, codomain) => {
+                let (domain, domain_level) = self.elaborate_type(domain);
+                let (codomain, codomain_level) = self.elaborate_type(codomain);
+                (
+                    Term::Pi(BinderName::default(), BinderInfo::Explicit, Box::new(domain), Box::new(codomain)),
+                    Term::Sort(Level::max(domain_level, codomain_level)),
+                )
+            }
+            SyntaxExprKind::Pi(binder, body) => {
+                let saved_lctx = self.lctx.clone();
+                let (binder, domain_level) = self.elaborate_binder(binder);
+                let (body, body_level) = self.elaborate_type(body);
+                self.lctx = saved_lctx;
+                (
+                    subst::mk_pi(&[binder], body),
+                    Term::Sort(Level::max(domain_level, body_level)),
+                )
+            }
+            SyntaxExprKind::Sigma(binder, body) => {
+                let saved_lctx = self.lctx.clone();
+                let ((fvar, info, domain), domain_level) = self.elaborate_binder(binder);
+                let (body, body_level) = self.elaborate_type(body);
+                self.lctx = saved_lctx;
+                (
+                    Term::Sigma(
+                        BinderName(fvar.display_name.clone()),
+                        info,
+                        Box::new(domain),
+                        Box::new(subst::abstract_fvar(&body, fvar)),
+                    ),
+                    Term::Sort(Level::max(domain_level, body_level)),
+                )
+            }
+            SyntaxExprKind::Lit(lit) => {
                 let ty = match lit {
-                    crate::spine::Literal::Nat(_) => Term::Const(PRIM_NAT),
-                    crate::spine::Literal::Str(_) => Term::Const(PRIM_STRING),
+                    crate::spine::Literal::Nat(_) => Term::Const(PRIM_NAT, Vec::new()),
+                    crate::spine::Literal::Str(_) => Term::Const(PRIM_STRING, Vec::new()),
                 };
                 (Term::Lit(lit.clone()), ty)
             }
-            SyntaxExpr::Array(elems) => {
-                let elem_type = if let Some(head) = elems.first() {
-                    let (_term, head_ty) = self.elaborate_term_inner(head);
-                    head_ty
-                } else {
-                    self.fresh_mvar(Term::Sort(Level::Zero))
+            SyntaxExprKind::Array(elems) => {
+                // The head is elaborated once: its type is the element type.
+                let (mut head, elem_type) = match elems.first() {
+                    Some(head) => {
+                        let (term, type_) = self.elaborate_term_inner(head);
+                        (Some(term), type_)
+                    }
+                    None => {
+                        let level = self.fresh_level_mvar();
+                        (None, self.fresh_mvar(Term::Sort(level)))
+                    }
                 };
                 let elems_len = elems.len() as u64;
+                let level = alloc::vec![self.infer_level(&elem_type)];
 
                 let array_type = Term::App(
                     Box::new(Term::App(
-                        Box::new(Term::Const(PRIM_ARRAY)),
+                        Box::new(Term::Const(PRIM_ARRAY, level.clone())),
                         Box::new(elem_type.clone()),
                     )),
                     Box::new(Term::Lit(Literal::Nat(elems_len))),
                 );
-                let mut result = Term::Const(PRIM_ARRAY_NIL);
+                let mut result = Term::mk_app(Term::Const(PRIM_ARRAY_NIL, level.clone()), elem_type.clone());
                 let mut current_length = 0;
-                let mut elems = elems.clone();
-                elems.reverse();
-                for elem in elems {
-                    let elaborated_elem = self.elaborate_term(&elem, Some(&elem_type));
+                for (index, elem) in elems.iter().enumerate().rev() {
+                    let elaborated_elem = match index {
+                        0 => head.take().unwrap(),
+                        _ => self.elaborate_term(elem, Some(&elem_type)),
+                    };
                     result = Term::App(
                         Box::new(Term::App(
                             Box::new(Term::App(
                                 Box::new(Term::App(
-                                    Box::new(Term::Const(PRIM_ARRAY_CONS)),
+                                    Box::new(Term::Const(PRIM_ARRAY_CONS, level.clone())),
                                     Box::new(elem_type.clone()),
                                 )),
                                 Box::new(Term::Lit(Literal::Nat(current_length))),
@@ -327,14 +818,14 @@ impl ElabState {
                 }
                 (result, array_type)
             }
-            SyntaxExpr::App(fun, arg) => {
+            SyntaxExprKind::App(fun, arg) => {
                 let (mut term, mut fn_type) = self.elaborate_term_inner(fun);
                 
                 loop {
                     fn_type = reduce::whnf(self, &fn_type);
                     match &fn_type {
-                        Term::Pi(info, param_ty, body_ty) if info != &BinderInfo::Explicit => {
-                            let mvar = self.fresh_mvar(*param_ty.clone());
+                        Term::Pi(_, info, param_ty, body_ty) if info != &BinderInfo::Explicit => {
+                            let mvar = self.fresh_implicit_arg(info, *param_ty.clone());
                             fn_type = subst::instantiate(body_ty, &mvar);
                             term = Term::App(Box::new(term), Box::new(mvar));
                         },
@@ -343,8 +834,9 @@ impl ElabState {
                 };
 
                 match fn_type {
-                    Term::Pi(_info, param_ty, body_ty) => {
+                    Term::Pi(_, _info, param_ty, body_ty) => {
                         let elaborated_arg = self.elaborate_term(arg, Some(&param_ty));
+                        self.process_postponed();
                         let return_type = subst::instantiate(&body_ty, &elaborated_arg);
                         (
                             Term::App(Box::new(term), Box::new(elaborated_arg)),
@@ -352,36 +844,181 @@ impl ElabState {
                         )
                     }
                     u => {
-                        self.errors.push(ElabError::NotAFunction(u));
+                        self.report(ElabErrorKind::NotAFunction(u));
                         return (self.erroneous_term(), self.erroneous_term());
                     }
                 }
             }
-            u => {
-                self.errors.push(ElabError::UnsupportedSyntax(u.clone()));
+            _ => {
+                self.report(ElabErrorKind::UnsupportedSyntax);
                 (self.erroneous_term(), self.erroneous_term())
             }
         }
     }
 
+    /// Binder types written as `_` are taken from the expected Pi type.
+    fn elaborate_lambda(
+        &mut self,
+        binders: &[SyntaxBinder],
+        body: &SyntaxExpr,
+        expected_type: Option<&Term>,
+    ) -> (Term, Term) {
+        let saved_lctx = self.lctx.clone();
+        let mut expected = expected_type.map(|ty| reduce::whnf(self, ty));
+        let mut binder_fvars = Vec::new();
+
+        for binder in binders {
+            let (binder_name, binder_type_syntax, info) = binder_parts(binder);
+            let domain = match &expected {
+                Some(Term::Pi(_, _, domain, _)) => Some((**domain).clone()),
+                _ => None,
+            };
+            let binder_type = match (&binder_type_syntax.kind, domain) {
+                (SyntaxExprKind::Hole, Some(domain)) => domain,
+                (_, domain) => {
+                    let (binder_type, _) = self.elaborate_type(binder_type_syntax);
+                    if let Some(domain) = domain
+                        && !self.unify(&binder_type, &domain)
+                    {
+                        self.report(ElabErrorKind::TypeMismatch {
+                            expected: domain,
+                            found: binder_type.clone(),
+                        });
+                    }
+                    binder_type
+                }
+            };
+            let (fvar, fvar_term) = self.fresh_fvar(binder_name.clone(), binder_type.clone());
+            expected = match expected {
+                Some(Term::Pi(_, _, _, codomain)) => {
+                    Some(reduce::whnf(self, &subst::instantiate(&codomain, &fvar_term)))
+                }
+                _ => None,
+            };
+            binder_fvars.push((fvar, info, binder_type));
+        }
+
+        let (body, body_type) = match expected {
+            Some(expected) => (self.elaborate_term(body, Some(&expected)), expected),
+            None => self.elaborate_term_inner(body),
+        };
+        self.lctx = saved_lctx;
+        (
+            subst::mk_lambda(&binder_fvars, body),
+            subst::mk_pi(&binder_fvars, body_type),
+        )
+    }
+
+    fn elaborate_let(
+        &mut self,
+        name: &str,
+        type_ann: Option<&SyntaxExpr>,
+        value: &SyntaxExpr,
+        body: &SyntaxExpr,
+        expected_type: Option<&Term>,
+    ) -> (Term, Term) {
+        let (value, value_type) = match type_ann {
+            Some(type_ann) => {
+                let (value_type, _) = self.elaborate_type(type_ann);
+                (self.elaborate_term(value, Some(&value_type)), value_type)
+            }
+            None => self.elaborate_term_inner(value),
+        };
+
+        let saved_lctx = self.lctx.clone();
+        let fvar = self
+            .lctx
+            .push_let(name.to_string(), value_type.clone(), value.clone(), &mut self.gen_);
+        let (body, body_type) = match expected_type {
+            Some(expected) => (self.elaborate_term(body, Some(expected)), expected.clone()),
+            None => self.elaborate_term_inner(body),
+        };
+        self.lctx = saved_lctx;
+
+        let body_type = subst::instantiate(&subst::abstract_fvar(&body_type, fvar.clone()), &value);
+        (
+            Term::Let(
+                BinderName(Some(name.to_string())),
+                Box::new(value_type),
+                Box::new(value),
+                Box::new(subst::abstract_fvar(&body, fvar)),
+            ),
+            body_type,
+        )
+    }
+
+    /// `(a, b, c)` is `(a, (b, c))`; against a Sigma type the second
+    /// component is checked with the first substituted in.
+    fn elaborate_tuple(&mut self, items: &[SyntaxExpr], expected_type: Option<&Term>) -> (Term, Term) {
+        let [first, rest @ ..] = items else {
+            self.report(ElabErrorKind::UnsupportedSyntax);
+            return (self.erroneous_term(), self.erroneous_term());
+        };
+        if rest.is_empty() {
+            return match expected_type {
+                Some(expected) => (self.elaborate_term(first, Some(expected)), expected.clone()),
+                None => self.elaborate_term_inner(first),
+            };
+        }
+
+        let expected = expected_type.map(|ty| reduce::whnf(self, ty));
+        let (fst, snd, sigma) = match expected {
+            Some(Term::Sigma(name, info, fst_type, snd_type)) => {
+                let fst = self.elaborate_term(first, Some(&fst_type));
+                let (snd, _) = self.elaborate_tuple(rest, Some(&subst::instantiate(&snd_type, &fst)));
+                (fst, snd, Term::Sigma(name, info, fst_type, snd_type))
+            }
+            _ => {
+                let (fst, fst_type) = self.elaborate_term_inner(first);
+                let (snd, snd_type) = self.elaborate_tuple(rest, None);
+                (
+                    fst,
+                    snd,
+                    Term::Sigma(BinderName::default(), BinderInfo::Explicit, Box::new(fst_type), Box::new(snd_type)),
+                )
+            }
+        };
+        (
+            Term::Pair(Box::new(sigma.clone()), Box::new(fst), Box::new(snd)),
+            sigma,
+        )
+    }
+
     fn unify(&mut self, a: &Term, b: &Term) -> bool {
         unify::is_def_eq(self, a, b)
     }
 }
 
+fn binder_parts(binder: &SyntaxBinder) -> (&String, &SyntaxExpr, BinderInfo) {
+    match binder {
+        SyntaxBinder::Explicit(n, ty) => (n, ty, BinderInfo::Explicit),
+        SyntaxBinder::Implicit(n, ty) => (n, ty, BinderInfo::Implicit),
+        SyntaxBinder::Instance(n, ty) => (n, ty, BinderInfo::InstanceImplicit),
+    }
+}
+
+/// The dotted name written by `A.b.c`, when the head is a constructor name.
+fn syntax_path(syntax: &SyntaxExpr) -> Option<String> {
+    match &syntax.kind {
+        SyntaxExprKind::Constructor(name) => Some(name.clone()),
+        SyntaxExprKind::Proj(head, field) => {
+            let mut path = syntax_path(head)?;
+            path.push('.');
+            path.push_str(field);
+            Some(path)
+        }
+        _ => None,
+    }
+}
+
 pub fn elaborate_file(
     module_id: ModuleId,
     root: &SyntaxExpr,
 ) -> Result<Environment, Vec<ElabError>> {
     let mut state = ElabState::pre_loaded(module_id);
 
-    match root {
-        SyntaxExpr::Root(commands) => {
-            for cmd in commands {
-                state.elaborate_command(cmd);
-            }
-        }
-        _ => return Err(alloc::vec![ElabError::ExpectedRoot]),
+    if let Err(err) = state.elaborate_root(root) {
+        return Err(alloc::vec![err]);
     }
 
     if state.errors.is_empty() {
@@ -0,0 +1,123 @@
+//! Imports, namespaces and resolving the names of global declarations.
+//!
+//! Declarations are entered in the environment under their full name, the
+//! enclosing namespaces followed by the declared name. A name in a term
+//! refers first to a declaration in the enclosing namespaces, innermost
+//! first, and otherwise to a declaration at the root or in an opened
+//! namespace; it is ambiguous if more than one of the latter exists.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    elaboration::{ElabState, err::ElabErrorKind},
+    module::name::{Name, QualifiedName},
+    syntax::tree::SyntaxExpr,
+};
+
+impl ElabState {
+    /// The full name of a declaration named `name` in the current namespace.
+    pub(super) fn qualify(&self, name: &str) -> String {
+        self.namespace.join(&Name::parse(name)).to_string()
+    }
+
+    pub fn resolve_global(&mut self, name: &str) -> Option<QualifiedName> {
+        let candidates = self.global_candidates(name);
+        if candidates.len() > 1 {
+            self.report(ElabErrorKind::AmbiguousName {
+                name: name.into(),
+                candidates: candidates
+                    .iter()
+                    .filter_map(|candidate| candidate.display().map(String::from))
+                    .collect(),
+            });
+        }
+        let found = candidates.into_iter().next()?;
+        if let Some(resolutions) = &mut self.resolutions
+            && !resolutions.iter().any(|(resolved, _)| resolved == name)
+        {
+            resolutions.push((name.into(), found.clone()));
+        }
+        Some(found)
+    }
+
+    /// The declarations `name` may refer to: the one in the innermost
+    /// enclosing namespace that has one, or else each one at the root or in
+    /// an opened namespace.
+    pub(super) fn global_candidates(&self, name: &str) -> Vec<QualifiedName> {
+        let path = Name::parse(name);
+        let mut namespace = Some(self.namespace.clone());
+        while let Some(prefix) = namespace.filter(|prefix| !prefix.is_root()) {
+            if let Some(found) = self.env.lookup_name(&prefix.join(&path)) {
+                return alloc::vec![found.clone()];
+            }
+            namespace = prefix.parent();
+        }
+
+        let mut candidates: Vec<QualifiedName> = Vec::new();
+        for open in core::iter::once(&Name::root()).chain(&self.opens) {
+            if let Some(found) = self.env.lookup_name(&open.join(&path))
+                && !candidates.contains(found)
+            {
+                candidates.push(found.clone());
+            }
+        }
+        candidates
+    }
+
+    /// `open A B`: each namespace is looked up relative to the enclosing
+    /// namespaces like a declaration, then at the root.
+    pub(super) fn open_namespaces(&mut self, paths: &[String]) {
+        for path in paths {
+            let path = Name::parse(path);
+            let mut namespace = Some(self.namespace.clone());
+            let found = loop {
+                let Some(prefix) = namespace else {
+                    break None;
+                };
+                let candidate = prefix.join(&path);
+                if self.env.has_namespace(&candidate) {
+                    break Some(candidate);
+                }
+                namespace = prefix.parent();
+            };
+            match found {
+                Some(namespace) => self.opens.push(namespace),
+                None => self.report(ElabErrorKind::UnknownNamespace(path.to_string())),
+            }
+        }
+    }
+
+    /// `namespace A ... end`. Namespaces opened inside are closed again at
+    /// `end`.
+    pub(super) fn elaborate_namespace(&mut self, name: &str, commands: &[SyntaxExpr]) {
+        let inner = self.namespace.join(&Name::parse(name));
+        let saved_namespace = core::mem::replace(&mut self.namespace, inner);
+        let saved_opens = self.opens.clone();
+        for cmd in commands {
+            self.elaborate_command(cmd);
+        }
+        self.namespace = saved_namespace;
+        self.opens = saved_opens;
+    }
+
+    /// `import A.B` brings the declarations of module `A.B`, which the
+    /// driver elaborated beforehand, into scope.
+    pub(super) fn import_module(&mut self, module: &str) {
+        match self.imports.get(module) {
+            Some(Some(env)) => {
+                let conflicts = self.env.merge(env);
+                for name in conflicts {
+                    self.report(ElabErrorKind::DuplicateDeclaration {
+                        name: name.to_string(),
+                        module: module.into(),
+                    });
+                }
+            }
+            Some(None) => self.report(ElabErrorKind::ImportCycle(module.into())),
+            None => self.report(ElabErrorKind::UnknownModule(module.into())),
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Normalisation by evaluation: terms are evaluated into a semantic domain in
+//! which binders are closures, so substitution never traverses a term, and
+//! values are read back into terms in normal form.
+//!
+//! Constants evaluate to neutrals, and a definition is only unfolded when
+//! something needs its value: iota reduction, literal arithmetic,
+//! unification once comparing spines fails, and `normalize`.
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+
+use crate::{
+    elaboration::{Declaration, ElabState, subst},
+    module::{
+        name::QualifiedName,
+        prim::{self, PRIM_NAT_BINOPS},
+        unique::Unique,
+    },
+    spine::{BinderInfo, BinderName, Level, Literal, Term},
+};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    /// A head that can't reduce, applied to a spine of eliminations.
+    Neutral(Head, Vec<Elim>),
+    Lam(BinderInfo, Rc<Value>, Closure),
+    Pi(BinderInfo, Rc<Value>, Closure),
+    Sigma(BinderInfo, Rc<Value>, Closure),
+    Sort(Level),
+    Lit(Literal),
+    Pair(Rc<Value>, Rc<Value>, Rc<Value>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Head {
+    /// A variable bound during read back or unification, as a de Bruijn level.
+    Var(usize),
+    /// A bound variable that escaped the evaluation environment, counted
+    /// from its end.
+    Loose(usize),
+    FVar(Unique),
+    MVar(Unique),
+    Const(QualifiedName, Vec<Level>),
+}
+
+impl Head {
+    /// Equality ignoring the universe levels of constants.
+    pub fn same_head(&self, other: &Head) -> bool {
+        match (self, other) {
+            (Head::Var(a), Head::Var(b)) | (Head::Loose(a), Head::Loose(b)) => a == b,
+            (Head::FVar(a), Head::FVar(b)) | (Head::MVar(a), Head::MVar(b)) => a == b,
+            (Head::Const(a, _), Head::Const(b, _)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Elim {
+    App(Value),
+    Fst,
+    Snd,
+}
+
+#[derive(Debug, Clone)]
+pub struct Closure {
+    name: BinderName,
+    env: Env,
+    body: Term,
+}
+
+/// The values of the bound variables in scope, innermost first.
+#[derive(Debug, Clone, Default)]
+pub struct Env(Option<Rc<(Value, Env)>>);
+
+impl Env {
+    pub fn push(&self, value: Value) -> Env {
+        Env(Some(Rc::new((value, self.clone()))))
+    }
+
+    /// Looks up a de Bruijn index, or returns how far past the end it is.
+    fn get(&self, index: usize) -> Result<&Value, usize> {
+        let mut current = self;
+        let mut index = index;
+        loop {
+            match &current.0 {
+                Some(node) if index == 0 => return Ok(&node.0),
+                Some(node) => {
+                    current = &node.1;
+                    index -= 1;
+                }
+                None => return Err(index),
+            }
+        }
+    }
+}
+
+impl Value {
+    pub fn var(level: usize) -> Value {
+        Value::Neutral(Head::Var(level), Vec::new())
+    }
+}
+
+impl Closure {
+    pub fn name(&self) -> &BinderName {
+        &self.name
+    }
+
+    pub fn apply(&self, state: &ElabState, arg: Value) -> Value {
+        eval(state, &self.env.push(arg), &self.body)
+    }
+}
+
+pub fn eval(state: &ElabState, env: &Env, term: &Term) -> Value {
+    match term {
+        Term::BVar(i) => match env.get(*i) {
+            Ok(value) => value.clone(),
+            Err(past_end) => Value::Neutral(Head::Loose(past_end), Vec::new()),
+        },
+        Term::FVar(u) => match state.lctx.lookup(u.clone()).and_then(|decl| decl.value.as_ref()) {
+            Some(value) => eval(state, &Env::default(), value),
+            None => Value::Neutral(Head::FVar(u.clone()), Vec::new()),
+        },
+        Term::MVar(u) => match state.mctx.get_assignment(u.clone()) {
+            Some(value) => eval(state, &Env::default(), value),
+            None => Value::Neutral(Head::MVar(u.clone()), Vec::new()),
+        },
+        Term::Const(name, levels) => Value::Neutral(Head::Const(name.clone(), levels.clone()), Vec::new()),
+        Term::App(f, arg) => {
+            let f = eval(state, env, f);
+            let arg = eval(state, env, arg);
+            apply(state, f, arg)
+        }
+        Term::Lam(name, info, domain, body) => Value::Lam(
+            info.clone(),
+            Rc::new(eval(state, env, domain)),
+            Closure {
+                name: name.clone(),
+                env: env.clone(),
+                body: (**body).clone(),
+            },
+        ),
+        Term::Pi(name, info, domain, body) => Value::Pi(
+            info.clone(),
+            Rc::new(eval(state, env, domain)),
+            Closure {
+                name: name.clone(),
+                env: env.clone(),
+                body: (**body).clone(),
+            },
+        ),
+        Term::Sigma(name, info, domain, body) => Value::Sigma(
+            info.clone(),
+            Rc::new(eval(state, env, domain)),
+            Closure {
+                name: name.clone(),
+                env: env.clone(),
+                body: (**body).clone(),
+            },
+        ),
+        Term::Let(_, _, value, body) => {
+            let value = eval(state, env, value);
+            eval(state, &env.push(value), body)
+        }
+        Term::Sort(level) => Value::Sort(level.clone()),
+        Term::Lit(lit) => Value::Lit(lit.clone()),
+        Term::Pair(sigma, fst, snd) => Value::Pair(
+            Rc::new(eval(state, env, sigma)),
+            Rc::new(eval(state, env, fst)),
+            Rc::new(eval(state, env, snd)),
+        ),
+        Term::Fst(pair) => project(eval(state, env, pair), Elim::Fst),
+        Term::Snd(pair) => project(eval(state, env, pair), Elim::Snd),
+    }
+}
+
+/// Applies `f` to `arg`. Applying a value that is not a function only
+/// happens on ill-typed terms the elaborator already reported, and leaves
+/// `f` as it is.
+pub fn apply(state: &ElabState, f: Value, arg: Value) -> Value {
+    match f {
+        Value::Lam(_, _, closure) => closure.apply(state, arg),
+        Value::Neutral(head, mut spine) => {
+            spine.push(Elim::App(arg));
+            reduce_neutral(state, head, spine)
+        }
+        f => f,
+    }
+}
+
+fn project(pair: Value, elim: Elim) -> Value {
+    match (pair, elim) {
+        (Value::Pair(_, fst, _), Elim::Fst) => (*fst).clone(),
+        (Value::Pair(_, _, snd), _) => (*snd).clone(),
+        (Value::Neutral(head, mut spine), elim) => {
+            spine.push(elim);
+            Value::Neutral(head, spine)
+        }
+        (pair, _) => pair,
+    }
+}
+
+/// Re-evaluates a neutral whose head metavariable was assigned after it was
+/// evaluated.
+pub fn force(state: &ElabState, value: &Value) -> Value {
+    match value {
+        Value::Neutral(Head::MVar(u), spine) => match state.mctx.get_assignment(u.clone()) {
+            Some(assigned) => {
+                let head = eval(state, &Env::default(), assigned);
+                force(state, &apply_spine(state, head, spine))
+            }
+            None => value.clone(),
+        },
+        _ => value.clone(),
+    }
+}
+
+/// Whether `head` is a definition, which `unfold` can unfold.
+pub fn is_definition(state: &ElabState, head: &Head) -> bool {
+    matches!(head, Head::Const(name, _) if matches!(state.env.lookup(name), Some(Declaration::Definition { .. })))
+}
+
+/// The value of the definition at the head of a neutral applied to its
+/// spine, or `None` if `value` isn't headed by a definition.
+pub fn unfold(state: &ElabState, value: &Value) -> Option<Value> {
+    let Value::Neutral(Head::Const(name, levels), spine) = value else {
+        return None;
+    };
+    let Some(Declaration::Definition {
+        level_params, value, ..
+    }) = state.env.lookup(name)
+    else {
+        return None;
+    };
+    let value = subst::instantiate_level_params(value, level_params, levels);
+    Some(apply_spine(state, eval(state, &Env::default(), &value), spine))
+}
+
+/// `value` with assigned metavariables and definitions at its head replaced
+/// by their values.
+pub fn whnf(state: &ElabState, value: &Value) -> Value {
+    let value = force(state, value);
+    match unfold(state, &value) {
+        Some(unfolded) => whnf(state, &unfolded),
+        None => value,
+    }
+}
+
+fn apply_spine<'e>(state: &ElabState, head: Value, spine: impl IntoIterator<Item = &'e Elim>) -> Value {
+    spine.into_iter().fold(head, |value, elim| match elim {
+        Elim::App(arg) => apply(state, value, arg.clone()),
+        elim => project(value, elim.clone()),
+    })
+}
+
+/// Literal arithmetic and iota reduction, tried whenever a constant's spine
+/// grows.
+fn reduce_neutral(state: &ElabState, head: Head, spine: Vec<Elim>) -> Value {
+    let Head::Const(name, levels) = &head else {
+        return Value::Neutral(head, spine);
+    };
+
+    if PRIM_NAT_BINOPS.contains(name)
+        && let [Elim::App(a), Elim::App(b)] = &spine[..]
+        && let (Value::Lit(Literal::Nat(a)), Value::Lit(Literal::Nat(b))) = (whnf(state, a), whnf(state, b))
+        && let Some(result) = prim::eval_nat_binop(name, a, b)
+    {
+        return Value::Lit(Literal::Nat(result));
+    }
+
+    if let Some(Declaration::Recursor {
+        level_params,
+        num_params,
+        num_indices,
+        num_minors,
+        rules,
+        ..
+    }) = state.env.lookup(name)
+    {
+        let prefix_len = num_params + 1 + num_minors;
+        let major_idx = prefix_len + num_indices;
+        if spine.len() == major_idx + 1
+            && let Some(Elim::App(major)) = spine.last()
+            && let Value::Neutral(Head::Const(ctor, _), ctor_spine) = whnf(state, major)
+            && let Some(rule) = rules.iter().find(|rule| rule.constructor == ctor)
+            && let Some(fields_start) = ctor_spine.len().checked_sub(rule.num_fields)
+        {
+            let rhs = subst::instantiate_level_params(&rule.rhs, level_params, levels);
+            let rhs = eval(state, &Env::default(), &rhs);
+            return apply_spine(state, rhs, spine[..prefix_len].iter().chain(&ctor_spine[fields_start..]));
+        }
+    }
+
+    Value::Neutral(head, spine)
+}
+
+/// Reads a value back into a term under `depth` binders, leaving
+/// definitions folded. Fails if the value mentions a variable bound outside
+/// those binders.
+pub fn quote(state: &ElabState, depth: usize, value: &Value) -> Option<Term> {
+    read_back(state, depth, value, false)
+}
+
+/// Like `quote`, but unfolds definitions first if `unfold` is set.
+fn read_back(state: &ElabState, depth: usize, value: &Value, unfold: bool) -> Option<Term> {
+    let quote = |depth: usize, value: &Value| read_back(state, depth, value, unfold);
+    let quote_binder = |domain: &Value, closure: &Closure| {
+        let domain = quote(depth, domain)?;
+        let body = quote(depth + 1, &closure.apply(state, Value::var(depth)))?;
+        Some((Box::new(domain), Box::new(body)))
+    };
+
+    let unfolded;
+    let value = if unfold {
+        unfolded = whnf(state, value);
+        &unfolded
+    } else {
+        value
+    };
+    Some(match value {
+        Value::Neutral(head, spine) => {
+            let head = match head {
+                Head::Var(level) => Term::BVar(depth.checked_sub(level + 1)?),
+                Head::Loose(index) => Term::BVar(index + depth),
+                Head::FVar(u) => Term::FVar(u.clone()),
+                Head::MVar(u) => Term::MVar(u.clone()),
+                Head::Const(name, levels) => Term::Const(name.clone(), levels.clone()),
+            };
+            spine.iter().try_fold(head, |term, elim| {
+                Some(match elim {
+                    Elim::App(arg) => Term::mk_app(term, quote(depth, arg)?),
+                    Elim::Fst => Term::Fst(Box::new(term)),
+                    Elim::Snd => Term::Snd(Box::new(term)),
+                })
+            })?
+        }
+        Value::Lam(info, domain, closure) => {
+            let (domain, body) = quote_binder(domain, closure)?;
+            Term::Lam(closure.name().clone(), info.clone(), domain, body)
+        }
+        Value::Pi(info, domain, closure) => {
+            let (domain, body) = quote_binder(domain, closure)?;
+            Term::Pi(closure.name().clone(), info.clone(), domain, body)
+        }
+        Value::Sigma(info, domain, closure) => {
+            let (domain, body) = quote_binder(domain, closure)?;
+            Term::Sigma(closure.name().clone(), info.clone(), domain, body)
+        }
+        Value::Sort(level) => Term::Sort(level.clone()),
+        Value::Lit(lit) => Term::Lit(lit.clone()),
+        Value::Pair(sigma, fst, snd) => Term::Pair(
+            Box::new(quote(depth, sigma)?),
+            Box::new(quote(depth, fst)?),
+            Box::new(quote(depth, snd)?),
+        ),
+    })
+}
+
+/// The beta-delta-zeta-iota normal form of `term`.
+pub fn normalize(state: &ElabState, term: &Term) -> Term {
+    let value = eval(state, &Env::default(), term);
+    // Evaluating in the empty environment never produces `Head::Var`.
+    read_back(state, 0, &value, true).unwrap_or_else(|| term.clone())
+}
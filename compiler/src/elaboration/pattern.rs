@@ -0,0 +1,263 @@
+//! Higher-order pattern unification.
+//!
+//! A metavariable applied to distinct variables, `?m x y`, is a pattern, and
+//! `?m x y =?= t` has the most general solution `?m := λ x y. t` provided
+//! `t` mentions no other variable outside the scope of `?m`. Solving inverts
+//! the spine: `t` is read back with `x` and `y` renamed to the binders of the
+//! solution, checking each other variable against the metavariable's local
+//! context. Metavariables in `t` applied to variables the solution can't
+//! mention are pruned, by assigning them a fresh metavariable that doesn't
+//! take those arguments.
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+
+use crate::{
+    elaboration::{
+        Binder, ElabState,
+        ctx::LocalContext,
+        nbe::{self, Closure, Elim, Head, Value},
+        subst, unify,
+    },
+    module::unique::Unique,
+    spine::Term,
+};
+
+/// How the right-hand side of a pattern problem is read back into the
+/// solution.
+struct Renaming {
+    mvar: Unique,
+    scope: LocalContext,
+    /// The variables of the pattern's spine, and the binders of the solution
+    /// they become.
+    vars: Vec<Head>,
+    params: Vec<Unique>,
+    /// Variables bound by unification at levels from `base` on were bound
+    /// inside the right-hand side.
+    base: usize,
+}
+
+/// Solves `a =?= b` when `a` is a pattern whose metavariable has a known
+/// type. Returns `None` when it isn't, so unification falls back to
+/// first-order approximation.
+pub(super) fn solve(state: &mut ElabState, depth: usize, a: &Value, b: &Value) -> Option<bool> {
+    let Value::Neutral(Head::MVar(mvar), spine) = a else {
+        return None;
+    };
+    if let Value::Neutral(Head::MVar(other), _) = b
+        && other == mvar
+    {
+        return None;
+    }
+    let vars = pattern_vars(state, spine)?;
+    let decl = state.mctx.lookup_decl(mvar.clone())?.clone();
+
+    let saved_lctx = core::mem::replace(&mut state.lctx, decl.lctx.clone());
+    let type_ = unify::instantiate_mvars(state, &decl.type_);
+    let (binders, _) = state.open_pi_n(&type_, "x", vars.len());
+    state.lctx = saved_lctx;
+    if binders.len() < vars.len() {
+        return None;
+    }
+
+    let renaming = Renaming {
+        mvar: mvar.clone(),
+        scope: decl.lctx,
+        vars,
+        params: binders.iter().map(|(fvar, ..)| fvar.clone()).collect(),
+        base: depth,
+    };
+    let Some(body) = rename(state, &renaming, depth, b) else {
+        return Some(false);
+    };
+    let solution = subst::mk_lambda(&binders, body);
+    Some(unify::try_assign_mvar(state, &Term::MVar(mvar.clone()), &solution))
+}
+
+/// The variables of a spine of distinct variables.
+fn pattern_vars(state: &ElabState, spine: &[Elim]) -> Option<Vec<Head>> {
+    let mut vars: Vec<Head> = Vec::new();
+    for elim in spine {
+        let Elim::App(arg) = elim else {
+            return None;
+        };
+        let head = as_var(state, arg)?;
+        if vars.iter().any(|var| var.same_head(&head)) {
+            return None;
+        }
+        vars.push(head);
+    }
+    Some(vars)
+}
+
+fn as_var(state: &ElabState, value: &Value) -> Option<Head> {
+    match nbe::force(state, value) {
+        Value::Neutral(head @ (Head::Var(_) | Head::FVar(_)), spine) if spine.is_empty() => Some(head),
+        _ => None,
+    }
+}
+
+/// Reads `value` back under `depth` binders as the body of the solution, or
+/// fails if it mentions a variable the solution can't.
+fn rename(state: &mut ElabState, renaming: &Renaming, depth: usize, value: &Value) -> Option<Term> {
+    Some(match nbe::force(state, value) {
+        Value::Neutral(Head::MVar(mvar), _) if mvar == renaming.mvar => return None,
+        Value::Neutral(Head::MVar(mvar), spine) => rename_flex(state, renaming, depth, &mvar, &spine)?,
+        Value::Neutral(head, spine) => {
+            let renamed = rename_head(renaming, depth, &head)
+                .and_then(|head| rename_spine(state, renaming, depth, head, &spine));
+            match renamed {
+                Some(term) => term,
+                // Unfolding a definition may drop what couldn't be renamed.
+                None => {
+                    let unfolded = nbe::unfold(state, &Value::Neutral(head, spine))?;
+                    rename(state, renaming, depth, &unfolded)?
+                }
+            }
+        }
+        Value::Lam(info, domain, closure) => {
+            let (domain, body) = rename_binder(state, renaming, depth, &domain, &closure)?;
+            Term::Lam(closure.name().clone(), info, domain, body)
+        }
+        Value::Pi(info, domain, closure) => {
+            let (domain, body) = rename_binder(state, renaming, depth, &domain, &closure)?;
+            Term::Pi(closure.name().clone(), info, domain, body)
+        }
+        Value::Sigma(info, domain, closure) => {
+            let (domain, body) = rename_binder(state, renaming, depth, &domain, &closure)?;
+            Term::Sigma(closure.name().clone(), info, domain, body)
+        }
+        Value::Sort(level) => Term::Sort(level),
+        Value::Lit(lit) => Term::Lit(lit),
+        Value::Pair(sigma, fst, snd) => Term::Pair(
+            Box::new(rename(state, renaming, depth, &sigma)?),
+            Box::new(rename(state, renaming, depth, &fst)?),
+            Box::new(rename(state, renaming, depth, &snd)?),
+        ),
+    })
+}
+
+fn rename_head(renaming: &Renaming, depth: usize, head: &Head) -> Option<Term> {
+    if let Some(i) = renaming.vars.iter().position(|var| var.same_head(head)) {
+        return Some(Term::FVar(renaming.params[i].clone()));
+    }
+    match head {
+        Head::Var(level) if *level >= renaming.base => Some(Term::BVar(depth - level - 1)),
+        Head::FVar(fvar) if renaming.scope.lookup(fvar.clone()).is_some() => Some(Term::FVar(fvar.clone())),
+        Head::Const(name, levels) => Some(Term::Const(name.clone(), levels.clone())),
+        _ => None,
+    }
+}
+
+fn rename_spine(state: &mut ElabState, renaming: &Renaming, depth: usize, head: Term, spine: &[Elim]) -> Option<Term> {
+    let mut term = head;
+    for elim in spine {
+        term = match elim {
+            Elim::App(arg) => Term::mk_app(term, rename(state, renaming, depth, arg)?),
+            Elim::Fst => Term::Fst(Box::new(term)),
+            Elim::Snd => Term::Snd(Box::new(term)),
+        };
+    }
+    Some(term)
+}
+
+fn rename_binder(
+    state: &mut ElabState,
+    renaming: &Renaming,
+    depth: usize,
+    domain: &Rc<Value>,
+    closure: &Closure,
+) -> Option<(Box<Term>, Box<Term>)> {
+    let domain = rename(state, renaming, depth, domain)?;
+    let body = closure.apply(state, Value::var(depth));
+    let body = rename(state, renaming, depth + 1, &body)?;
+    Some((Box::new(domain), Box::new(body)))
+}
+
+/// Renames another metavariable's arguments, pruning those that are
+/// variables the solution can't mention.
+fn rename_flex(state: &mut ElabState, renaming: &Renaming, depth: usize, mvar: &Unique, spine: &[Elim]) -> Option<Term> {
+    let mut args = Vec::new();
+    for elim in spine {
+        let Elim::App(arg) = elim else {
+            return rename_spine(state, renaming, depth, Term::MVar(mvar.clone()), spine);
+        };
+        args.push((arg, rename(state, renaming, depth, arg)));
+    }
+    if args.iter().any(|(arg, renamed)| renamed.is_none() && as_var(state, arg).is_none()) {
+        return None;
+    }
+    let mvar = restrict_scope(state, renaming, mvar)?;
+    let keep: Vec<bool> = args.iter().map(|(_, renamed)| renamed.is_some()).collect();
+    let head = if keep.iter().all(|keep| *keep) {
+        Term::MVar(mvar)
+    } else {
+        prune(state, &mvar, &keep)?
+    };
+    Some(Term::mk_apps(head, args.into_iter().filter_map(|(_, renamed)| renamed)))
+}
+
+/// A metavariable created in a local context with locals the solution
+/// can't mention is assigned one restricted to the solution's scope, so it
+/// can't later be solved with them.
+fn restrict_scope(state: &mut ElabState, renaming: &Renaming, mvar: &Unique) -> Option<Unique> {
+    let Some(decl) = state.mctx.lookup_decl(mvar.clone()) else {
+        return Some(mvar.clone());
+    };
+    let in_scope = |fvar: &Unique| renaming.scope.lookup(fvar.clone()).is_some();
+    if decl.lctx.decls.iter().all(|local| in_scope(&local.fvar)) {
+        return Some(mvar.clone());
+    }
+    if unify::is_named_hole(mvar) {
+        return None;
+    }
+    let type_ = unify::instantiate_mvars(state, &decl.type_.clone());
+    let mut escapes = false;
+    subst::for_each_fvar(&type_, &mut |fvar| escapes |= !in_scope(fvar));
+    if escapes {
+        return None;
+    }
+    let span = decl.span;
+    let restricted = state.mctx.fresh_mvar(type_, &renaming.scope, span, &mut state.gen_);
+    state.mctx.assign(mvar.clone(), Term::MVar(restricted.clone()));
+    Some(restricted)
+}
+
+/// Assigns `?n := λ xs. ?p ys` where `ys` are the arguments of `?n` marked
+/// in `keep`, and returns `?p`. Fails if the type of `?n` depends on a
+/// dropped argument.
+fn prune(state: &mut ElabState, mvar: &Unique, keep: &[bool]) -> Option<Term> {
+    if unify::is_named_hole(mvar) {
+        return None;
+    }
+    let decl = state.mctx.lookup_decl(mvar.clone())?.clone();
+    let saved_lctx = core::mem::replace(&mut state.lctx, decl.lctx.clone());
+    let type_ = unify::instantiate_mvars(state, &decl.type_);
+    let (binders, result) = state.open_pi_n(&type_, "x", keep.len());
+
+    let kept: Vec<Binder> = binders
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| **keep)
+        .map(|(binder, _)| binder.clone())
+        .collect();
+    let dropped: Vec<&Unique> = binders
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| !**keep)
+        .map(|((fvar, ..), _)| fvar)
+        .collect();
+    let depends = |term: &Term| dropped.iter().any(|fvar| subst::has_fvar(term, fvar));
+    let prunable =
+        binders.len() == keep.len() && !depends(&result) && kept.iter().all(|(_, _, domain)| !depends(domain));
+
+    let pruned = prunable.then(|| {
+        state.lctx = decl.lctx.clone();
+        let pruned = state.fresh_mvar(subst::mk_pi(&kept, result));
+        let args = kept.iter().map(|(fvar, ..)| Term::FVar(fvar.clone()));
+        let solution = subst::mk_lambda(&binders, Term::mk_apps(pruned.clone(), args));
+        state.mctx.assign(mvar.clone(), solution);
+        pruned
+    });
+    state.lctx = saved_lctx;
+    pruned
+}
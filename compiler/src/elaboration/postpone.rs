@@ -0,0 +1,235 @@
+//! Problems postponed until more metavariables are known.
+//!
+//! Unification problems whose head is an unassigned metavariable applied to
+//! arguments, and lambdas or anonymous constructors whose expected type is
+//! still a metavariable, are queued instead of failing. The queue is retried
+//! as arguments get elaborated, and whatever is left at the end of a
+//! definition is forced or reported.
+
+use alloc::vec::Vec;
+
+use crate::{
+    elaboration::{
+        ElabState,
+        ctx::{LocalContext, MetavarContext},
+        err::ElabErrorKind,
+        reduce, unify,
+    },
+    module::unique::Unique,
+    spine::{Level, Term},
+    syntax::{
+        Span,
+        tree::{SyntaxBinder, SyntaxExpr, SyntaxExprKind},
+    },
+};
+
+#[derive(Debug, Clone)]
+pub enum Postponed {
+    /// `lhs =?= rhs`, stuck on a metavariable applied to arguments.
+    Unify {
+        lhs: Term,
+        rhs: Term,
+        lctx: LocalContext,
+        span: Span,
+    },
+    /// `syntax` elaborated against `expected` once that is known, with the
+    /// result assigned to `mvar`.
+    Elaborate {
+        syntax: SyntaxExpr,
+        expected: Term,
+        mvar: Unique,
+        lctx: LocalContext,
+    },
+}
+
+/// The state a failed attempt at unification or instance resolution rolls
+/// back to.
+pub(super) struct Snapshot {
+    mctx: MetavarContext,
+    level_constraints: Vec<(Level, Level)>,
+    postponed: usize,
+}
+
+impl ElabState {
+    pub(super) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            mctx: self.mctx.clone(),
+            level_constraints: self.level_constraints.clone(),
+            postponed: self.postponed.len(),
+        }
+    }
+
+    /// Only problems postponed since the snapshot are dropped, so the queue
+    /// must not have been processed in between.
+    pub(super) fn restore(&mut self, snapshot: Snapshot) {
+        self.mctx = snapshot.mctx;
+        self.level_constraints = snapshot.level_constraints;
+        self.postponed.truncate(snapshot.postponed);
+    }
+
+    pub(super) fn postpone_unification(&mut self, lhs: Term, rhs: Term) {
+        self.postponed.push(Postponed::Unify {
+            lhs,
+            rhs,
+            lctx: self.lctx.clone(),
+            span: self.span,
+        });
+    }
+
+    /// Postpones elaborating `syntax` if it needs to know the shape of
+    /// `expected` and that is still a metavariable, returning a metavariable
+    /// standing for the result.
+    pub(super) fn try_postpone_elaboration(&mut self, syntax: &SyntaxExpr, expected: &Term) -> Option<Term> {
+        if !self.needs_expected_type(syntax, expected) {
+            return None;
+        }
+        let mvar = self.fresh_mvar(expected.clone());
+        let Term::MVar(u) = &mvar else { unreachable!() };
+        self.postponed.push(Postponed::Elaborate {
+            syntax: syntax.clone(),
+            expected: expected.clone(),
+            mvar: u.clone(),
+            lctx: self.lctx.clone(),
+        });
+        Some(mvar)
+    }
+
+    fn needs_expected_type(&self, syntax: &SyntaxExpr, expected: &Term) -> bool {
+        match &syntax.kind {
+            SyntaxExprKind::AnonymousConstructor(_) => self.is_stuck(expected),
+            SyntaxExprKind::Lambda { binders, .. } => self.lambda_is_stuck(binders, expected),
+            _ => false,
+        }
+    }
+
+    fn is_stuck(&self, type_: &Term) -> bool {
+        matches!(reduce::whnf(self, type_).app_head(), Term::MVar(u) if !self.mctx.is_assigned(u.clone()))
+    }
+
+    /// Whether a binder of the lambda is untyped and the domain it would
+    /// take from `expected` is not known yet.
+    fn lambda_is_stuck(&self, binders: &[SyntaxBinder], expected: &Term) -> bool {
+        if !binders.iter().any(is_untyped) {
+            return false;
+        }
+        let mut type_ = expected.clone();
+        for binder in binders {
+            match reduce::whnf(self, &type_) {
+                Term::Pi(_, _, domain, codomain) => {
+                    if is_untyped(binder) && self.is_stuck(&domain) {
+                        return true;
+                    }
+                    type_ = *codomain;
+                }
+                other => return self.is_stuck(&other),
+            }
+        }
+        false
+    }
+
+    /// Retries postponed problems until none of them makes progress.
+    pub(super) fn process_postponed(&mut self) {
+        loop {
+            let mut progress = false;
+            for problem in core::mem::take(&mut self.postponed) {
+                progress |= self.retry(problem, false);
+            }
+            if !progress {
+                return;
+            }
+        }
+    }
+
+    /// Settles everything the current definition left pending: postponed
+    /// problems are retried as instances get synthesized, stuck elaboration
+    /// tasks are elaborated without knowing their expected type, and
+    /// unification problems that remain stuck are reported.
+    pub(super) fn solve_pending(&mut self) {
+        loop {
+            self.process_postponed();
+            self.synthesize_pending_instances();
+            self.process_postponed();
+            let Some(i) = self
+                .postponed
+                .iter()
+                .position(|problem| matches!(problem, Postponed::Elaborate { .. }))
+            else {
+                break;
+            };
+            let task = self.postponed.remove(i);
+            self.retry(task, true);
+        }
+        for problem in core::mem::take(&mut self.postponed) {
+            if let Postponed::Unify { lhs, rhs, span, .. } = problem {
+                let kind = ElabErrorKind::StuckUnification {
+                    lhs: unify::instantiate_mvars(self, &lhs),
+                    rhs: unify::instantiate_mvars(self, &rhs),
+                };
+                self.report_at(span, kind);
+            }
+        }
+    }
+
+    /// Returns whether `problem` made progress; if not it is queued again.
+    fn retry(&mut self, problem: Postponed, force: bool) -> bool {
+        match problem {
+            Postponed::Unify { lhs, rhs, lctx, span } => {
+                let new_lhs = unify::instantiate_mvars(self, &lhs);
+                let new_rhs = unify::instantiate_mvars(self, &rhs);
+                if new_lhs == lhs && new_rhs == rhs {
+                    self.postponed.push(Postponed::Unify { lhs, rhs, lctx, span });
+                    return false;
+                }
+                let saved_lctx = core::mem::replace(&mut self.lctx, lctx);
+                if !self.unify(&new_lhs, &new_rhs) {
+                    let kind = ElabErrorKind::CannotUnify {
+                        lhs: unify::instantiate_mvars(self, &new_lhs),
+                        rhs: unify::instantiate_mvars(self, &new_rhs),
+                    };
+                    self.report_at(span, kind);
+                }
+                self.lctx = saved_lctx;
+                true
+            }
+            Postponed::Elaborate {
+                syntax,
+                expected,
+                mvar,
+                lctx,
+            } => {
+                let saved_lctx = core::mem::replace(&mut self.lctx, lctx);
+                let stuck = self.needs_expected_type(&syntax, &expected);
+                if stuck && !force {
+                    let lctx = core::mem::replace(&mut self.lctx, saved_lctx);
+                    self.postponed.push(Postponed::Elaborate {
+                        syntax,
+                        expected,
+                        mvar,
+                        lctx,
+                    });
+                    return false;
+                }
+                let term = if stuck {
+                    let (term, type_) = self.elaborate_term_inner(&syntax);
+                    if !self.unify(&type_, &expected) {
+                        let kind = ElabErrorKind::TypeMismatch {
+                            expected: expected.clone(),
+                            found: type_,
+                        };
+                        self.report_at(syntax.span, kind);
+                    }
+                    term
+                } else {
+                    self.elaborate_term(&syntax, Some(&expected))
+                };
+                self.unify(&Term::MVar(mvar), &term);
+                self.lctx = saved_lctx;
+                true
+            }
+        }
+    }
+}
+
+fn is_untyped(binder: &SyntaxBinder) -> bool {
+    matches!(binder, SyntaxBinder::Explicit(_, ty) if matches!(ty.kind, SyntaxExprKind::Hole))
+}
@@ -0,0 +1,662 @@
+use alloc::{boxed::Box, string::{String, ToString}, vec::Vec};
+
+use crate::{
+    elaboration::{
+        Binder, Declaration, ElabState,
+        err::{ElabError, ElabErrorKind},
+        reduce, subst, unify,
+    },
+    module::{name::QualifiedName, unique::Unique},
+    spine::{BinderInfo, BinderName, Term},
+    syntax::{
+        Span,
+        tree::{SyntaxBinder, SyntaxExpr},
+    },
+};
+
+pub struct DefSyntax<'a> {
+    pub name: &'a str,
+    pub binders: &'a [SyntaxBinder],
+    pub return_type: &'a SyntaxExpr,
+    pub body: &'a SyntaxExpr,
+}
+
+/// A definition of the block, elaborated with every member of the block in
+/// scope as the local `fvar`.
+struct RecFn {
+    name: QualifiedName,
+    display: String,
+    fvar: Unique,
+    binders: Vec<Binder>,
+    return_type: Term,
+    type_: Term,
+    body: Term,
+}
+
+/// What elaborating the bodies of a block records to check its recursive
+/// calls against, before the bodies are compiled.
+#[derive(Debug, Clone)]
+pub struct RecursiveCalls {
+    fns: Vec<Unique>,
+    /// The member whose body is being elaborated.
+    caller: usize,
+    calls: Vec<Call>,
+    fields: Vec<Subterm>,
+    /// Pattern variables, with the local each one names.
+    aliases: Vec<(Unique, Unique)>,
+}
+
+/// A call to a member of the block, as it was written in `caller`'s body.
+#[derive(Debug, Clone)]
+struct Call {
+    span: Span,
+    caller: usize,
+    term: Term,
+}
+
+/// A recursive field of a constructor that `parent` was matched against,
+/// with the arity of its Pi telescope.
+#[derive(Debug, Clone)]
+struct Subterm {
+    field: Unique,
+    parent: Unique,
+    arity: usize,
+}
+
+impl RecursiveCalls {
+    fn resolve(&self, local: &Unique) -> Unique {
+        match self.aliases.iter().find(|(alias, _)| alias == local) {
+            Some((_, named)) => self.resolve(named),
+            None => local.clone(),
+        }
+    }
+
+    /// Whether `term` is a recursive field of a constructor that `major` was
+    /// matched against, applied to as many arguments as the field takes.
+    fn is_smaller(&self, term: &Term, major: &Unique) -> bool {
+        let Term::FVar(local) = term.app_head() else {
+            return false;
+        };
+        let local = self.resolve(local);
+        let arity = term.app_args().len();
+        self.fields
+            .iter()
+            .any(|sub| sub.field == local && sub.arity == arity && self.resolve(&sub.parent) == *major)
+    }
+}
+
+/// How a function recurses on its `major` binder: binders before it are
+/// either fixed or determined by the major's indices, binders after it are
+/// generalised in the motive.
+struct Plan {
+    major: usize,
+    indices: Vec<Term>,
+    index_params: Vec<Option<usize>>,
+    fixed: Vec<usize>,
+    others: Vec<usize>,
+}
+
+/// A recursive field of the constructor being compiled, with its induction
+/// hypothesis and the arity of its Pi telescope.
+struct RecursiveField {
+    field: Unique,
+    ih: Unique,
+    arity: usize,
+}
+
+impl ElabState {
+    /// Returns the names of the definitions admitted to the environment.
+    pub(super) fn elaborate_defs(&mut self, defs: &[DefSyntax]) -> Vec<QualifiedName> {
+        let base = self.lctx.clone();
+        let errors_before = self.errors.len();
+        let mut headers = Vec::new();
+        for def in defs {
+            let name = QualifiedName::User(self.gen_.fresh(self.qualify(def.name)));
+            let binders = self.elaborate_binders(def.binders);
+            let (return_type, _) = self.elaborate_type(def.return_type);
+            let type_ = subst::mk_pi(&binders, return_type.clone());
+            let locals = self.lctx.decls[base.decls.len()..].to_vec();
+            self.lctx = base.clone();
+            headers.push((name, binders, return_type, type_, locals));
+        }
+
+        let fvars: Vec<Unique> = defs
+            .iter()
+            .zip(&headers)
+            .map(|(def, (.., type_, _))| self.fresh_fvar(def.name.to_string(), type_.clone()).0)
+            .collect();
+        let with_fns = self.lctx.clone();
+        self.recursive_calls = Some(RecursiveCalls {
+            fns: fvars.clone(),
+            caller: 0,
+            calls: Vec::new(),
+            fields: Vec::new(),
+            aliases: Vec::new(),
+        });
+
+        let mut fns = Vec::new();
+        for (caller, ((def, (name, binders, return_type, type_, locals)), fvar)) in
+            defs.iter().zip(headers).zip(fvars).enumerate()
+        {
+            self.lctx = with_fns.clone();
+            self.lctx.decls.extend(locals);
+            if let Some(calls) = &mut self.recursive_calls {
+                calls.caller = caller;
+            }
+            let body = self.elaborate_term(def.body, Some(&return_type));
+            self.solve_pending();
+            let body = unify::instantiate_mvars(self, &body);
+            fns.push(RecFn {
+                name,
+                display: def.name.to_string(),
+                fvar,
+                binders,
+                return_type,
+                type_,
+                body,
+            });
+        }
+        self.lctx = with_fns;
+        let calls = self.recursive_calls.take().unwrap();
+
+        let recursive = fns
+            .iter()
+            .any(|f| fns.iter().any(|g| subst::has_fvar(&f.body, &g.fvar)));
+        let values = if recursive {
+            self.compile_structural(&fns, &calls)
+        } else {
+            Ok(fns
+                .iter()
+                .map(|f| subst::mk_lambda(&f.binders, f.body.clone()))
+                .collect())
+        };
+        self.lctx = base;
+
+        let (values, checked) = match values {
+            Ok(values) => (values, self.errors.len() == errors_before),
+            Err(err) => {
+                self.errors.push(err);
+                (self.without_recursion(&fns), false)
+            }
+        };
+        let mut names = Vec::new();
+        for (f, value) in fns.into_iter().zip(values) {
+            self.add_definition(f.name.clone(), &f.type_, &value, checked);
+            if self.env.lookup(&f.name).is_some() {
+                names.push(f.name);
+            }
+        }
+        names
+    }
+
+    /// The values of a block that couldn't be compiled, with its recursive
+    /// calls replaced by erroneous terms so that unfolding them terminates.
+    fn without_recursion(&mut self, fns: &[RecFn]) -> Vec<Term> {
+        let holes: Vec<Term> = fns.iter().map(|_| self.erroneous_term()).collect();
+        fns.iter()
+            .map(|f| {
+                let body = fns
+                    .iter()
+                    .zip(&holes)
+                    .fold(f.body.clone(), |body, (g, hole)| subst::replace_fvar(&body, g.fvar.clone(), hole));
+                subst::mk_lambda(&f.binders, body)
+            })
+            .collect()
+    }
+
+    /// Tries every inductive argument of the first function as the one the
+    /// block recurses on; the other members recurse on their first argument
+    /// of the same type.
+    fn compile_structural(&mut self, fns: &[RecFn], calls: &RecursiveCalls) -> Result<Vec<Term>, ElabError> {
+        let candidates: Vec<Vec<usize>> = fns
+            .iter()
+            .map(|f| {
+                (0..f.binders.len())
+                    .filter(|&i| self.inductive_info(&f.binders[i].2).is_some())
+                    .collect()
+            })
+            .collect();
+        if let Some((f, _)) = fns.iter().zip(&candidates).find(|(_, c)| c.is_empty()) {
+            return Err(ElabError {
+                kind: ElabErrorKind::NoStructuralArgument(f.display.clone()),
+                span: self.span,
+            });
+        }
+
+        let mut first_error = None;
+        for &first in &candidates[0] {
+            let info = self.inductive_info(&fns[0].binders[first].2).unwrap();
+            let mut majors = alloc::vec![first];
+            for (f, candidates) in fns.iter().zip(&candidates).skip(1) {
+                let major = candidates.iter().copied().find(|&k| {
+                    self.inductive_info(&f.binders[k].2)
+                        .is_some_and(|other| other.name == info.name && other.params == info.params)
+                });
+                match major {
+                    Some(major) => majors.push(major),
+                    None => {
+                        first_error.get_or_insert(ElabError {
+                            kind: ElabErrorKind::UnsupportedMutualRecursion(f.display.clone()),
+                            span: self.span,
+                        });
+                        break;
+                    }
+                }
+            }
+            if majors.len() != fns.len() {
+                continue;
+            }
+
+            let saved_lctx = self.lctx.clone();
+            let result = self.compile_on(fns, calls, &majors);
+            self.lctx = saved_lctx;
+            match result {
+                Ok(values) => return Ok(values),
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        Err(first_error.unwrap())
+    }
+
+    /// Builds `G : (is) -> (t : I params is) -> P_1 × ... × P_n` with the
+    /// recursor, where `P_i` is the type of the i-th function after its major
+    /// argument; recursive calls on a constructor's fields become projections
+    /// of the matching induction hypothesis.
+    fn compile_on(
+        &mut self,
+        fns: &[RecFn],
+        calls: &RecursiveCalls,
+        majors: &[usize],
+    ) -> Result<Vec<Term>, ElabError> {
+        let info = self.inductive_info(&fns[0].binders[majors[0]].2).unwrap();
+
+        let mut plans = Vec::new();
+        for (f, &major) in fns.iter().zip(majors) {
+            let indices = self.inductive_info(&f.binders[major].2).unwrap().indices;
+            let mut index_params: Vec<Option<usize>> = Vec::new();
+            for index in &indices {
+                let position = match index {
+                    Term::FVar(u) => f.binders[..major]
+                        .iter()
+                        .position(|(fvar, ..)| fvar == u)
+                        .filter(|p| !index_params.contains(&Some(*p))),
+                    _ => None,
+                };
+                index_params.push(position);
+            }
+            let fixed: Vec<usize> = (0..major).filter(|i| !index_params.contains(&Some(*i))).collect();
+            if fns.len() > 1 && !fixed.is_empty() {
+                return Err(ElabError {
+                    kind: ElabErrorKind::UnsupportedMutualRecursion(f.display.clone()),
+                    span: self.span,
+                });
+            }
+            plans.push(Plan {
+                major,
+                indices,
+                index_params,
+                fixed,
+                others: (major + 1..f.binders.len()).collect(),
+            });
+        }
+        self.check_calls(fns, calls, &plans)?;
+
+        let mut ind_type = self.env.lookup(&info.name).unwrap().instantiate_type(&info.levels);
+        for param in &info.params {
+            ind_type = self.instantiate_pi(&ind_type, param);
+        }
+        let (index_binders, _) = self.open_pi(&ind_type, "i");
+        let index_terms: Vec<Term> = index_binders.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+        let major_type = Term::mk_apps(
+            Term::Const(info.name.clone(), info.levels.clone()),
+            info.params.iter().cloned().chain(index_terms.iter().cloned()),
+        );
+        let (major, major_term) = self.fresh_internal_fvar("t".into(), major_type.clone());
+
+        let components: Vec<Term> = fns
+            .iter()
+            .zip(&plans)
+            .map(|(f, plan)| {
+                let others = generalised_binders(f, plan, &major_term, &index_terms);
+                subst::mk_pi(&others, substitute_major(f, plan, &major_term, &index_terms, &f.return_type))
+            })
+            .collect();
+        let mut motive_binders = index_binders;
+        motive_binders.push((major, BinderInfo::Explicit, major_type));
+        let motive_body = tuple_type(components);
+        let rec_levels = info.recursor_levels(self.infer_level(&motive_body));
+        let motive = subst::mk_lambda(&motive_binders, motive_body);
+
+        let mut rec_type = self.env.lookup(&info.recursor).unwrap().instantiate_type(&rec_levels);
+        for arg in info.params.iter().chain([&motive]) {
+            rec_type = self.instantiate_pi(&rec_type, arg);
+        }
+
+        let mut minors = Vec::new();
+        for ctor in &info.constructors {
+            let Some(decl @ Declaration::Intro { num_fields, .. }) = self.env.lookup(ctor).cloned() else {
+                continue;
+            };
+            let intro_type = decl.instantiate_type(&info.levels);
+            let Term::Pi(_, _, minor_type, _) = reduce::whnf(self, &rec_type) else {
+                break;
+            };
+            let (fields, ihs, target) = self.open_minor(&minor_type, &info.name, num_fields);
+            let binders: Vec<Binder> = fields.iter().chain(&ihs).cloned().collect();
+            let field_terms: Vec<Term> = fields.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+            let ctor_app = Term::mk_apps(
+                Term::Const(ctor.clone(), info.levels.clone()),
+                info.params.iter().cloned().chain(field_terms.iter().cloned()),
+            );
+
+            let mut result = intro_type;
+            for arg in info.params.iter().chain(&field_terms) {
+                result = self.instantiate_pi(&result, arg);
+            }
+            let result = reduce::whnf(self, &result);
+            let ctor_indices: Vec<Term> = result
+                .app_args()
+                .into_iter()
+                .skip(info.params.len())
+                .cloned()
+                .collect();
+
+            let mut recursive = Vec::new();
+            let mut ih_iter = ihs.iter();
+            for (fvar, _, field_type) in &fields {
+                let (ys, field_result) = self.open_pi(field_type, "y");
+                if field_result.app_head().const_name() == Some(&info.name)
+                    && let Some((ih, ..)) = ih_iter.next()
+                {
+                    recursive.push(RecursiveField {
+                        field: fvar.clone(),
+                        ih: ih.clone(),
+                        arity: ys.len(),
+                    });
+                }
+            }
+
+            let mut bodies = Vec::new();
+            for (f, plan) in fns.iter().zip(&plans) {
+                let body = substitute_major(f, plan, &ctor_app, &ctor_indices, &f.body);
+                let body = self.simplify(&body);
+                let body = rewrite_calls(&body, fns, &plans, &recursive);
+                let others = generalised_binders(f, plan, &ctor_app, &ctor_indices);
+                bodies.push(subst::mk_lambda(&others, body));
+            }
+
+            let target = reduce::whnf(self, &target);
+            let minor = subst::mk_lambda(&binders, tuple(&target, bodies));
+            rec_type = self.instantiate_pi(&rec_type, &minor);
+            minors.push(minor);
+        }
+
+        let g = Term::mk_apps(
+            Term::Const(info.recursor.clone(), rec_levels),
+            info.params.iter().cloned().chain([motive]).chain(minors),
+        );
+        Ok(fns
+            .iter()
+            .zip(&plans)
+            .enumerate()
+            .map(|(i, (f, plan))| {
+                let applied = Term::mk_apps(
+                    g.clone(),
+                    plan.indices
+                        .iter()
+                        .cloned()
+                        .chain([Term::FVar(f.binders[plan.major].0.clone())]),
+                );
+                let others = plan.others.iter().map(|&o| Term::FVar(f.binders[o].0.clone()));
+                let body = Term::mk_apps(project(applied, i, fns.len()), others);
+                subst::mk_lambda(&f.binders, body)
+            })
+            .collect())
+    }
+
+    /// Records `term`, elaborated from the syntax at `span`, if it is a call
+    /// to a member of the block being elaborated.
+    pub(super) fn record_call(&mut self, span: Span, term: &Term) {
+        if let Some(calls) = &mut self.recursive_calls
+            && let Term::FVar(head) = term.app_head()
+            && calls.fns.contains(head)
+        {
+            calls.calls.push(Call {
+                span,
+                caller: calls.caller,
+                term: term.clone(),
+            });
+        }
+    }
+
+    /// Records the recursive fields of a constructor `scrutinee` was matched
+    /// against.
+    pub(super) fn record_fields(&mut self, scrutinee: &Term, inductive: &QualifiedName, fields: &[Binder]) {
+        let Term::FVar(parent) = scrutinee else {
+            return;
+        };
+        if self.recursive_calls.is_none() {
+            return;
+        }
+        let saved_lctx = self.lctx.clone();
+        let mut subterms = Vec::new();
+        for (field, _, type_) in fields {
+            let (ys, result) = self.open_pi(type_, "y");
+            if result.app_head().const_name() == Some(inductive) {
+                subterms.push(Subterm {
+                    field: field.clone(),
+                    parent: parent.clone(),
+                    arity: ys.len(),
+                });
+            }
+        }
+        self.lctx = saved_lctx;
+        if let Some(calls) = &mut self.recursive_calls {
+            calls.fields.extend(subterms);
+        }
+    }
+
+    /// Records that the pattern variable `alias` names `value`.
+    pub(super) fn record_alias(&mut self, alias: &Unique, value: &Term) {
+        if let Some(calls) = &mut self.recursive_calls
+            && let Term::FVar(value) = value
+        {
+            calls.aliases.push((alias.clone(), value.clone()));
+        }
+    }
+
+    /// Checks that every call passes its callee's fixed arguments unchanged
+    /// and, for its major, a recursive field of the caller's major.
+    fn check_calls(&self, fns: &[RecFn], calls: &RecursiveCalls, plans: &[Plan]) -> Result<(), ElabError> {
+        let terms: Vec<Term> = calls
+            .calls
+            .iter()
+            .map(|call| unify::instantiate_mvars(self, &call.term))
+            .collect();
+        for (i, call) in calls.calls.iter().enumerate() {
+            let term = &terms[i];
+            // The head of an application was recorded on its own, and an
+            // expression may be recorded again by the syntax around it.
+            let covered = calls.calls.iter().zip(&terms).enumerate().any(|(k, (outer, outer_term))| {
+                k != i
+                    && outer.span.start <= call.span.start
+                    && call.span.end <= outer.span.end
+                    && (outer.span != call.span || k > i)
+                    && is_applied_in(term, outer_term)
+            });
+            if covered {
+                continue;
+            }
+
+            let Term::FVar(head) = term.app_head() else {
+                continue;
+            };
+            let j = calls.fns.iter().position(|f| f == head).unwrap();
+            let (f, plan) = (&fns[j], &plans[j]);
+            let args = term.app_args();
+            let caller_major = &fns[call.caller].binders[plans[call.caller].major].0;
+            let decreasing = args.len() == f.binders.len()
+                && plan
+                    .fixed
+                    .iter()
+                    .all(|&i| *args[i] == Term::FVar(f.binders[i].0.clone()))
+                && calls.is_smaller(args[plan.major], caller_major);
+            if !decreasing {
+                return Err(ElabError {
+                    kind: ElabErrorKind::NonDecreasingCall {
+                        function: f.display.clone(),
+                        call: term.clone(),
+                    },
+                    span: call.span,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Beta and iota reduces everywhere, so that matches on the major premise
+    /// collapse onto the constructor fields being compiled.
+    fn simplify(&self, term: &Term) -> Term {
+        let reduce_head = |term: Term| {
+            let reduced = reduce::whnf(self, &term);
+            if reduced == term {
+                term
+            } else {
+                self.simplify(&reduced)
+            }
+        };
+        match term {
+            Term::App(f, a) => reduce_head(Term::App(Box::new(self.simplify(f)), Box::new(self.simplify(a)))),
+            Term::Fst(p) => reduce_head(Term::Fst(Box::new(self.simplify(p)))),
+            Term::Snd(p) => reduce_head(Term::Snd(Box::new(self.simplify(p)))),
+            Term::Lam(name, info, ty, body) => Term::Lam(name.clone(), 
+                info.clone(),
+                Box::new(self.simplify(ty)),
+                Box::new(self.simplify(body)),
+            ),
+            Term::Pi(name, info, ty, body) => Term::Pi(name.clone(), 
+                info.clone(),
+                Box::new(self.simplify(ty)),
+                Box::new(self.simplify(body)),
+            ),
+            Term::Sigma(name, info, ty, body) => Term::Sigma(name.clone(), 
+                info.clone(),
+                Box::new(self.simplify(ty)),
+                Box::new(self.simplify(body)),
+            ),
+            Term::Let(name, ty, val, body) => Term::Let(name.clone(), 
+                Box::new(self.simplify(ty)),
+                Box::new(self.simplify(val)),
+                Box::new(self.simplify(body)),
+            ),
+            Term::Pair(ty, fst, snd) => Term::Pair(
+                Box::new(self.simplify(ty)),
+                Box::new(self.simplify(fst)),
+                Box::new(self.simplify(snd)),
+            ),
+            _ => term.clone(),
+        }
+    }
+}
+
+fn substitute_major(f: &RecFn, plan: &Plan, major: &Term, indices: &[Term], term: &Term) -> Term {
+    let mut term = subst::replace_fvar(term, f.binders[plan.major].0.clone(), major);
+    for (position, index) in plan.index_params.iter().zip(indices) {
+        if let Some(position) = position {
+            term = subst::replace_fvar(&term, f.binders[*position].0.clone(), index);
+        }
+    }
+    term
+}
+
+fn generalised_binders(f: &RecFn, plan: &Plan, major: &Term, indices: &[Term]) -> Vec<Binder> {
+    plan.others
+        .iter()
+        .map(|&i| {
+            let (fvar, info, type_) = &f.binders[i];
+            (fvar.clone(), info.clone(), substitute_major(f, plan, major, indices, type_))
+        })
+        .collect()
+}
+
+fn tuple_type(mut components: Vec<Term>) -> Term {
+    let last = components.pop().unwrap();
+    components.into_iter().rev().fold(last, |rest, component| {
+        Term::Sigma(BinderName::default(), BinderInfo::Explicit, Box::new(component), Box::new(rest))
+    })
+}
+
+fn tuple(type_: &Term, mut components: Vec<Term>) -> Term {
+    if components.len() == 1 {
+        return components.pop().unwrap();
+    }
+    let rest_type = match type_ {
+        Term::Sigma(_, _, _, rest) => (**rest).clone(),
+        _ => type_.clone(),
+    };
+    let first = components.remove(0);
+    Term::Pair(
+        Box::new(type_.clone()),
+        Box::new(first),
+        Box::new(tuple(&rest_type, components)),
+    )
+}
+
+fn project(term: Term, index: usize, len: usize) -> Term {
+    if len == 1 {
+        return term;
+    }
+    let mut term = term;
+    for _ in 0..index {
+        term = Term::Snd(Box::new(term));
+    }
+    if index + 1 < len {
+        term = Term::Fst(Box::new(term));
+    }
+    term
+}
+
+/// Whether `outer` is `term` applied to zero or more arguments.
+fn is_applied_in(term: &Term, outer: &Term) -> bool {
+    term == outer || matches!(outer, Term::App(f, _) if is_applied_in(term, f))
+}
+
+/// Replaces the calls with projections of the induction hypotheses. Calls
+/// `check_calls` accepted always have one; any other is left as it is for
+/// the kernel to reject.
+fn rewrite_calls(term: &Term, fns: &[RecFn], plans: &[Plan], recursive: &[RecursiveField]) -> Term {
+    if let Term::FVar(head) = term.app_head()
+        && let Some(j) = fns.iter().position(|f| f.fvar == *head)
+        && let args = term.app_args()
+        && args.len() == fns[j].binders.len()
+        && let major = args[plans[j].major]
+        && let Term::FVar(field) = major.app_head()
+        && let ys = major.app_args()
+        && let Some(rec) = recursive.iter().find(|rec| rec.field == *field && rec.arity == ys.len())
+    {
+        let mut ih = Term::FVar(rec.ih.clone());
+        for y in ys {
+            ih = Term::mk_app(ih, rewrite_calls(y, fns, plans, recursive));
+        }
+        let mut call = project(ih, j, fns.len());
+        for &i in &plans[j].others {
+            call = Term::mk_app(call, rewrite_calls(args[i], fns, plans, recursive));
+        }
+        return call;
+    }
+
+    let rewrite = |term: &Term| Box::new(rewrite_calls(term, fns, plans, recursive));
+    match term {
+        Term::App(f, a) => Term::App(rewrite(f), rewrite(a)),
+        Term::Lam(name, info, ty, body) => Term::Lam(name.clone(), info.clone(), rewrite(ty), rewrite(body)),
+        Term::Pi(name, info, ty, body) => Term::Pi(name.clone(), info.clone(), rewrite(ty), rewrite(body)),
+        Term::Sigma(name, info, ty, body) => Term::Sigma(name.clone(), info.clone(), rewrite(ty), rewrite(body)),
+        Term::Let(name, ty, val, body) => Term::Let(name.clone(), rewrite(ty), rewrite(val), rewrite(body)),
+        Term::Pair(ty, fst, snd) => Term::Pair(rewrite(ty), rewrite(fst), rewrite(snd)),
+        Term::Fst(p) => Term::Fst(rewrite(p)),
+        Term::Snd(p) => Term::Snd(rewrite(p)),
+        _ => term.clone(),
+    }
+}
@@ -1,28 +1,93 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 
-use crate::{elaboration::{ElabState, subst}, spine::Term};
+use crate::{elaboration::{Declaration, ElabState, subst}, spine::Term};
 
 pub fn whnf(state: &ElabState, term: &Term) -> Term {
     match term {
         Term::App(f, arg) => {
             let f = whnf(state, f);
             match &f {
-                Term::Lam(_, _, body) => {
+                Term::Lam(_, _, _, body) => {
                     whnf(state, &subst::instantiate(body, arg))
                 }
-                _ => Term::App(Box::new(f), arg.clone()),
+                _ => {
+                    let app = Term::App(Box::new(f), arg.clone());
+                    match iota(state, &app) {
+                        Some(reduced) => whnf(state, &reduced),
+                        None => app,
+                    }
+                }
             }
         }
 
-        Term::Let(_, val, body) => {
+        Term::Let(_, _, val, body) => {
             whnf(state, &subst::instantiate(body, val))
         }
 
+        Term::Fst(pair) => match whnf(state, pair) {
+            Term::Pair(_, fst, _) => whnf(state, &fst),
+            pair => Term::Fst(Box::new(pair)),
+        },
+
+        Term::Snd(pair) => match whnf(state, pair) {
+            Term::Pair(_, _, snd) => whnf(state, &snd),
+            pair => Term::Snd(Box::new(pair)),
+        },
+
+        Term::FVar(u) => match state.lctx.lookup(u.clone()).and_then(|decl| decl.value.as_ref()) {
+            Some(val) => whnf(state, val),
+            None => term.clone(),
+        },
+
         Term::MVar(u) => match state.mctx.get_assignment(u.clone()) {
             Some(val) => whnf(state, val),
             None => term.clone(),
         },
 
+        Term::Const(name, levels) => match state.env.lookup(name) {
+            Some(Declaration::Definition {
+                level_params, value, ..
+            }) => whnf(state, &subst::instantiate_level_params(value, level_params, levels)),
+            _ => term.clone(),
+        },
+
         _ => term.clone(),
     }
-}
\ No newline at end of file
+}
+
+/// `I.rec params motive minors indices (c params fields) extra` steps to the
+/// rule for `c` applied to `params motive minors fields extra`.
+fn iota(state: &ElabState, term: &Term) -> Option<Term> {
+    let Term::Const(rec_name, levels) = term.app_head() else {
+        return None;
+    };
+    let Some(Declaration::Recursor {
+        level_params,
+        num_params,
+        num_indices,
+        num_minors,
+        rules,
+        ..
+    }) = state.env.lookup(rec_name)
+    else {
+        return None;
+    };
+
+    let args = term.app_args();
+    let prefix_len = num_params + 1 + num_minors;
+    let major_idx = prefix_len + num_indices;
+    let major = whnf(state, args.get(major_idx)?);
+    let ctor_name = major.app_head().const_name()?;
+    let rule = rules.iter().find(|rule| rule.constructor == *ctor_name)?;
+
+    let ctor_args = major.app_args();
+    let fields = &ctor_args[ctor_args.len().checked_sub(rule.num_fields)?..];
+    let new_args: Vec<Term> = args[..prefix_len]
+        .iter()
+        .chain(fields)
+        .chain(&args[major_idx + 1..])
+        .map(|arg| (*arg).clone())
+        .collect();
+    let rhs = subst::instantiate_level_params(&rule.rhs, level_params, levels);
+    Some(Term::mk_apps(rhs, new_args))
+}
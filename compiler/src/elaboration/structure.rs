@@ -0,0 +1,228 @@
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+
+use crate::{
+    elaboration::{Binder, Declaration, ElabState, err::ElabErrorKind, reduce, subst},
+    module::name::QualifiedName,
+    spine::{BinderInfo, BinderName, Term},
+    syntax::tree::{SyntaxBinder, SyntaxConstructor, SyntaxExpr},
+};
+
+impl ElabState {
+    /// A structure is a single-constructor inductive `S.mk` plus one
+    /// projection `S.field {params} (self : S params)` per field, defined
+    /// through `S.rec`. `self_info` is how projections take `self`.
+    pub(super) fn elaborate_struct(
+        &mut self,
+        name: &str,
+        binders: &[SyntaxBinder],
+        fields: &[SyntaxBinder],
+        self_info: BinderInfo,
+    ) -> Option<QualifiedName> {
+        let ctor = SyntaxConstructor {
+            name: "mk".into(),
+            binders: fields.to_vec(),
+            type_: None,
+        };
+        let ind_name = self.elaborate_inductive(name, binders, None, &[ctor])?;
+        let Some(Declaration::Inductive {
+            type_: ind_type,
+            constructors,
+            recursor,
+            ..
+        }) = self.env.lookup(&ind_name).cloned()
+        else {
+            return None;
+        };
+        let intro_type = constructors
+            .first()
+            .and_then(|ctor| self.env.lookup(ctor))
+            .map(|decl| decl.type_().clone())?;
+
+        let saved_lctx = self.lctx.clone();
+        let (params, _) = self.open_pi(&ind_type, "p");
+        let params: Vec<Binder> = params
+            .into_iter()
+            .map(|(fvar, _, ty)| (fvar, BinderInfo::Implicit, ty))
+            .collect();
+        let param_terms: Vec<Term> = params.iter().map(|(fvar, ..)| Term::FVar(fvar.clone())).collect();
+
+        let mut ctor_type = intro_type;
+        for param in &param_terms {
+            if let Term::Pi(_, _, _, body) = reduce::whnf(self, &ctor_type) {
+                ctor_type = subst::instantiate(&body, param);
+            }
+        }
+
+        let levels = self.inductive_levels();
+        let self_type = Term::mk_apps(Term::Const(ind_name.clone(), levels.clone()), param_terms.iter().cloned());
+        let (self_fvar, self_term) = self.fresh_internal_fvar("self".into(), self_type.clone());
+        let (field_fvars, _) = self.open_pi(&ctor_type, "x");
+
+        let mut proj_binders = params.clone();
+        proj_binders.push((self_fvar.clone(), self_info, self_type.clone()));
+
+        let mut remaining = ctor_type;
+        for ((field_fvar, ..), field) in field_fvars.iter().zip(fields) {
+            let Term::Pi(_, _, field_type, body) = reduce::whnf(self, &remaining) else {
+                break;
+            };
+            let SyntaxBinder::Explicit(field_name, _) = field else {
+                continue;
+            };
+            let proj_name = QualifiedName::User(self.gen_.fresh(format!("{}.{}", self.qualify(name), field_name)));
+
+            let motive = Term::Lam(
+                BinderName(self_fvar.display_name.clone()),
+                BinderInfo::Explicit,
+                Box::new(self_type.clone()),
+                Box::new(subst::abstract_fvar(&field_type, self_fvar.clone())),
+            );
+            let minor = subst::mk_lambda(&field_fvars, Term::FVar(field_fvar.clone()));
+            let mut rec_levels = alloc::vec![self.infer_level(&field_type)];
+            rec_levels.extend(levels.iter().cloned());
+            let body_value = Term::mk_apps(
+                Term::Const(recursor.clone(), rec_levels),
+                param_terms
+                    .iter()
+                    .cloned()
+                    .chain([motive, minor, self_term.clone()]),
+            );
+
+            self.add_definition(
+                proj_name.clone(),
+                &subst::mk_pi(&proj_binders, *field_type),
+                &subst::mk_lambda(&proj_binders, body_value),
+                true,
+            );
+
+            let proj_app = Term::mk_apps(
+                Term::Const(proj_name, levels.clone()),
+                param_terms.iter().cloned().chain([self_term.clone()]),
+            );
+            remaining = subst::instantiate(&body, &proj_app);
+        }
+
+        self.lctx = saved_lctx;
+        Some(ind_name)
+    }
+
+    /// `e.field` where `e : S params` becomes `S.field params e`.
+    pub(super) fn elaborate_proj(&mut self, head: &SyntaxExpr, field: &str) -> (Term, Term) {
+        let (term, type_) = self.elaborate_term_inner(head);
+        let type_ = reduce::whnf(self, &type_);
+
+        if let Term::Sigma(_, _, fst_type, snd_type) = &type_ {
+            match field {
+                "fst" => return (Term::Fst(Box::new(term)), (**fst_type).clone()),
+                "snd" => {
+                    let fst = Term::Fst(Box::new(term.clone()));
+                    return (Term::Snd(Box::new(term)), subst::instantiate(snd_type, &fst));
+                }
+                _ => {}
+            }
+        }
+
+        let structure = match type_.app_head() {
+            Term::Const(name, _) => match self.env.lookup(name) {
+                Some(Declaration::Inductive { name, .. }) => name.display().map(String::from),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(structure) = structure else {
+            self.report(ElabErrorKind::NotAStructure(type_));
+            return (self.erroneous_term(), self.erroneous_term());
+        };
+        let Some(proj) = self
+            .env
+            .lookup_string(&format!("{}.{}", structure, field))
+            .filter(|decl| matches!(decl, Declaration::Definition { .. }))
+        else {
+            self.report(ElabErrorKind::UnknownField {
+                structure,
+                field: field.into(),
+            });
+            return (self.erroneous_term(), self.erroneous_term());
+        };
+
+        let proj_name = proj.name().clone();
+        let levels = match type_.app_head() {
+            Term::Const(_, levels) => levels.clone(),
+            _ => Vec::new(),
+        };
+        let mut proj_type = proj.instantiate_type(&levels);
+        let mut args: Vec<Term> = type_.app_args().into_iter().cloned().collect();
+        args.push(term);
+        for arg in &args {
+            if let Term::Pi(_, _, _, body) = reduce::whnf(self, &proj_type) {
+                proj_type = subst::instantiate(&body, arg);
+            }
+        }
+        (Term::mk_apps(Term::Const(proj_name, levels), args), proj_type)
+    }
+
+    /// `⟨a, b⟩` against an expected type whose head is an inductive with a
+    /// single constructor.
+    pub(super) fn elaborate_anonymous_constructor(&mut self, args: &[SyntaxExpr], expected: &Term) -> Term {
+        let expected_whnf = reduce::whnf(self, expected);
+        let (ctor, levels) = match expected_whnf.app_head() {
+            Term::Const(name, levels) => match self.env.lookup(name) {
+                Some(Declaration::Inductive { constructors, .. }) if constructors.len() == 1 => {
+                    (self.env.lookup(&constructors[0]).cloned(), levels.clone())
+                }
+                _ => (None, Vec::new()),
+            },
+            _ => (None, Vec::new()),
+        };
+        let Some(Declaration::Intro {
+            name,
+            level_params,
+            type_,
+            num_params,
+            num_fields,
+            ..
+        }) = ctor
+        else {
+            self.report(ElabErrorKind::InvalidAnonymousConstructor(expected.clone()));
+            return self.erroneous_term();
+        };
+        if args.len() != num_fields {
+            self.report(ElabErrorKind::AnonymousConstructorArity {
+                expected: num_fields,
+                found: args.len(),
+            });
+            return self.erroneous_term();
+        }
+
+        let params: Vec<Term> = expected_whnf
+            .app_args()
+            .into_iter()
+            .take(num_params)
+            .cloned()
+            .collect();
+        let mut term = Term::Const(name, levels.clone());
+        let mut ctor_type = subst::instantiate_level_params(&type_, &level_params, &levels);
+        for param in params {
+            if let Term::Pi(_, _, _, body) = reduce::whnf(self, &ctor_type) {
+                ctor_type = subst::instantiate(&body, &param);
+            }
+            term = Term::mk_app(term, param);
+        }
+        for arg in args {
+            let Term::Pi(_, _, field_type, body) = reduce::whnf(self, &ctor_type) else {
+                break;
+            };
+            let arg = self.elaborate_term(arg, Some(&field_type));
+            ctor_type = subst::instantiate(&body, &arg);
+            term = Term::mk_app(term, arg);
+        }
+
+        if !self.unify(&ctor_type, expected) {
+            self.report(ElabErrorKind::TypeMismatch {
+                expected: expected.clone(),
+                found: ctor_type,
+            });
+        }
+        term
+    }
+}
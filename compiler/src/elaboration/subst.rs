@@ -1,6 +1,39 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, string::String};
 
-use crate::{module::unique::Unique, spine::Term};
+use crate::{module::{name::QualifiedName, unique::Unique}, spine::{BinderInfo, BinderName, Level, Term}};
+
+pub fn mk_pi(binders: &[(Unique, BinderInfo, Term)], body: Term) -> Term {
+    binders.iter().rev().fold(body, |body, (fvar, info, ty)| {
+        Term::Pi(
+            BinderName(fvar.display_name.clone()),
+            info.clone(),
+            Box::new(ty.clone()),
+            Box::new(abstract_fvar(&body, fvar.clone())),
+        )
+    })
+}
+
+pub fn mk_lambda(binders: &[(Unique, BinderInfo, Term)], body: Term) -> Term {
+    binders.iter().rev().fold(body, |body, (fvar, info, ty)| {
+        Term::Lam(
+            BinderName(fvar.display_name.clone()),
+            info.clone(),
+            Box::new(ty.clone()),
+            Box::new(abstract_fvar(&body, fvar.clone())),
+        )
+    })
+}
+
+pub fn instantiate_level_params(term: &Term, params: &[String], levels: &[Level]) -> Term {
+    if params.is_empty() {
+        return term.clone();
+    }
+    term.replace_levels(&|level| level.instantiate_params(params, levels))
+}
+
+pub fn replace_fvar(term: &Term, fvar: Unique, replacement: &Term) -> Term {
+    instantiate(&abstract_fvar(term, fvar), replacement)
+}
 
 pub fn instantiate(term: &Term, replacement: &Term) -> Term {
     instantiate_at(term, replacement, 0)
@@ -17,31 +50,38 @@ fn instantiate_at(term: &Term, replacement: &Term, depth: usize) -> Term {
                 Term::BVar(*i)
             }
         },
-        Term::Const(_) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => term.clone(),
+        Term::Const(..) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => term.clone(),
         Term::App(f, a) => Term::App(
             Box::new(instantiate_at(f, replacement, depth)),
             Box::new(instantiate_at(a, replacement, depth)),
         ),
-        Term::Lam(info, ty, body) => Term::Lam(
+        Term::Lam(name, info, ty, body) => Term::Lam(name.clone(), 
             info.clone(),
             Box::new(instantiate_at(ty, replacement, depth)),
             Box::new(instantiate_at(body, replacement, depth + 1)),
         ),
-        Term::Pi(info, ty, body) => Term::Pi(
+        Term::Pi(name, info, ty, body) => Term::Pi(name.clone(), 
             info.clone(),
             Box::new(instantiate_at(ty, replacement, depth)),
             Box::new(instantiate_at(body, replacement, depth + 1)),
         ),
-        Term::Sigma(info, ty, body) => Term::Sigma(
+        Term::Sigma(name, info, ty, body) => Term::Sigma(name.clone(), 
             info.clone(),
             Box::new(instantiate_at(ty, replacement, depth)),
             Box::new(instantiate_at(body, replacement, depth + 1)),
         ),
-        Term::Let(ty, val, body) => Term::Let(
+        Term::Let(name, ty, val, body) => Term::Let(name.clone(), 
             Box::new(instantiate_at(ty, replacement, depth)),
             Box::new(instantiate_at(val, replacement, depth)),
             Box::new(instantiate_at(body, replacement, depth + 1)),
         ),
+        Term::Pair(ty, fst, snd) => Term::Pair(
+            Box::new(instantiate_at(ty, replacement, depth)),
+            Box::new(instantiate_at(fst, replacement, depth)),
+            Box::new(instantiate_at(snd, replacement, depth)),
+        ),
+        Term::Fst(p) => Term::Fst(Box::new(instantiate_at(p, replacement, depth))),
+        Term::Snd(p) => Term::Snd(Box::new(instantiate_at(p, replacement, depth))),
     }
 }
 
@@ -62,32 +102,39 @@ fn shift_at(term: &Term, amount: usize, depth: usize) -> Term {
             }
         }
 
-        Term::Const(_) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => term.clone(),
+        Term::Const(..) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => term.clone(),
 
         Term::App(f, a) => Term::App(
             Box::new(shift_at(f, amount, depth)),
             Box::new(shift_at(a, amount, depth)),
         ),
-        Term::Lam(info, ty, body) => Term::Lam(
+        Term::Lam(name, info, ty, body) => Term::Lam(name.clone(), 
             info.clone(),
             Box::new(shift_at(ty, amount, depth)),
             Box::new(shift_at(body, amount, depth + 1)),
         ),
-        Term::Pi(info, ty, body) => Term::Pi(
+        Term::Pi(name, info, ty, body) => Term::Pi(name.clone(), 
             info.clone(),
             Box::new(shift_at(ty, amount, depth)),
             Box::new(shift_at(body, amount, depth + 1)),
         ),
-        Term::Sigma(info, ty, body) => Term::Sigma(
+        Term::Sigma(name, info, ty, body) => Term::Sigma(name.clone(), 
             info.clone(),
             Box::new(shift_at(ty, amount, depth)),
             Box::new(shift_at(body, amount, depth + 1)),
         ),
-        Term::Let(ty, val, body) => Term::Let(
+        Term::Let(name, ty, val, body) => Term::Let(name.clone(), 
             Box::new(shift_at(ty, amount, depth)),
             Box::new(shift_at(val, amount, depth)),
             Box::new(shift_at(body, amount, depth + 1)),
         ),
+        Term::Pair(ty, fst, snd) => Term::Pair(
+            Box::new(shift_at(ty, amount, depth)),
+            Box::new(shift_at(fst, amount, depth)),
+            Box::new(shift_at(snd, amount, depth)),
+        ),
+        Term::Fst(p) => Term::Fst(Box::new(shift_at(p, amount, depth))),
+        Term::Snd(p) => Term::Snd(Box::new(shift_at(p, amount, depth))),
     }
 }
 
@@ -107,31 +154,139 @@ fn abstract_fvar_at(term: &Term, fvar: Unique, depth: usize) -> Term {
             }
         }
 
-        Term::Const(_) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => term.clone(),
+        Term::Const(..) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => term.clone(),
 
         Term::App(f, a) => Term::App(
             Box::new(abstract_fvar_at(f, fvar.clone(), depth)),
             Box::new(abstract_fvar_at(a, fvar, depth)),
         ),
-        Term::Lam(info, ty, body) => Term::Lam(
+        Term::Lam(name, info, ty, body) => Term::Lam(name.clone(), 
             info.clone(),
             Box::new(abstract_fvar_at(ty, fvar.clone(), depth)),
             Box::new(abstract_fvar_at(body, fvar, depth + 1)),
         ),
-        Term::Pi(info, ty, body) => Term::Pi(
+        Term::Pi(name, info, ty, body) => Term::Pi(name.clone(), 
             info.clone(),
             Box::new(abstract_fvar_at(ty, fvar.clone(), depth)),
             Box::new(abstract_fvar_at(body, fvar, depth + 1)),
         ),
-        Term::Sigma(info, ty, body) => Term::Sigma(
+        Term::Sigma(name, info, ty, body) => Term::Sigma(name.clone(), 
             info.clone(),
             Box::new(abstract_fvar_at(ty, fvar.clone(), depth)),
             Box::new(abstract_fvar_at(body, fvar, depth + 1)),
         ),
-        Term::Let(ty, val, body) => Term::Let(
+        Term::Let(name, ty, val, body) => Term::Let(name.clone(), 
             Box::new(abstract_fvar_at(ty, fvar.clone(), depth)),
             Box::new(abstract_fvar_at(val, fvar.clone(), depth)),
             Box::new(abstract_fvar_at(body, fvar, depth + 1)),
         ),
+        Term::Pair(ty, fst, snd) => Term::Pair(
+            Box::new(abstract_fvar_at(ty, fvar.clone(), depth)),
+            Box::new(abstract_fvar_at(fst, fvar.clone(), depth)),
+            Box::new(abstract_fvar_at(snd, fvar, depth)),
+        ),
+        Term::Fst(p) => Term::Fst(Box::new(abstract_fvar_at(p, fvar, depth))),
+        Term::Snd(p) => Term::Snd(Box::new(abstract_fvar_at(p, fvar, depth))),
+    }
+}
+
+pub fn has_fvar(term: &Term, fvar: &Unique) -> bool {
+    match term {
+        Term::FVar(u) => u == fvar,
+        Term::BVar(_) | Term::Const(..) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => false,
+        Term::App(a, b) | Term::Lam(_, _, a, b) | Term::Pi(_, _, a, b) | Term::Sigma(_, _, a, b) => {
+            has_fvar(a, fvar) || has_fvar(b, fvar)
+        }
+        Term::Let(_, a, b, c) | Term::Pair(a, b, c) => {
+            has_fvar(a, fvar) || has_fvar(b, fvar) || has_fvar(c, fvar)
+        }
+        Term::Fst(p) | Term::Snd(p) => has_fvar(p, fvar),
+    }
+}
+
+/// Whether `term` mentions the bound variable with index `index`, counted
+/// from outside `term`.
+pub fn has_loose_bvar(term: &Term, index: usize) -> bool {
+    match term {
+        Term::BVar(i) => *i == index,
+        Term::FVar(_) | Term::Const(..) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => false,
+        Term::App(a, b) => has_loose_bvar(a, index) || has_loose_bvar(b, index),
+        Term::Lam(_, _, a, b) | Term::Pi(_, _, a, b) | Term::Sigma(_, _, a, b) => {
+            has_loose_bvar(a, index) || has_loose_bvar(b, index + 1)
+        }
+        Term::Let(_, a, b, c) => has_loose_bvar(a, index) || has_loose_bvar(b, index) || has_loose_bvar(c, index + 1),
+        Term::Pair(a, b, c) => has_loose_bvar(a, index) || has_loose_bvar(b, index) || has_loose_bvar(c, index),
+        Term::Fst(p) | Term::Snd(p) => has_loose_bvar(p, index),
+    }
+}
+
+/// Calls `f` on every free variable occurrence in `term`, left to right.
+pub fn for_each_fvar(term: &Term, f: &mut impl FnMut(&Unique)) {
+    match term {
+        Term::FVar(u) => f(u),
+        Term::BVar(_) | Term::MVar(_) | Term::Const(..) | Term::Lit(_) | Term::Sort(_) => (),
+        Term::App(a, b) | Term::Lam(_, _, a, b) | Term::Pi(_, _, a, b) | Term::Sigma(_, _, a, b) => {
+            for_each_fvar(a, f);
+            for_each_fvar(b, f);
+        }
+        Term::Let(_, a, b, c) | Term::Pair(a, b, c) => {
+            for_each_fvar(a, f);
+            for_each_fvar(b, f);
+            for_each_fvar(c, f);
+        }
+        Term::Fst(p) | Term::Snd(p) => for_each_fvar(p, f),
+    }
+}
+
+/// Calls `f` on every metavariable occurrence in `term`, left to right.
+pub fn for_each_mvar(term: &Term, f: &mut impl FnMut(&Unique)) {
+    match term {
+        Term::MVar(u) => f(u),
+        Term::BVar(_) | Term::FVar(_) | Term::Const(..) | Term::Lit(_) | Term::Sort(_) => (),
+        Term::App(a, b) | Term::Lam(_, _, a, b) | Term::Pi(_, _, a, b) | Term::Sigma(_, _, a, b) => {
+            for_each_mvar(a, f);
+            for_each_mvar(b, f);
+        }
+        Term::Let(_, a, b, c) | Term::Pair(a, b, c) => {
+            for_each_mvar(a, f);
+            for_each_mvar(b, f);
+            for_each_mvar(c, f);
+        }
+        Term::Fst(p) | Term::Snd(p) => for_each_mvar(p, f),
+    }
+}
+
+/// Calls `f` on every constant occurrence in `term`, left to right.
+pub fn for_each_const(term: &Term, f: &mut impl FnMut(&QualifiedName)) {
+    match term {
+        Term::Const(name, _) => f(name),
+        Term::BVar(_) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) | Term::Sort(_) => (),
+        Term::App(a, b) | Term::Lam(_, _, a, b) | Term::Pi(_, _, a, b) | Term::Sigma(_, _, a, b) => {
+            for_each_const(a, f);
+            for_each_const(b, f);
+        }
+        Term::Let(_, a, b, c) | Term::Pair(a, b, c) => {
+            for_each_const(a, f);
+            for_each_const(b, f);
+            for_each_const(c, f);
+        }
+        Term::Fst(p) | Term::Snd(p) => for_each_const(p, f),
+    }
+}
+
+/// Rebuilds `term`, replacing the metavariables for which `f` returns a term.
+pub fn replace_mvars(term: &Term, f: &impl Fn(&Unique) -> Option<Term>) -> Term {
+    let go = |t: &Term| Box::new(replace_mvars(t, f));
+    match term {
+        Term::MVar(u) => f(u).unwrap_or_else(|| term.clone()),
+        Term::BVar(_) | Term::FVar(_) | Term::Const(..) | Term::Lit(_) | Term::Sort(_) => term.clone(),
+        Term::App(a, b) => Term::App(go(a), go(b)),
+        Term::Lam(name, info, a, b) => Term::Lam(name.clone(), info.clone(), go(a), go(b)),
+        Term::Pi(name, info, a, b) => Term::Pi(name.clone(), info.clone(), go(a), go(b)),
+        Term::Sigma(name, info, a, b) => Term::Sigma(name.clone(), info.clone(), go(a), go(b)),
+        Term::Let(name, a, b, c) => Term::Let(name.clone(), go(a), go(b), go(c)),
+        Term::Pair(a, b, c) => Term::Pair(go(a), go(b), go(c)),
+        Term::Fst(p) => Term::Fst(go(p)),
+        Term::Snd(p) => Term::Snd(go(p)),
     }
 }
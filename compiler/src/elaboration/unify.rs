@@ -1,7 +1,11 @@
 use alloc::boxed::Box;
 
 use crate::{
-    elaboration::{ElabState, reduce::whnf},
+    elaboration::{
+        ElabState,
+        pattern, reduce, subst,
+        nbe::{self, Elim, Env, Head, Value},
+    },
     module::unique::Unique,
     spine::{Level, Term},
 };
@@ -25,34 +29,137 @@ pub fn is_def_eq(state: &mut ElabState, a: &Term, b: &Term) -> bool {
         return true;
     }
 
-    let a = whnf(state, &a);
-    let b = whnf(state, &b);
+    let a = nbe::eval(state, &Env::default(), &a);
+    let b = nbe::eval(state, &Env::default(), &b);
+    unify_values(state, 0, &a, &b)
+}
 
-    if try_assign_mvar(state, &a, &b) {
-        return true;
+/// Unifies two values under `depth` binders. A metavariable at the head of
+/// a neutral is solved by first-order approximation: `?m a` against `f x a`
+/// assigns `f x` to `?m`. When that fails the problem is postponed, as `?m`
+/// may later be assigned something else.
+fn unify_values(state: &mut ElabState, depth: usize, a: &Value, b: &Value) -> bool {
+    let a = nbe::force(state, a);
+    let b = nbe::force(state, b);
+    if !is_flex(&a) && !is_flex(&b) {
+        return unify_forced(state, depth, &a, &b);
     }
-    if try_assign_mvar(state, &b, &a) {
+    let snapshot = state.snapshot();
+    if unify_forced(state, depth, &a, &b) {
         return true;
     }
+    state.restore(snapshot);
+    postpone(state, &a, &b)
+}
+
+/// A metavariable applied to arguments.
+fn is_flex(value: &Value) -> bool {
+    matches!(value, Value::Neutral(Head::MVar(_), spine) if !spine.is_empty())
+}
 
-    match (&a, &b) {
-        (Term::App(f1, a1), Term::App(f2, a2)) => {
-            is_def_eq(state, f1, f2) && is_def_eq(state, a1, a2)
+/// Queues `a =?= b`, unless it mentions variables bound during unification.
+fn postpone(state: &mut ElabState, a: &Value, b: &Value) -> bool {
+    let (Some(a), Some(b)) = (nbe::quote(state, 0, a), nbe::quote(state, 0, b)) else {
+        return false;
+    };
+    state.postpone_unification(a, b);
+    true
+}
+
+fn unify_forced(state: &mut ElabState, depth: usize, a: &Value, b: &Value) -> bool {
+    if let Some(solved) = pattern::solve(state, depth, a, b).or_else(|| pattern::solve(state, depth, b, a)) {
+        return solved;
+    }
+    match (a, b) {
+        (Value::Neutral(h1, s1), Value::Neutral(h2, s2)) if h1.same_head(h2) && s1.len() == s2.len() => {
+            // A definition applied to equal arguments is equal without
+            // unfolding it, but arguments that differ may still unfold to
+            // equal values.
+            let definition = nbe::is_definition(state, h1);
+            let snapshot = definition.then(|| state.snapshot());
+            let levels_unify = match (h1, h2) {
+                (Head::Const(_, l1), Head::Const(_, l2)) => {
+                    l1.iter().zip(l2).all(|(a, b)| state.unify_levels(a, b))
+                }
+                _ => true,
+            };
+            if levels_unify && unify_spines(state, depth, s1, s2) {
+                return true;
+            }
+            match snapshot {
+                Some(snapshot) => {
+                    state.restore(snapshot);
+                    unify_unfolded(state, depth, a, b)
+                }
+                None => false,
+            }
         }
-        (Term::Lam(_, ty1, b1), Term::Lam(_, ty2, b2)) => {
-            is_def_eq(state, ty1, ty2) && is_def_eq(state, b1, b2)
+        (Value::Neutral(Head::MVar(m), s1), Value::Neutral(head, s2))
+        | (Value::Neutral(head, s2), Value::Neutral(Head::MVar(m), s1))
+            if s1.len() <= s2.len() =>
+        {
+            let (prefix, suffix) = s2.split_at(s2.len() - s1.len());
+            let solution = Value::Neutral(head.clone(), prefix.to_vec());
+            assign_value(state, m, &solution) && unify_spines(state, depth, s1, suffix)
         }
-        (Term::Pi(_, ty1, b1), Term::Pi(_, ty2, b2)) => {
-            is_def_eq(state, ty1, ty2) && is_def_eq(state, b1, b2)
+        (Value::Neutral(Head::MVar(m), spine), other) | (other, Value::Neutral(Head::MVar(m), spine))
+            if spine.is_empty() =>
+        {
+            assign_value(state, m, other)
+        }
+        (Value::Sort(l1), Value::Sort(l2)) => state.unify_levels(l1, l2),
+        (Value::Lit(l1), Value::Lit(l2)) => l1 == l2,
+        (Value::Lam(_, d1, c1), Value::Lam(_, d2, c2))
+        | (Value::Pi(_, d1, c1), Value::Pi(_, d2, c2))
+        | (Value::Sigma(_, d1, c1), Value::Sigma(_, d2, c2)) => {
+            unify_values(state, depth, d1, d2) && {
+                let b1 = c1.apply(state, Value::var(depth));
+                let b2 = c2.apply(state, Value::var(depth));
+                unify_values(state, depth + 1, &b1, &b2)
+            }
         }
-        (Term::Sigma(_, ty1, b1), Term::Sigma(_, ty2, b2)) => {
-            is_def_eq(state, ty1, ty2) && is_def_eq(state, b1, b2)
+        (Value::Lam(_, _, closure), other) | (other, Value::Lam(_, _, closure)) => {
+            let body = closure.apply(state, Value::var(depth));
+            let applied = nbe::apply(state, other.clone(), Value::var(depth));
+            unify_values(state, depth + 1, &body, &applied)
         }
-        (Term::Let(ty1, v1, b1), Term::Let(ty2, v2, b2)) => {
-            is_def_eq(state, ty1, ty2) && is_def_eq(state, v1, v2) && is_def_eq(state, b1, b2)
+        (Value::Pair(_, f1, s1), Value::Pair(_, f2, s2)) => {
+            unify_values(state, depth, f1, f2) && unify_values(state, depth, s1, s2)
         }
-        _ => structural_eq(&a, &b),
+        _ => unify_unfolded(state, depth, a, b),
+    }
+}
+
+/// Unifies `a` and `b` after unfolding the definition at the head of
+/// either.
+fn unify_unfolded(state: &mut ElabState, depth: usize, a: &Value, b: &Value) -> bool {
+    if let Some(a) = nbe::unfold(state, a) {
+        return unify_values(state, depth, &a, b);
+    }
+    match nbe::unfold(state, b) {
+        Some(b) => unify_values(state, depth, a, &b),
+        None => false,
+    }
+}
+
+fn unify_spines(state: &mut ElabState, depth: usize, s1: &[Elim], s2: &[Elim]) -> bool {
+    s1.iter().zip(s2).all(|pair| match pair {
+        (Elim::App(a1), Elim::App(a2)) => unify_values(state, depth, a1, a2),
+        (Elim::Fst, Elim::Fst) | (Elim::Snd, Elim::Snd) => true,
+        _ => false,
+    })
+}
+
+/// Assigns `value` to an unassigned `mvar` if it mentions no variable bound
+/// during unification.
+fn assign_value(state: &mut ElabState, mvar: &Unique, value: &Value) -> bool {
+    if state.mctx.is_assigned(mvar.clone()) {
+        return false;
     }
+    let Some(term) = nbe::quote(state, 0, value) else {
+        return false;
+    };
+    try_assign_mvar(state, &Term::MVar(mvar.clone()), &term)
 }
 
 fn structural_eq(a: &Term, b: &Term) -> bool {
@@ -63,19 +170,25 @@ fn structural_eq(a: &Term, b: &Term) -> bool {
         (Term::Lit(l1), Term::Lit(l2)) => l1 == l2,
         (Term::Sort(l1), Term::Sort(l2)) => structural_eq_level(l1, l2),
         (Term::App(f1, a1), Term::App(f2, a2)) => structural_eq(f1, f2) && structural_eq(a1, a2),
-        (Term::Const(n1), Term::Const(n2)) => n1 == n2,
-        (Term::Lam(_, ty1, b1), Term::Lam(_, ty2, b2)) => {
+        (Term::Const(n1, l1), Term::Const(n2, l2)) => {
+            n1 == n2 && l1.len() == l2.len() && l1.iter().zip(l2).all(|(a, b)| structural_eq_level(a, b))
+        }
+        (Term::Lam(_, _, ty1, b1), Term::Lam(_, _, ty2, b2)) => {
             structural_eq(ty1, ty2) && structural_eq(b1, b2)
         }
-        (Term::Pi(_, ty1, b1), Term::Pi(_, ty2, b2)) => {
+        (Term::Pi(_, _, ty1, b1), Term::Pi(_, _, ty2, b2)) => {
             structural_eq(ty1, ty2) && structural_eq(b1, b2)
         }
-        (Term::Sigma(_, ty1, b1), Term::Sigma(_, ty2, b2)) => {
+        (Term::Sigma(_, _, ty1, b1), Term::Sigma(_, _, ty2, b2)) => {
             structural_eq(ty1, ty2) && structural_eq(b1, b2)
         }
-        (Term::Let(ty1, v1, b1), Term::Let(ty2, v2, b2)) => {
+        (Term::Let(_, ty1, v1, b1), Term::Let(_, ty2, v2, b2)) => {
             structural_eq(ty1, ty2) && structural_eq(v1, v2) && structural_eq(b1, b2)
         }
+        (Term::Pair(ty1, a1, b1), Term::Pair(ty2, a2, b2)) => {
+            structural_eq(ty1, ty2) && structural_eq(a1, a2) && structural_eq(b1, b2)
+        }
+        (Term::Fst(p1), Term::Fst(p2)) | (Term::Snd(p1), Term::Snd(p2)) => structural_eq(p1, p2),
         _ => false,
     }
 }
@@ -90,12 +203,13 @@ fn structural_eq_level(a: &Level, b: &Level) -> bool {
         (Level::IMax(a1, a2), Level::IMax(b1, b2)) => {
             structural_eq_level(a1, b1) && structural_eq_level(a2, b2)
         }
+        (Level::Param(a), Level::Param(b)) => a == b,
         (Level::MVar(u1), Level::MVar(u2)) => u1 == u2,
         _ => false,
     }
 }
 
-fn instantiate_mvars(state: &ElabState, term: &Term) -> Term {
+pub(crate) fn instantiate_mvars(state: &ElabState, term: &Term) -> Term {
     match term {
         Term::MVar(u) => {
             if let Some(val) = state.mctx.get_assignment(u.clone()) {
@@ -104,96 +218,135 @@ fn instantiate_mvars(state: &ElabState, term: &Term) -> Term {
                 term.clone()
             }
         }
-        Term::Const(_) | Term::BVar(_) | Term::FVar(_) | Term::Lit(_) => term.clone(),
+        Term::BVar(_) | Term::FVar(_) | Term::Lit(_) => term.clone(),
+        Term::Const(name, levels) => Term::Const(
+            name.clone(),
+            levels.iter().map(|l| instantiate_mvars_level(state, l)).collect(),
+        ),
         Term::Sort(l) => Term::Sort(instantiate_mvars_level(state, l)),
         Term::App(f, a) => Term::App(
             Box::new(instantiate_mvars(state, f)),
             Box::new(instantiate_mvars(state, a)),
         ),
-        Term::Lam(info, ty, body) => Term::Lam(
+        Term::Lam(name, info, ty, body) => Term::Lam(name.clone(), 
             info.clone(),
             Box::new(instantiate_mvars(state, ty)),
             Box::new(instantiate_mvars(state, body)),
         ),
-        Term::Pi(info, ty, body) => Term::Pi(
+        Term::Pi(name, info, ty, body) => Term::Pi(name.clone(), 
             info.clone(),
             Box::new(instantiate_mvars(state, ty)),
             Box::new(instantiate_mvars(state, body)),
         ),
-        Term::Sigma(info, ty, body) => Term::Sigma(
+        Term::Sigma(name, info, ty, body) => Term::Sigma(name.clone(), 
             info.clone(),
             Box::new(instantiate_mvars(state, ty)),
             Box::new(instantiate_mvars(state, body)),
         ),
-        Term::Let(ty, val, body) => Term::Let(
+        Term::Let(name, ty, val, body) => Term::Let(name.clone(), 
             Box::new(instantiate_mvars(state, ty)),
             Box::new(instantiate_mvars(state, val)),
             Box::new(instantiate_mvars(state, body)),
         ),
+        Term::Pair(ty, fst, snd) => Term::Pair(
+            Box::new(instantiate_mvars(state, ty)),
+            Box::new(instantiate_mvars(state, fst)),
+            Box::new(instantiate_mvars(state, snd)),
+        ),
+        Term::Fst(p) => Term::Fst(Box::new(instantiate_mvars(state, p))),
+        Term::Snd(p) => Term::Snd(Box::new(instantiate_mvars(state, p))),
     }
 }
 
-fn instantiate_mvars_level(state: &ElabState, level: &Level) -> Level {
-    match level {
-        Level::Zero => Level::Zero,
-        Level::Succ(l) => Level::Succ(Box::new(instantiate_mvars_level(state, l))),
-        Level::Max(l1, l2) => Level::Max(
-            Box::new(instantiate_mvars_level(state, l1)),
-            Box::new(instantiate_mvars_level(state, l2)),
-        ),
-        Level::IMax(l1, l2) => Level::IMax(
-            Box::new(instantiate_mvars_level(state, l1)),
-            Box::new(instantiate_mvars_level(state, l2)),
-        ),
-        Level::MVar(u) => {
-            // todo: create their table
-            Level::MVar(u.clone())
-        }
-    }
+pub(crate) fn instantiate_mvars_level(state: &ElabState, level: &Level) -> Level {
+    level.replace(&|level| match level {
+        Level::MVar(u) => state
+            .mctx
+            .get_level_assignment(u)
+            .map(|assigned| instantiate_mvars_level(state, assigned)),
+        _ => None,
+    })
 }
 
-fn try_assign_mvar(state: &mut ElabState, a: &Term, b: &Term) -> bool {
+pub(super) fn try_assign_mvar(state: &mut ElabState, a: &Term, b: &Term) -> bool {
     let mvar_a = match a {
         Term::MVar(u) => u.clone(),
         _ => return false,
     };
 
-    if state.mctx.is_assigned(mvar_a.clone()) {
+    if state.mctx.is_assigned(mvar_a.clone()) || is_named_hole(&mvar_a) {
         return false;
     }
 
-    if occurs_in(mvar_a.clone(), b) {
+    if occurs_in(mvar_a.clone(), b) || escapes_scope(state, &mvar_a, b) {
         return false;
     }
 
-    state.mctx.assign(mvar_a, b.clone());
+    state.mctx.assign(mvar_a.clone(), b.clone());
+    unify_assigned_universe(state, mvar_a, b);
     return true;
 }
 
+/// Assigning a type to `?m : Type u` fixes `u` to the universe the type
+/// lives in.
+fn unify_assigned_universe(state: &mut ElabState, mvar: Unique, value: &Term) {
+    let Some(decl_type) = state.mctx.lookup_decl(mvar).map(|decl| decl.type_.clone()) else {
+        return;
+    };
+    let Term::Sort(expected) = reduce::whnf(state, &decl_type) else {
+        return;
+    };
+    let value_type = state.infer_type(value);
+    if let Term::Sort(found) = reduce::whnf(state, &value_type) {
+        state.unify_levels(&expected, &found);
+    }
+}
+
+/// `?name` holes are goals for the user, so unification leaves them alone.
+pub(super) fn is_named_hole(mvar: &Unique) -> bool {
+    mvar.display_name.is_some()
+}
+
+/// Whether `term` mentions a local that isn't in scope where `mvar` was
+/// created.
+fn escapes_scope(state: &ElabState, mvar: &Unique, term: &Term) -> bool {
+    let Some(decl) = state.mctx.lookup_decl(mvar.clone()) else {
+        return false;
+    };
+    let mut escapes = false;
+    subst::for_each_fvar(term, &mut |fvar| escapes |= decl.lctx.lookup(fvar.clone()).is_none());
+    escapes
+}
+
 fn occurs_in(mvar: Unique, term: &Term) -> bool {
     match term {
         Term::MVar(u) => *u == mvar,
         Term::BVar(_) => false,
         Term::FVar(_) => false,
         Term::Lit(_) => false,
-        Term::Const(_) => false,
+        Term::Const(_, levels) => levels.iter().any(|l| occurs_in_level(mvar.clone(), l)),
         Term::Sort(l) => occurs_in_level(mvar, l),
         Term::App(f, a) => occurs_in(mvar.clone(), f) || occurs_in(mvar, a),
-        Term::Lam(_, ty, body) => occurs_in(mvar.clone(), ty) || occurs_in(mvar, body),
-        Term::Pi(_, ty, body) => occurs_in(mvar.clone(), ty) || occurs_in(mvar, body),
-        Term::Sigma(_, ty, body) => occurs_in(mvar.clone(), ty) || occurs_in(mvar, body),
-        Term::Let(ty, val, body) => {
+        Term::Lam(_, _, ty, body) => occurs_in(mvar.clone(), ty) || occurs_in(mvar, body),
+        Term::Pi(_, _, ty, body) => occurs_in(mvar.clone(), ty) || occurs_in(mvar, body),
+        Term::Sigma(_, _, ty, body) => occurs_in(mvar.clone(), ty) || occurs_in(mvar, body),
+        Term::Let(_, ty, val, body) => {
             occurs_in(mvar.clone(), ty) || occurs_in(mvar.clone(), val) || occurs_in(mvar, body)
         }
+        Term::Pair(ty, fst, snd) => {
+            occurs_in(mvar.clone(), ty) || occurs_in(mvar.clone(), fst) || occurs_in(mvar, snd)
+        }
+        Term::Fst(p) | Term::Snd(p) => occurs_in(mvar, p),
     }
 }
 
-fn occurs_in_level(mvar: Unique, level: &Level) -> bool {
+pub(crate) fn occurs_in_level(mvar: Unique, level: &Level) -> bool {
     match level {
         Level::Zero => false,
         Level::Succ(l) => occurs_in_level(mvar, l),
         Level::Max(l1, l2) => occurs_in_level(mvar.clone(), l1) || occurs_in_level(mvar, l2),
         Level::IMax(l1, l2) => occurs_in_level(mvar.clone(), l1) || occurs_in_level(mvar, l2),
+        Level::Param(_) => false,
         Level::MVar(u) => *u == mvar,
     }
 }
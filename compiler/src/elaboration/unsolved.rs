@@ -0,0 +1,63 @@
+//! Reporting the metavariables a command leaves unassigned.
+
+use alloc::vec::Vec;
+
+use crate::{
+    elaboration::{
+        ElabState,
+        ctx::{LocalContext, LocalDecl},
+        err::ElabErrorKind,
+        subst, unify,
+    },
+    module::unique::Unique,
+    spine::Term,
+};
+
+impl ElabState {
+    /// Reports each unassigned metavariable in `terms` with its type and
+    /// local context, returning whether there were any. `?name` holes are
+    /// always reported; other metavariables only when `report_all` is set,
+    /// as after an error they are usually left over from it.
+    pub(super) fn report_unsolved(&mut self, terms: &[&Term], report_all: bool) -> bool {
+        let mut unsolved: Vec<Unique> = Vec::new();
+        for term in terms {
+            let term = unify::instantiate_mvars(self, term);
+            subst::for_each_mvar(&term, &mut |mvar| {
+                if !self.mctx.is_assigned(mvar.clone()) && !unsolved.contains(mvar) {
+                    unsolved.push(mvar.clone());
+                }
+            });
+        }
+
+        let mut reported = false;
+        for mvar in unsolved {
+            let Some(decl) = self.mctx.lookup_decl(mvar.clone()).cloned() else {
+                continue;
+            };
+            if mvar.display_name.is_none() && !report_all {
+                continue;
+            }
+            let context = LocalContext {
+                decls: decl
+                    .lctx
+                    .decls
+                    .iter()
+                    .map(|local| LocalDecl {
+                        fvar: local.fvar.clone(),
+                        type_: unify::instantiate_mvars(self, &local.type_),
+                        value: local.value.as_ref().map(|value| unify::instantiate_mvars(self, value)),
+                        accessible: local.accessible,
+                    })
+                    .collect(),
+            };
+            let kind = ElabErrorKind::UnsolvedGoal {
+                goal: Term::MVar(mvar),
+                type_: unify::instantiate_mvars(self, &decl.type_),
+                context,
+            };
+            self.report_at(decl.span, kind);
+            reported = true;
+        }
+        reported
+    }
+}
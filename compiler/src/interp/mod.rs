@@ -0,0 +1,291 @@
+//! A tree-walking interpreter for the erased IR, which runs programs.
+//!
+//! Evaluation is strict. `Nat`s are machine integers, strings are byte
+//! strings and arrays are vectors. An `IO` action is a value describing an
+//! effect, which only `run_io` performs, so a program is run by evaluating
+//! its `main : IO Unit` and then running the action it evaluates to.
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    format,
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use thiserror::Error;
+
+use crate::{
+    elaboration::Environment,
+    ir::{
+        Expr, Global, Program,
+        lower::{LowerError, lower_environment},
+    },
+    kernel::TypeChecker,
+    module::{
+        name::{IntrinsicName, Name, QualifiedName},
+        prim::{self, PRIM_IO, PRIM_UNIT, PRIM_UNIT_UNIT},
+        unique::UniqueGen,
+    },
+    spine::Term,
+};
+
+/// Where a program's input comes from and its output goes.
+pub trait Console {
+    fn write(&mut self, bytes: &[u8]);
+
+    /// The next line of input without its line break, `None` at the end of
+    /// the input.
+    fn read_line(&mut self) -> Option<Vec<u8>>;
+}
+
+#[derive(Debug, Error)]
+pub enum RuntimeError {
+    #[error("there is no `main` to run")]
+    NoMain,
+    #[error("`main` has type `{0}`, but should have type `IO Unit`")]
+    MainType(Term),
+    #[error("`{0}` has no definition to run")]
+    UnknownGlobal(String),
+    #[error("`Nat` arithmetic overflowed")]
+    Overflow,
+    #[error("evaluation got stuck: {0}")]
+    Stuck(&'static str),
+    #[error("{0}")]
+    Lower(LowerError),
+}
+
+#[derive(Debug, Clone)]
+pub enum Value<'p> {
+    Nat(u64),
+    Str(Rc<[u8]>),
+    Array(Rc<Vec<Value<'p>>>),
+    /// A constructor applied to its fields; its parameters are dropped.
+    Ctor(QualifiedName, Rc<Vec<Value<'p>>>),
+    Pair(Rc<(Value<'p>, Value<'p>)>),
+    Closure(Env<'p>, &'p Expr),
+    /// A constructor, recursor or primitive waiting for more arguments.
+    Partial(QualifiedName, Rc<Vec<Value<'p>>>),
+    Io(Rc<Io<'p>>),
+    Erased,
+}
+
+#[derive(Debug)]
+pub enum Io<'p> {
+    Pure(Value<'p>),
+    Bind(Value<'p>, Value<'p>),
+    /// Writes the string, followed by a line break if asked for.
+    Print(Rc<[u8]>, bool),
+    ReadLine,
+}
+
+/// The values of the variables in scope, innermost first.
+#[derive(Debug, Clone, Default)]
+pub struct Env<'p>(Option<Rc<(Value<'p>, Env<'p>)>>);
+
+impl<'p> Env<'p> {
+    fn push(&self, value: Value<'p>) -> Self {
+        Env(Some(Rc::new((value, self.clone()))))
+    }
+
+    fn get(&self, index: usize) -> Option<&Value<'p>> {
+        let (value, rest) = &**self.0.as_ref()?;
+        match index {
+            0 => Some(value),
+            _ => rest.get(index - 1),
+        }
+    }
+}
+
+/// Runs the `main` of `env`.
+pub fn run_main(env: &Environment, console: &mut dyn Console) -> Result<(), RuntimeError> {
+    let main = env.lookup_name(&Name::parse("main")).ok_or(RuntimeError::NoMain)?;
+    let decl = env.lookup(main).ok_or(RuntimeError::NoMain)?;
+    let type_ = decl.type_();
+    let io_unit = Term::mk_app(Term::Const(PRIM_IO, Vec::new()), Term::Const(PRIM_UNIT, Vec::new()));
+    let mut gen_ = UniqueGen::new(env.module_id.clone());
+    if !TypeChecker::new(env, &mut gen_, decl.level_params()).is_def_eq(type_, &io_unit) {
+        return Err(RuntimeError::MainType(type_.clone()));
+    }
+    let program = lower_environment(env).map_err(RuntimeError::Lower)?;
+    let mut interpreter = Interpreter::new(&program);
+    let action = interpreter.global(main)?;
+    interpreter.run_io(action, console)?;
+    Ok(())
+}
+
+pub struct Interpreter<'p> {
+    program: &'p Program,
+    /// The values of the definitions evaluated so far.
+    defs: BTreeMap<QualifiedName, Value<'p>>,
+}
+
+impl<'p> Interpreter<'p> {
+    pub fn new(program: &'p Program) -> Self {
+        Self {
+            program,
+            defs: Default::default(),
+        }
+    }
+
+    pub fn eval(&mut self, env: &Env<'p>, expr: &'p Expr) -> Result<Value<'p>, RuntimeError> {
+        Ok(match expr {
+            Expr::Var(index) => env.get(*index).cloned().ok_or(RuntimeError::Stuck("unbound variable"))?,
+            Expr::Global(name) => self.global(name)?,
+            Expr::Lam(body) => Value::Closure(env.clone(), body),
+            Expr::App(f, arg) => {
+                let f = self.eval(env, f)?;
+                let arg = self.eval(env, arg)?;
+                self.apply(f, arg)?
+            }
+            Expr::Let(value, body) => {
+                let value = self.eval(env, value)?;
+                self.eval(&env.push(value), body)?
+            }
+            Expr::Nat(n) => Value::Nat(*n),
+            Expr::Str(s) => Value::Str(s.as_slice().into()),
+            Expr::Pair(fst, snd) => Value::Pair(Rc::new((self.eval(env, fst)?, self.eval(env, snd)?))),
+            Expr::Fst(pair) => match self.eval(env, pair)? {
+                Value::Pair(pair) => pair.0.clone(),
+                _ => return Err(RuntimeError::Stuck("projected from a value that isn't a pair")),
+            },
+            Expr::Snd(pair) => match self.eval(env, pair)? {
+                Value::Pair(pair) => pair.1.clone(),
+                _ => return Err(RuntimeError::Stuck("projected from a value that isn't a pair")),
+            },
+            Expr::Erased => Value::Erased,
+        })
+    }
+
+    pub fn global(&mut self, name: &QualifiedName) -> Result<Value<'p>, RuntimeError> {
+        if let Some(value) = self.defs.get(name) {
+            return Ok(value.clone());
+        }
+        let unknown = || RuntimeError::UnknownGlobal(name.display().unwrap_or_default().to_string());
+        let global = self.program.globals.get(name).ok_or_else(unknown)?;
+        if let Global::Def { body, .. } = global {
+            let value = self.eval(&Env::default(), body)?;
+            self.defs.insert(name.clone(), value.clone());
+            return Ok(value);
+        }
+        match global.arity() {
+            0 => self.saturated(name, Vec::new()),
+            _ => Ok(Value::Partial(name.clone(), Rc::new(Vec::new()))),
+        }
+    }
+
+    pub fn apply(&mut self, f: Value<'p>, arg: Value<'p>) -> Result<Value<'p>, RuntimeError> {
+        match f {
+            Value::Closure(env, body) => self.eval(&env.push(arg), body),
+            Value::Partial(name, args) => {
+                let mut args = Rc::unwrap_or_clone(args);
+                args.push(arg);
+                if args.len() == self.program.globals[&name].arity() {
+                    self.saturated(&name, args)
+                } else {
+                    Ok(Value::Partial(name, Rc::new(args)))
+                }
+            }
+            // A type applied to arguments is still a type.
+            Value::Erased => Ok(Value::Erased),
+            _ => Err(RuntimeError::Stuck("applied a value that isn't a function")),
+        }
+    }
+
+    /// Applies a constructor, recursor or primitive to all its arguments.
+    fn saturated(&mut self, name: &QualifiedName, args: Vec<Value<'p>>) -> Result<Value<'p>, RuntimeError> {
+        let program = self.program;
+        match &program.globals[name] {
+            Global::Def { .. } => unreachable!("definitions are evaluated, not applied"),
+            Global::Ctor { num_params, .. } => Ok(Value::Ctor(name.clone(), Rc::new(args[*num_params..].to_vec()))),
+            Global::Rec { num_prefix, rules, .. } => {
+                let Some(Value::Ctor(constructor, fields)) = args.last() else {
+                    return Err(RuntimeError::Stuck("eliminated a value that isn't a constructor"));
+                };
+                let rule = rules
+                    .iter()
+                    .find(|rule| rule.constructor == *constructor)
+                    .ok_or(RuntimeError::Stuck("no recursor rule for the constructor"))?;
+                let mut value = self.eval(&Env::default(), &rule.rhs)?;
+                let prefix = &args[..*num_prefix];
+                for arg in prefix.iter().chain(fields.iter()).cloned() {
+                    value = self.apply(value, arg)?;
+                }
+                Ok(value)
+            }
+            Global::Prim(intrinsic) => primitive(intrinsic, name, args),
+        }
+    }
+
+    /// Performs `action` and the actions it is bound to, returning the
+    /// result of the last.
+    pub fn run_io(&mut self, action: Value<'p>, console: &mut dyn Console) -> Result<Value<'p>, RuntimeError> {
+        // The functions the results of the actions being run are bound to,
+        // innermost last.
+        let mut continuations = Vec::new();
+        let mut current = action;
+        loop {
+            let Value::Io(io) = current else {
+                return Err(RuntimeError::Stuck("ran a value that isn't an IO action"));
+            };
+            let result = match &*io {
+                Io::Pure(value) => value.clone(),
+                Io::Bind(action, continuation) => {
+                    continuations.push(continuation.clone());
+                    current = action.clone();
+                    continue;
+                }
+                Io::Print(s, newline) => {
+                    console.write(s);
+                    if *newline {
+                        console.write(b"\n");
+                    }
+                    unit()
+                }
+                Io::ReadLine => Value::Str(console.read_line().unwrap_or_default().into()),
+            };
+            match continuations.pop() {
+                Some(continuation) => current = self.apply(continuation, result)?,
+                None => return Ok(result),
+            }
+        }
+    }
+}
+
+fn unit<'p>() -> Value<'p> {
+    Value::Ctor(PRIM_UNIT_UNIT, Rc::new(Vec::new()))
+}
+
+fn primitive<'p>(
+    intrinsic: &IntrinsicName,
+    name: &QualifiedName,
+    mut args: Vec<Value<'p>>,
+) -> Result<Value<'p>, RuntimeError> {
+    let io = |io| Ok(Value::Io(Rc::new(io)));
+    match (intrinsic, args.as_slice()) {
+        (IntrinsicName::UnitUnit, []) => Ok(unit()),
+        (IntrinsicName::IOReadLine, []) => io(Io::ReadLine),
+        (IntrinsicName::ArrayNil, []) => Ok(Value::Array(Rc::new(Vec::new()))),
+        (IntrinsicName::ArrayCons, [_, head, Value::Array(tail)]) => {
+            let mut elems = Vec::with_capacity(tail.len() + 1);
+            elems.push(head.clone());
+            elems.extend(tail.iter().cloned());
+            Ok(Value::Array(Rc::new(elems)))
+        }
+        (IntrinsicName::IOPure, [_]) => io(Io::Pure(args.pop().unwrap())),
+        (IntrinsicName::IOBind, [_, _]) => {
+            let continuation = args.pop().unwrap();
+            io(Io::Bind(args.pop().unwrap(), continuation))
+        }
+        (IntrinsicName::IOPrint, [Value::Str(s)]) => io(Io::Print(s.clone(), false)),
+        (IntrinsicName::IOPrintln, [Value::Str(s)]) => io(Io::Print(s.clone(), true)),
+        (IntrinsicName::NatToStr, [Value::Nat(n)]) => Ok(Value::Str(format!("{}", n).as_bytes().into())),
+        (IntrinsicName::StrAppend, [Value::Str(a), Value::Str(b)]) => {
+            Ok(Value::Str([&a[..], &b[..]].concat().into()))
+        }
+        (_, [Value::Nat(a), Value::Nat(b)]) => {
+            prim::eval_nat_binop(name, *a, *b).map(Value::Nat).ok_or(RuntimeError::Overflow)
+        }
+        _ => Err(RuntimeError::Stuck("a primitive got arguments of the wrong kind")),
+    }
+}
@@ -0,0 +1,323 @@
+//! Lowering elaborated declarations to the erased IR.
+//!
+//! Whether something is relevant is decided from types: a term is erased
+//! when its type is a sort or a function type ending in one, and a
+//! parameter when its domain is. The leading parameters of a global are
+//! dropped by its signature, and every application of the global drops the
+//! same arguments; a global applied to fewer arguments than its signature
+//! covers is eta-expanded first. A lambda that isn't a global's keeps all
+//! its parameters, since it may be applied anywhere, and is passed `Erased`
+//! for the irrelevant ones.
+
+use alloc::{
+    boxed::Box,
+    collections::btree_map::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use thiserror::Error;
+
+use crate::{
+    elaboration::{Declaration, Environment},
+    ir::{Expr, Global, Program, Rule, primitive_signature},
+    kernel::TypeChecker,
+    module::{
+        name::{IntrinsicName, QualifiedName},
+        unique::{Unique, UniqueGen},
+    },
+    spine::{Literal, Term},
+};
+
+/// Which leading parameters of each global are relevant.
+pub type Signatures = BTreeMap<QualifiedName, Vec<bool>>;
+
+#[derive(Debug, Error)]
+pub enum LowerError {
+    #[error("`{0}` mentions a local variable that isn't bound in it")]
+    UnboundLocal(String),
+    #[error("`{0}` mentions a metavariable that elaboration left unsolved")]
+    Metavariable(String),
+}
+
+/// Lowers every declaration of `env` that computes something: definitions,
+/// constructors, recursors and primitives. Types and type formers are left
+/// out.
+pub fn lower_environment(env: &Environment) -> Result<Program, LowerError> {
+    let mut gen_ = UniqueGen::new(env.module_id.clone());
+    let signatures = signatures(env, &mut gen_);
+
+    let mut globals = BTreeMap::new();
+    for (name, signature) in &signatures {
+        let relevant = |signature: &[bool]| signature.iter().filter(|keep| **keep).count();
+        let global = match env.lookup(name) {
+            Some(decl @ Declaration::Definition { value, .. }) => {
+                let mut lower = Lower::new(env, &mut gen_, &signatures, decl);
+                Global::Def {
+                    arity: relevant(signature),
+                    body: lower.lambdas(value, signature)?,
+                }
+            }
+            Some(Declaration::Intro { num_params, .. }) => Global::Ctor {
+                num_params: relevant(&signature[..*num_params]),
+                num_fields: relevant(&signature[*num_params..]),
+            },
+            Some(
+                decl @ Declaration::Recursor {
+                    num_params,
+                    num_minors,
+                    rules,
+                    ..
+                },
+            ) => {
+                let prefix = &signature[..num_params + 1 + num_minors];
+                let mut lowered = Vec::new();
+                for rule in rules {
+                    let ctor_signature = &signatures[&rule.constructor];
+                    let fields = &ctor_signature[ctor_signature.len() - rule.num_fields..];
+                    let rhs_signature: Vec<bool> = prefix.iter().chain(fields).copied().collect();
+                    let mut lower = Lower::new(env, &mut gen_, &signatures, decl);
+                    lowered.push(Rule {
+                        constructor: rule.constructor.clone(),
+                        num_fields: relevant(fields),
+                        rhs: lower.lambdas(&rule.rhs, &rhs_signature)?,
+                    });
+                }
+                Global::Rec {
+                    num_prefix: relevant(prefix),
+                    arity: relevant(signature),
+                    rules: lowered,
+                }
+            }
+            _ => match name {
+                QualifiedName::Intrinsic(intrinsic) => Global::Prim(intrinsic.clone()),
+                _ => unreachable!("only computing declarations have signatures"),
+            },
+        };
+        globals.insert(name.clone(), global);
+    }
+    Ok(Program { globals })
+}
+
+/// The signatures of the declarations of `env` that compute something, and
+/// of every primitive that isn't a type.
+pub fn signatures(env: &Environment, gen_: &mut UniqueGen) -> Signatures {
+    let mut signatures = Signatures::new();
+    for decl in env.decls.values() {
+        let mut checker = TypeChecker::new(env, gen_, decl.level_params());
+        if is_type_former_type(&mut checker, decl.type_()) {
+            continue;
+        }
+        let signature = match decl {
+            Declaration::Definition { value, .. } => lambda_signature(&mut checker, value, usize::MAX),
+            Declaration::Intro { type_, .. } | Declaration::Recursor { type_, .. } => pi_signature(&mut checker, type_),
+            Declaration::Constructor { .. } | Declaration::Inductive { .. } => continue,
+        };
+        signatures.insert(decl.name().clone(), signature);
+    }
+    // Some primitives are typed by the kernel itself, so they aren't all
+    // declared.
+    for intrinsic in IntrinsicName::ALL {
+        if let Some(signature) = primitive_signature(&intrinsic) {
+            signatures.insert(QualifiedName::Intrinsic(intrinsic), signature.to_vec());
+        }
+    }
+    signatures
+}
+
+/// Which of the parameters of a function of type `type_` are relevant.
+fn pi_signature(checker: &mut TypeChecker, type_: &Term) -> Vec<bool> {
+    let mut signature = Vec::new();
+    let mut current = checker.whnf(type_);
+    while let Term::Pi(_, _, domain, body) = current {
+        signature.push(!is_type_former_type(checker, &domain));
+        let (_, body) = checker.open(&domain, &body);
+        current = checker.whnf(&body);
+    }
+    for _ in &signature {
+        checker.close();
+    }
+    signature
+}
+
+/// Which of the (at most `max`) leading lambdas of `value` bind something
+/// relevant.
+fn lambda_signature(checker: &mut TypeChecker, value: &Term, max: usize) -> Vec<bool> {
+    let mut signature = Vec::new();
+    let mut current = value.clone();
+    while signature.len() < max
+        && let Term::Lam(_, _, domain, body) = current
+    {
+        signature.push(!is_type_former_type(checker, &domain));
+        let (_, body) = checker.open(&domain, &body);
+        current = body;
+    }
+    for _ in &signature {
+        checker.close();
+    }
+    signature
+}
+
+/// Whether the inhabitants of `type_` are types or type families.
+pub fn is_type_former_type(checker: &mut TypeChecker, type_: &Term) -> bool {
+    match checker.whnf(type_) {
+        Term::Sort(_) => true,
+        Term::Pi(_, _, domain, body) => {
+            let (_, body) = checker.open(&domain, &body);
+            let result = is_type_former_type(checker, &body);
+            checker.close();
+            result
+        }
+        _ => false,
+    }
+}
+
+struct Lower<'a> {
+    checker: TypeChecker<'a>,
+    signatures: &'a Signatures,
+    /// The declaration being lowered, for errors.
+    decl: &'a QualifiedName,
+    /// The locals bound by the enclosing `Lam`s and `Let`s, innermost last.
+    /// Eta-expansion binds parameters no local stands for.
+    scope: Vec<Option<Unique>>,
+    /// The locals of the dropped parameters.
+    dropped: Vec<Unique>,
+}
+
+impl<'a> Lower<'a> {
+    fn new(env: &'a Environment, gen_: &'a mut UniqueGen, signatures: &'a Signatures, decl: &'a Declaration) -> Self {
+        Self {
+            checker: TypeChecker::new(env, gen_, decl.level_params()),
+            signatures,
+            decl: decl.name(),
+            scope: Vec::new(),
+            dropped: Vec::new(),
+        }
+    }
+
+    /// Lowers `term` under its leading lambdas, keeping the ones `signature`
+    /// keeps.
+    fn lambdas(&mut self, term: &Term, signature: &[bool]) -> Result<Expr, LowerError> {
+        let Some((keep, rest)) = signature.split_first() else {
+            return self.lower(term);
+        };
+        let Term::Lam(_, _, domain, body) = term else {
+            unreachable!("signatures count the leading lambdas")
+        };
+        let (fvar, body) = self.checker.open(domain, body);
+        let result = if *keep {
+            self.scope.push(Some(fvar));
+            let body = self.lambdas(&body, rest);
+            self.scope.pop();
+            body.map(|body| Expr::Lam(Box::new(body)))
+        } else {
+            self.dropped.push(fvar);
+            let body = self.lambdas(&body, rest);
+            self.dropped.pop();
+            body
+        };
+        self.checker.close();
+        result
+    }
+
+    fn lower(&mut self, term: &Term) -> Result<Expr, LowerError> {
+        if self.is_irrelevant(term) {
+            return Ok(Expr::Erased);
+        }
+        Ok(match term {
+            Term::FVar(fvar) => {
+                if let Some(position) = self.scope.iter().rposition(|local| local.as_ref() == Some(fvar)) {
+                    Expr::Var(self.scope.len() - 1 - position)
+                } else if self.dropped.contains(fvar) {
+                    Expr::Erased
+                } else {
+                    return Err(self.unbound_local());
+                }
+            }
+            Term::BVar(_) => return Err(self.unbound_local()),
+            Term::MVar(_) => return Err(LowerError::Metavariable(self.decl_name())),
+            Term::Const(..) | Term::App(..) => self.app(term)?,
+            Term::Lam(_, _, domain, body) => {
+                let (fvar, body) = self.checker.open(domain, body);
+                self.scope.push(Some(fvar));
+                let body = self.lower(&body);
+                self.scope.pop();
+                self.checker.close();
+                Expr::Lam(Box::new(body?))
+            }
+            Term::Let(_, type_, value, body) => {
+                let value = self.lower(value)?;
+                let (fvar, body) = self.checker.open(type_, body);
+                self.scope.push(Some(fvar));
+                let body = self.lower(&body);
+                self.scope.pop();
+                self.checker.close();
+                Expr::Let(Box::new(value), Box::new(body?))
+            }
+            Term::Lit(Literal::Nat(n)) => Expr::Nat(*n),
+            Term::Lit(Literal::Str(s)) => Expr::Str(s.as_bytes().to_vec()),
+            Term::Pair(_, fst, snd) => Expr::Pair(Box::new(self.lower(fst)?), Box::new(self.lower(snd)?)),
+            Term::Fst(pair) => Expr::Fst(Box::new(self.lower(pair)?)),
+            Term::Snd(pair) => Expr::Snd(Box::new(self.lower(pair)?)),
+            Term::Sort(_) | Term::Pi(..) | Term::Sigma(..) => Expr::Erased,
+        })
+    }
+
+    fn is_irrelevant(&mut self, term: &Term) -> bool {
+        match self.checker.infer(term) {
+            Ok(type_) => is_type_former_type(&mut self.checker, &type_),
+            Err(_) => false,
+        }
+    }
+
+    fn app(&mut self, term: &Term) -> Result<Expr, LowerError> {
+        let head = term.app_head();
+        let args = term.app_args();
+        let Some((name, signature)) = head
+            .const_name()
+            .and_then(|name| Some((name, self.signatures.get(name)?)))
+        else {
+            // Constants without a signature are type formers.
+            let mut result = match head {
+                Term::Const(..) => Expr::Erased,
+                _ => self.lower(head)?,
+            };
+            for arg in args {
+                result = Expr::App(Box::new(result), Box::new(self.lower(arg)?));
+            }
+            return Ok(result);
+        };
+
+        // Parameters eta-expansion adds for the arguments that are missing.
+        let missing = &signature[signature.len().min(args.len())..];
+        self.scope.extend(missing.iter().map(|_| None));
+        let mut result = Ok(Expr::Global(name.clone()));
+        for (arg, keep) in args.iter().zip(signature) {
+            if *keep {
+                result = result.and_then(|f| Ok(Expr::App(Box::new(f), Box::new(self.lower(arg)?))));
+            }
+        }
+        self.scope.truncate(self.scope.len() - missing.len());
+        let mut result = result?;
+        for (i, keep) in missing.iter().enumerate() {
+            if *keep {
+                result = Expr::App(Box::new(result), Box::new(Expr::Var(missing.len() - 1 - i)));
+            }
+        }
+        for _ in missing {
+            result = Expr::Lam(Box::new(result));
+        }
+        for arg in args.iter().skip(signature.len()) {
+            result = Expr::App(Box::new(result), Box::new(self.lower(arg)?));
+        }
+        Ok(result)
+    }
+
+    fn unbound_local(&self) -> LowerError {
+        LowerError::UnboundLocal(self.decl_name())
+    }
+
+    fn decl_name(&self) -> String {
+        self.decl.display().unwrap_or_default().to_string()
+    }
+}
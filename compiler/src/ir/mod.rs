@@ -0,0 +1,100 @@
+//! The erased intermediate representation programs are run from.
+//!
+//! Erasure replaces every type by `Expr::Erased`, and drops the parameters
+//! of globals that carry types along with the arguments passed for them.
+//! Constructors, recursors and primitives are globals the interpreter knows
+//! how to apply.
+
+pub mod lower;
+
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, vec::Vec};
+
+use crate::module::name::{IntrinsicName, QualifiedName};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A de Bruijn index of an enclosing `Lam` or `Let`.
+    Var(usize),
+    Global(QualifiedName),
+    Lam(Box<Expr>),
+    App(Box<Expr>, Box<Expr>),
+    /// `Let(value, body)`.
+    Let(Box<Expr>, Box<Expr>),
+    Nat(u64),
+    Str(Vec<u8>),
+    Pair(Box<Expr>, Box<Expr>),
+    Fst(Box<Expr>),
+    Snd(Box<Expr>),
+    /// A type, which nothing computes with.
+    Erased,
+}
+
+/// The counts below are of relevant parameters only.
+#[derive(Debug, Clone)]
+pub enum Global {
+    /// A definition whose leading `arity` lambdas are its parameters.
+    Def { arity: usize, body: Expr },
+    /// A constructor of an inductive, applied to the inductive's parameters
+    /// and then its fields.
+    Ctor { num_params: usize, num_fields: usize },
+    /// A recursor, applied to the inductive's parameters, the minor
+    /// premises, the indices and then the major premise.
+    Rec {
+        /// How many arguments come before the indices.
+        num_prefix: usize,
+        arity: usize,
+        rules: Vec<Rule>,
+    },
+    Prim(IntrinsicName),
+}
+
+impl Global {
+    pub fn arity(&self) -> usize {
+        match self {
+            Global::Def { arity, .. } | Global::Rec { arity, .. } => *arity,
+            Global::Ctor { num_params, num_fields } => num_params + num_fields,
+            Global::Prim(intrinsic) => primitive_signature(intrinsic).map_or(0, |signature| {
+                signature.iter().filter(|keep| **keep).count()
+            }),
+        }
+    }
+}
+
+/// See `RecursorRule`: `rhs` takes the recursor's arguments before the
+/// indices followed by the constructor's fields.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub constructor: QualifiedName,
+    pub num_fields: usize,
+    pub rhs: Expr,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub globals: BTreeMap<QualifiedName, Global>,
+}
+
+/// Which parameters of a primitive are relevant, or `None` for the
+/// primitive types.
+pub fn primitive_signature(intrinsic: &IntrinsicName) -> Option<&'static [bool]> {
+    Some(match intrinsic {
+        IntrinsicName::Nat
+        | IntrinsicName::Str
+        | IntrinsicName::Fin
+        | IntrinsicName::Array
+        | IntrinsicName::Unit
+        | IntrinsicName::IO => return None,
+        IntrinsicName::UnitUnit | IntrinsicName::IOReadLine => &[],
+        IntrinsicName::ArrayNil => &[false],
+        IntrinsicName::IOPrint | IntrinsicName::IOPrintln | IntrinsicName::NatToStr => &[true],
+        IntrinsicName::NatAdd
+        | IntrinsicName::NatSub
+        | IntrinsicName::NatMul
+        | IntrinsicName::NatDiv
+        | IntrinsicName::NatMod
+        | IntrinsicName::StrAppend => &[true, true],
+        IntrinsicName::IOPure => &[false, true],
+        IntrinsicName::ArrayCons => &[false, true, true, true],
+        IntrinsicName::IOBind => &[false, false, true, true],
+    })
+}
@@ -0,0 +1,165 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{
+    elaboration::{Declaration, subst},
+    kernel::TypeChecker,
+    module::prim,
+    spine::{Literal, Term},
+};
+
+impl TypeChecker<'_> {
+    /// Weak head normal form including delta: definitions at the head are
+    /// unfolded until the head is stuck.
+    pub fn whnf(&mut self, term: &Term) -> Term {
+        let mut current = self.whnf_core(term);
+        while let Some(unfolded) = self.unfold(&current) {
+            current = self.whnf_core(&unfolded);
+        }
+        current
+    }
+
+    /// Beta, zeta, projection and iota reduction at the head, without
+    /// unfolding definitions at the head.
+    fn whnf_core(&mut self, term: &Term) -> Term {
+        match term {
+            Term::App(f, arg) => match self.whnf_core(f) {
+                Term::Lam(_, _, _, body) => self.whnf_core(&subst::instantiate(&body, arg)),
+                f => {
+                    let app = Term::App(Box::new(f), arg.clone());
+                    match self.iota(&app) {
+                        Some(reduced) => self.whnf_core(&reduced),
+                        None => self.nat_binop(&app).unwrap_or(app),
+                    }
+                }
+            },
+            Term::Let(_, _, value, body) => self.whnf_core(&subst::instantiate(body, value)),
+            Term::Fst(pair) => match self.whnf(pair) {
+                Term::Pair(_, fst, _) => self.whnf_core(&fst),
+                pair => Term::Fst(Box::new(pair)),
+            },
+            Term::Snd(pair) => match self.whnf(pair) {
+                Term::Pair(_, _, snd) => self.whnf_core(&snd),
+                pair => Term::Snd(Box::new(pair)),
+            },
+            _ => term.clone(),
+        }
+    }
+
+    /// Replaces a definition at the head of an application by its value.
+    fn unfold(&self, term: &Term) -> Option<Term> {
+        let Term::Const(name, levels) = term.app_head() else {
+            return None;
+        };
+        let Some(Declaration::Definition {
+            level_params, value, ..
+        }) = self.env.lookup(name)
+        else {
+            return None;
+        };
+        let value = subst::instantiate_level_params(value, level_params, levels);
+        let args = term.app_args().into_iter().cloned();
+        Some(Term::mk_apps(value, args))
+    }
+
+    /// `I.rec params motive minors indices (c params fields) extra` steps to
+    /// the rule for `c` applied to `params motive minors fields extra`.
+    fn iota(&mut self, term: &Term) -> Option<Term> {
+        let Term::Const(rec_name, levels) = term.app_head() else {
+            return None;
+        };
+        let Some(Declaration::Recursor {
+            level_params,
+            num_params,
+            num_indices,
+            num_minors,
+            rules,
+            ..
+        }) = self.env.lookup(rec_name)
+        else {
+            return None;
+        };
+
+        let args = term.app_args();
+        let prefix_len = num_params + 1 + num_minors;
+        let major_idx = prefix_len + num_indices;
+        let major = self.whnf(args.get(major_idx)?);
+        let ctor_name = major.app_head().const_name()?;
+        let rule = rules.iter().find(|rule| rule.constructor == *ctor_name)?;
+
+        let ctor_args = major.app_args();
+        let fields = &ctor_args[ctor_args.len().checked_sub(rule.num_fields)?..];
+        let new_args: Vec<Term> = args[..prefix_len]
+            .iter()
+            .chain(fields)
+            .chain(&args[major_idx + 1..])
+            .map(|arg| (*arg).clone())
+            .collect();
+        let rhs = subst::instantiate_level_params(&rule.rhs, level_params, levels);
+        Some(Term::mk_apps(rhs, new_args))
+    }
+
+    /// `Nat.add 2 3` steps to `5` once both arguments reduce to literals.
+    fn nat_binop(&mut self, term: &Term) -> Option<Term> {
+        let name = term.app_head().const_name()?;
+        let [a, b] = term.app_args()[..] else {
+            return None;
+        };
+        let (Term::Lit(Literal::Nat(a)), Term::Lit(Literal::Nat(b))) = (self.whnf(a), self.whnf(b)) else {
+            return None;
+        };
+        prim::eval_nat_binop(name, a, b).map(|n| Term::Lit(Literal::Nat(n)))
+    }
+
+    /// Definitional equality up to beta, delta, zeta, iota and eta. Heads are
+    /// compared structurally first and definitions only unfolded when that
+    /// fails.
+    pub fn is_def_eq(&mut self, a: &Term, b: &Term) -> bool {
+        if a == b {
+            return true;
+        }
+        let a = self.whnf_core(a);
+        let b = self.whnf_core(b);
+        if self.is_def_eq_congruence(&a, &b) {
+            return true;
+        }
+        match (self.unfold(&a), self.unfold(&b)) {
+            (Some(a), Some(b)) => self.is_def_eq(&a, &b),
+            (Some(a), None) => self.is_def_eq(&a, &b),
+            (None, Some(b)) => self.is_def_eq(&a, &b),
+            (None, None) => self.is_def_eq_eta(&a, &b) || self.is_def_eq_eta(&b, &a),
+        }
+    }
+
+    fn is_def_eq_congruence(&mut self, a: &Term, b: &Term) -> bool {
+        match (a, b) {
+            (Term::Sort(l1), Term::Sort(l2)) => l1.is_equivalent(l2),
+            (Term::Const(n1, l1), Term::Const(n2, l2)) => {
+                n1 == n2 && l1.len() == l2.len() && l1.iter().zip(l2).all(|(a, b)| a.is_equivalent(b))
+            }
+            (Term::Lam(_, _, d1, b1), Term::Lam(_, _, d2, b2))
+            | (Term::Pi(_, _, d1, b1), Term::Pi(_, _, d2, b2))
+            | (Term::Sigma(_, _, d1, b1), Term::Sigma(_, _, d2, b2)) => {
+                self.is_def_eq(d1, d2) && {
+                    let x = Term::FVar(self.gen_.fresh_unnamed());
+                    self.is_def_eq(&subst::instantiate(b1, &x), &subst::instantiate(b2, &x))
+                }
+            }
+            (Term::App(f1, a1), Term::App(f2, a2)) => self.is_def_eq(f1, f2) && self.is_def_eq(a1, a2),
+            (Term::Pair(_, f1, s1), Term::Pair(_, f2, s2)) => self.is_def_eq(f1, f2) && self.is_def_eq(s1, s2),
+            (Term::Fst(p1), Term::Fst(p2)) | (Term::Snd(p1), Term::Snd(p2)) => self.is_def_eq(p1, p2),
+            _ => a == b,
+        }
+    }
+
+    /// `λ x. f x` is equal to `f`.
+    fn is_def_eq_eta(&mut self, lam: &Term, other: &Term) -> bool {
+        let Term::Lam(_, _, _, body) = lam else {
+            return false;
+        };
+        if matches!(other, Term::Lam(..)) {
+            return false;
+        }
+        let x = Term::FVar(self.gen_.fresh_unnamed());
+        self.is_def_eq(&subst::instantiate(body, &x), &Term::mk_app(other.clone(), x))
+    }
+}
@@ -0,0 +1,32 @@
+use alloc::string::String;
+use thiserror::Error;
+
+use crate::spine::Term;
+
+#[derive(Debug, Error)]
+pub enum KernelError {
+    #[error("unknown constant `{0}`")]
+    UnknownConstant(Term),
+    #[error("unknown free variable `{0}`")]
+    UnknownFVar(Term),
+    #[error("unassigned metavariable `{0}`")]
+    UnassignedMVar(Term),
+    #[error("loose bound variable `{0}`")]
+    LooseBVar(Term),
+    #[error("universe level of `{0}` contains a metavariable")]
+    LevelMVar(Term),
+    #[error("universe parameter `{param}` in `{term}` is not bound by the declaration")]
+    UnknownLevelParam { term: Term, param: String },
+    #[error("`{term}` expects {expected} universe levels")]
+    LevelCount { term: Term, expected: usize },
+    #[error("expected a type, found `{term}` of type `{type_}`")]
+    ExpectedSort { term: Term, type_: Term },
+    #[error("expected a function, found `{term}` of type `{type_}`")]
+    ExpectedPi { term: Term, type_: Term },
+    #[error("expected a pair, found `{term}` of type `{type_}`")]
+    ExpectedSigma { term: Term, type_: Term },
+    #[error("`{0}` is not a fully applied array constructor")]
+    InvalidArrayCons(Term),
+    #[error("`{term}` has type `{found}` but is expected to have type `{expected}`")]
+    TypeMismatch { term: Term, expected: Term, found: Term },
+}
@@ -0,0 +1,260 @@
+//! A small trusted type checker that re-checks every definition produced by
+//! the elaborator before it is admitted to the environment. It shares only
+//! the term representation and substitution with the elaborator; metavariables
+//! and local contexts of the elaborator are not visible here.
+
+pub mod defeq;
+pub mod err;
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use crate::{
+    elaboration::{Declaration, Environment, subst},
+    kernel::err::KernelError,
+    module::{
+        prim::{PRIM_ARRAY, PRIM_ARRAY_CONS, PRIM_ARRAY_NIL, PRIM_NAT, PRIM_STRING},
+        unique::{Unique, UniqueGen},
+    },
+    spine::{BinderInfo, BinderName, Level, Literal, Term},
+};
+
+pub struct TypeChecker<'a> {
+    env: &'a Environment,
+    gen_: &'a mut UniqueGen,
+    /// The universe parameters of the declaration being checked.
+    level_params: &'a [String],
+    locals: Vec<(Unique, Term)>,
+}
+
+/// Checks that a definition's type is a type and its value inhabits it.
+/// Other declarations are produced by trusted code in the elaborator.
+pub fn check_declaration(env: &Environment, gen_: &mut UniqueGen, decl: &Declaration) -> Result<(), KernelError> {
+    let Declaration::Definition {
+        level_params,
+        type_,
+        value,
+        ..
+    } = decl
+    else {
+        return Ok(());
+    };
+    let mut checker = TypeChecker::new(env, gen_, level_params);
+    checker.ensure_type(type_)?;
+    checker.check(value, type_)
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(env: &'a Environment, gen_: &'a mut UniqueGen, level_params: &'a [String]) -> Self {
+        Self {
+            env,
+            gen_,
+            level_params,
+            locals: Vec::new(),
+        }
+    }
+
+    pub fn infer(&mut self, term: &Term) -> Result<Term, KernelError> {
+        match term {
+            Term::BVar(_) => Err(KernelError::LooseBVar(term.clone())),
+            Term::FVar(fvar) => self
+                .locals
+                .iter()
+                .rev()
+                .find(|(local, _)| local == fvar)
+                .map(|(_, type_)| type_.clone())
+                .ok_or_else(|| KernelError::UnknownFVar(term.clone())),
+            Term::MVar(_) => Err(KernelError::UnassignedMVar(term.clone())),
+            Term::Sort(level) => {
+                self.check_level(term, level)?;
+                Ok(Term::Sort(level.clone().succ()))
+            }
+            Term::Const(name, levels) => {
+                for level in levels {
+                    self.check_level(term, level)?;
+                }
+                if *name == PRIM_ARRAY_NIL
+                    && let [level] = &levels[..]
+                {
+                    return Ok(array_nil_type(level));
+                }
+                let decl = self
+                    .env
+                    .lookup(name)
+                    .ok_or_else(|| KernelError::UnknownConstant(term.clone()))?;
+                if decl.level_params().len() != levels.len() {
+                    return Err(KernelError::LevelCount {
+                        term: term.clone(),
+                        expected: decl.level_params().len(),
+                    });
+                }
+                Ok(decl.instantiate_type(levels))
+            }
+            Term::App(..) if term.app_head().const_name() == Some(&PRIM_ARRAY_CONS) => self.infer_array_cons(term),
+            Term::App(f, arg) => {
+                let f_type = self.infer(f)?;
+                match self.whnf(&f_type) {
+                    Term::Pi(_, _, domain, body) => {
+                        self.check(arg, &domain)?;
+                        Ok(subst::instantiate(&body, arg))
+                    }
+                    type_ => Err(KernelError::ExpectedPi {
+                        term: (**f).clone(),
+                        type_,
+                    }),
+                }
+            }
+            Term::Lam(name, info, domain, body) => {
+                self.ensure_type(domain)?;
+                let (fvar, body) = self.open(domain, body);
+                let body_type = self.infer(&body);
+                self.locals.pop();
+                Ok(Term::Pi(
+                    name.clone(),
+                    info.clone(),
+                    domain.clone(),
+                    Box::new(subst::abstract_fvar(&body_type?, fvar)),
+                ))
+            }
+            Term::Pi(_, _, domain, body) | Term::Sigma(_, _, domain, body) => {
+                let domain_level = self.ensure_type(domain)?;
+                let (_, body) = self.open(domain, body);
+                let body_level = self.ensure_type(&body);
+                self.locals.pop();
+                Ok(Term::Sort(Level::max(domain_level, body_level?)))
+            }
+            Term::Let(_, type_, value, body) => {
+                self.ensure_type(type_)?;
+                self.check(value, type_)?;
+                self.infer(&subst::instantiate(body, value))
+            }
+            Term::Lit(Literal::Nat(_)) => Ok(Term::Const(PRIM_NAT, Vec::new())),
+            Term::Lit(Literal::Str(_)) => Ok(Term::Const(PRIM_STRING, Vec::new())),
+            Term::Pair(sigma, fst, snd) => {
+                self.ensure_type(sigma)?;
+                let Term::Sigma(_, _, fst_type, snd_type) = self.whnf(sigma) else {
+                    return Err(KernelError::ExpectedSigma {
+                        term: term.clone(),
+                        type_: (**sigma).clone(),
+                    });
+                };
+                self.check(fst, &fst_type)?;
+                self.check(snd, &subst::instantiate(&snd_type, fst))?;
+                Ok((**sigma).clone())
+            }
+            Term::Fst(pair) | Term::Snd(pair) => {
+                let pair_type = self.infer(pair)?;
+                match (term, self.whnf(&pair_type)) {
+                    (Term::Fst(_), Term::Sigma(_, _, fst_type, _)) => Ok(*fst_type),
+                    (_, Term::Sigma(_, _, _, snd_type)) => {
+                        Ok(subst::instantiate(&snd_type, &Term::Fst(pair.clone())))
+                    }
+                    (_, type_) => Err(KernelError::ExpectedSigma {
+                        term: (**pair).clone(),
+                        type_,
+                    }),
+                }
+            }
+        }
+    }
+
+    pub fn check(&mut self, term: &Term, expected: &Term) -> Result<(), KernelError> {
+        let found = self.infer(term)?;
+        if self.is_def_eq(&found, expected) {
+            Ok(())
+        } else {
+            Err(KernelError::TypeMismatch {
+                term: term.clone(),
+                expected: expected.clone(),
+                found,
+            })
+        }
+    }
+
+    /// Checks that `term` is a type and returns its universe level.
+    pub fn ensure_type(&mut self, term: &Term) -> Result<Level, KernelError> {
+        let type_ = self.infer(term)?;
+        match self.whnf(&type_) {
+            Term::Sort(level) => Ok(level),
+            type_ => Err(KernelError::ExpectedSort {
+                term: term.clone(),
+                type_,
+            }),
+        }
+    }
+
+    /// Levels may only mention the universe parameters of the declaration.
+    fn check_level(&self, term: &Term, level: &Level) -> Result<(), KernelError> {
+        if level.has_mvar() {
+            return Err(KernelError::LevelMVar(term.clone()));
+        }
+        match unknown_level_param(level, self.level_params) {
+            Some(param) => Err(KernelError::UnknownLevelParam {
+                term: term.clone(),
+                param: param.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Pushes a local of type `domain` and instantiates `body` with it;
+    /// callers pop the local afterwards.
+    pub fn open(&mut self, domain: &Term, body: &Term) -> (Unique, Term) {
+        let fvar = self.gen_.fresh_unnamed();
+        self.locals.push((fvar.clone(), domain.clone()));
+        let body = subst::instantiate(body, &Term::FVar(fvar.clone()));
+        (fvar, body)
+    }
+
+    /// Pops the local pushed by the last `open`.
+    pub fn close(&mut self) {
+        self.locals.pop();
+    }
+
+    /// `Array.cons A n x xs : Array A (n + 1)` for a literal `n`, which has no
+    /// Pi type to look up since `Nat` has no successor in the kernel.
+    fn infer_array_cons(&mut self, term: &Term) -> Result<Term, KernelError> {
+        let invalid = || KernelError::InvalidArrayCons(term.clone());
+        let (Term::Const(_, levels), [elem_type, length, elem, tail]) = (term.app_head(), &term.app_args()[..]) else {
+            return Err(invalid());
+        };
+        let [level] = &levels[..] else {
+            return Err(invalid());
+        };
+        self.check_level(term, level)?;
+        let Term::Lit(Literal::Nat(length)) = self.whnf(length) else {
+            return Err(invalid());
+        };
+        self.check(elem_type, &Term::Sort(level.clone()))?;
+        self.check(elem, elem_type)?;
+        self.check(tail, &array_type(level, (*elem_type).clone(), length))?;
+        Ok(array_type(level, (*elem_type).clone(), length + 1))
+    }
+}
+
+fn unknown_level_param<'l>(level: &'l Level, params: &[String]) -> Option<&'l String> {
+    match level {
+        Level::Param(name) if !params.contains(name) => Some(name),
+        Level::Succ(l) => unknown_level_param(l, params),
+        Level::Max(a, b) | Level::IMax(a, b) => {
+            unknown_level_param(a, params).or_else(|| unknown_level_param(b, params))
+        }
+        _ => None,
+    }
+}
+
+fn array_type(level: &Level, elem_type: Term, length: u64) -> Term {
+    Term::mk_apps(
+        Term::Const(PRIM_ARRAY, alloc::vec![level.clone()]),
+        [elem_type, Term::Lit(Literal::Nat(length))],
+    )
+}
+
+/// `Array.nil.{u} : Pi (A : Type u) -> Array.{u} A 0`
+fn array_nil_type(level: &Level) -> Term {
+    Term::Pi(
+        BinderName(Some(String::from("A"))),
+        BinderInfo::Explicit,
+        Box::new(Term::Sort(level.clone())),
+        Box::new(array_type(level, Term::BVar(0), 0)),
+    )
+}
@@ -0,0 +1,16 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod anf;
+pub mod driver;
+pub mod elaboration;
+pub mod interp;
+pub mod ir;
+pub mod kernel;
+pub mod log;
+pub mod lsp;
+pub mod module;
+pub mod repl;
+pub mod spine;
+pub mod syntax;
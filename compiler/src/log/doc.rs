@@ -0,0 +1,96 @@
+//! Wadler-style documents: text with line breaks that a group lays out on
+//! one line when it fits in the remaining width, and breaks otherwise.
+
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Text(String),
+    /// A space when its group is flat, a newline and indentation otherwise.
+    Line,
+    /// Always a newline.
+    HardLine,
+    Concat(Vec<Doc>),
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    pub fn nest(self, indent: usize) -> Doc {
+        Doc::Nest(indent, Box::new(self))
+    }
+
+    pub fn group(self) -> Doc {
+        Doc::Group(Box::new(self))
+    }
+
+    pub fn parens(self) -> Doc {
+        Doc::concat([Doc::text("("), self.nest(1), Doc::text(")")])
+    }
+
+    /// Lays the document out in `width` columns, or on one line when
+    /// `width` is `usize::MAX`.
+    pub fn render(&self, width: usize) -> String {
+        let mut out = String::new();
+        let mut column = 0;
+        let mut stack: Vec<(usize, bool, &Doc)> = vec![(0, false, self)];
+        while let Some((indent, flat, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(text) => {
+                    out.push_str(text);
+                    column += text.chars().count();
+                }
+                Doc::Line if flat => {
+                    out.push(' ');
+                    column += 1;
+                }
+                Doc::Line | Doc::HardLine => {
+                    out.push('\n');
+                    out.extend(core::iter::repeat_n(' ', indent));
+                    column = indent;
+                }
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, flat, doc))),
+                Doc::Nest(extra, doc) => stack.push((indent + extra, flat, doc)),
+                Doc::Group(doc) => {
+                    let fits = flat || fits(doc, width.saturating_sub(column));
+                    stack.push((indent, fits, doc));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Whether `doc` laid out flat takes at most `width` columns.
+fn fits(doc: &Doc, width: usize) -> bool {
+    let mut remaining = width;
+    let mut stack = vec![doc];
+    while let Some(doc) = stack.pop() {
+        let used = match doc {
+            Doc::Text(text) => text.chars().count(),
+            Doc::Line => 1,
+            Doc::HardLine => return false,
+            Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev());
+                0
+            }
+            Doc::Nest(_, doc) | Doc::Group(doc) => {
+                stack.push(doc);
+                0
+            }
+        };
+        let Some(left) = remaining.checked_sub(used) else {
+            return false;
+        };
+        remaining = left;
+    }
+    true
+}
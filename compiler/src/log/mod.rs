@@ -1,7 +1,8 @@
+pub mod doc;
 pub mod pretty;
 
 use alloc::string::String;
-use miette::{Diagnostic, NamedSource};
+use miette::{Diagnostic, NamedSource, NarratableReportHandler};
 
 #[derive(Debug)]
 pub struct ErrorWithSource<'a, E: Diagnostic + ::core::fmt::Debug> {
@@ -37,4 +38,11 @@ impl<E: Diagnostic + ::core::fmt::Debug> Diagnostic for ErrorWithSource<'_, E> {
     fn source_code(&self) -> Option<&dyn miette::SourceCode> {
         Some(self.source)
     }
-}
\ No newline at end of file
+}
+/// Renders `error` in `source` the way the compiler reports errors.
+pub fn render<E: Diagnostic + ::core::fmt::Debug>(error: &E, source: &NamedSource<String>) -> String {
+    let mut output = String::new();
+    let err_with_source = ErrorWithSource { error, source };
+    let _ = NarratableReportHandler::new().render_report(&mut output, &err_with_source);
+    output
+}
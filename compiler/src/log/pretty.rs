@@ -1,13 +1,40 @@
 use core::fmt::Display;
 
-use alloc::{format, string::{String, ToString}};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 
-use crate::{elaboration::{Declaration, Environment}, spine::{BinderInfo, Term}};
+use crate::{
+    elaboration::{Declaration, Environment, ctx::LocalContext, delab},
+    log::doc::Doc,
+    spine::{Level, Literal, Term},
+    syntax::tree::{SyntaxBinder, SyntaxConstructor, SyntaxExpr, SyntaxExprKind, SyntaxLevel, SyntaxMatchArm, SyntaxPattern, SyntaxPatternKind},
+};
+
+#[derive(Debug, Clone)]
+pub struct PrettyOptions {
+    /// Columns to wrap long terms at.
+    pub width: usize,
+    /// Whether to print the implicit arguments of constants. Hiding them
+    /// needs the environment the constants are declared in.
+    pub show_implicit: bool,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            show_implicit: true,
+        }
+    }
+}
 
 impl Display for Environment {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         for (_, decl) in &self.decls {
-            writeln!(f, "{}", decl)?;
+            writeln!(f, "{}", pretty_declaration(decl, Some(self), &PrettyOptions::default()))?;
         }
         Ok(())
     }    
@@ -15,53 +42,367 @@ impl Display for Environment {
 
 impl Display for Declaration {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        match self {
-            Declaration::Definition { name, type_, value } => {
-                write!(f, "def {} : {} := {}", name.display().unwrap(), type_, value)
-            },
-            Declaration::Constructor { name, type_ } => {
-                write!(f, "constructor {} : {}", name.display().unwrap(), type_)
-            }
+        write!(f, "{}", pretty_declaration(self, None, &PrettyOptions::default()))
+    }
+}
+
+pub fn pretty_declaration(decl: &Declaration, env: Option<&Environment>, options: &PrettyOptions) -> String {
+    let keyword = match decl {
+        Declaration::Definition { .. } => "def",
+        Declaration::Constructor { .. } => "constructor",
+        Declaration::Inductive { .. } => "inductive",
+        Declaration::Intro { .. } => "intro",
+        Declaration::Recursor { .. } => "recursor",
+    };
+    let name = delab::const_name(decl.name());
+    let header = format!("{} {}{} :", keyword, name, pretty_level_params(decl.level_params()));
+    let mut terms = alloc::vec![decl.type_()];
+    if let Declaration::Definition { value, .. } = decl {
+        terms.push(value);
+    }
+    let syntax = delab::delaborate_all(&terms, env, options.show_implicit);
+    let signature = Doc::concat([Doc::text(header), Doc::Line, doc(&syntax[0], Prec::Top)]);
+    let doc = match syntax.get(1) {
+        Some(value) => {
+            let signature = Doc::concat([signature, Doc::text(" :=")]).nest(2).group();
+            Doc::concat([signature, Doc::concat([Doc::Line, doc(value, Prec::Top)]).nest(2)])
         }
+        None => signature.nest(2),
+    };
+    doc.group().render(options.width)
+}
+
+fn pretty_level_params(params: &[String]) -> String {
+    if params.is_empty() {
+        String::new()
+    } else {
+        format!(".{{{}}}", params.join(", "))
     }
 }
 
+/// Terms print on one line; use `pretty_term` to wrap them.
 impl Display for Term {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "{}", pretty_term(self))
+        let options = PrettyOptions {
+            width: usize::MAX,
+            ..PrettyOptions::default()
+        };
+        write!(f, "{}", pretty_term_with(self, None, &options))
     }
 }
 
 pub fn pretty_term(term: &Term) -> String {
-    match term {
-        Term::MVar(unique) => format!("m{}", unique.id),
-        Term::BVar(de_bruijn_index) => format!("b{}", de_bruijn_index),
-        Term::FVar(unique) => format!("f{}", unique.id),
-        Term::Const(qname) => qname.display().unwrap().to_string(),
-        Term::App(func, arg) => format!("({} {})", pretty_term(func), pretty_term(arg)),
-        Term::Pi(binder_info, param, body) => {
-            let binder_str = binder_surrounding(binder_info, pretty_term(param));
-            format!("Pi {} -> {}", binder_str, pretty_term(body))
-        }
-        Term::Lam(binder_info, param, body) => {
-            let binder_str = binder_surrounding(binder_info, pretty_term(param));
-            format!("λ {}. {}", binder_str, pretty_term(body))
-        },
-        Term::Sigma(binder_info, param, body) => {
-            let binder_str = binder_surrounding(binder_info, pretty_term(param));
-            format!("Σ {} × {}", binder_str, pretty_term(body))
-        },
-        Term::Sort(level) => format!("Type({:?})", level),
-        Term::Let(binding, value, body) => format!("(let {} = {} in {})", pretty_term(binding), pretty_term(value), pretty_term(body)),
-        Term::Lit(lit) => format!("{:?}", lit),
+    pretty_term_with(term, None, &PrettyOptions::default())
+}
+
+/// Prints `term` in surface syntax, by way of the delaborator.
+pub fn pretty_term_with(term: &Term, env: Option<&Environment>, options: &PrettyOptions) -> String {
+    let syntax = delab::delaborate(term, env, options.show_implicit);
+    doc(&syntax, Prec::Top).render(options.width)
+}
+
+impl Display for SyntaxExpr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", pretty_syntax(self, PrettyOptions::default().width))
+    }
+}
+
+pub fn pretty_syntax(syntax: &SyntaxExpr, width: usize) -> String {
+    doc(syntax, Prec::Top).render(width)
+}
+
+/// How tightly the context a piece of syntax is printed in binds: binders
+/// and arrows extend as far right as they can, so they need parentheses
+/// anywhere but at the top, and only atoms can be arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    Top,
+    App,
+    Atom,
+}
+
+fn doc(syntax: &SyntaxExpr, prec: Prec) -> Doc {
+    match &syntax.kind {
+        SyntaxExprKind::Var(name) | SyntaxExprKind::Constructor(name) => Doc::text(name.clone()),
+        SyntaxExprKind::Lit(Literal::Nat(n)) => Doc::text(n.to_string()),
+        SyntaxExprKind::Lit(Literal::Str(s)) => Doc::text(format!("\"{}\"", s)),
+        SyntaxExprKind::Hole => Doc::text("_"),
+        SyntaxExprKind::NamedHole(name) => Doc::text(format!("?{}", name)),
+        SyntaxExprKind::Sort(None) => Doc::text("Type"),
+        SyntaxExprKind::Sort(Some(level)) => {
+            paren_if(prec == Prec::Atom, Doc::text(format!("Type {}", level_atom(level))))
+        }
+        SyntaxExprKind::Proj(head, field) => Doc::concat([doc(head, Prec::Atom), Doc::text(format!(".{}", field))]),
+        SyntaxExprKind::App(..) => {
+            let mut args = Vec::new();
+            let mut head = syntax;
+            while let SyntaxExprKind::App(fun, arg) = &head.kind {
+                args.push(arg);
+                head = fun;
+            }
+            let mut docs = alloc::vec![doc(head, Prec::App)];
+            for arg in args.into_iter().rev() {
+                docs.extend([Doc::Line, doc(arg, Prec::Atom)]);
+            }
+            paren_if(prec == Prec::Atom, Doc::concat(docs).nest(2).group())
+        }
+        SyntaxExprKind::Arrow(domain, codomain) => {
+            paren_if(prec > Prec::Top, infix(doc(domain, Prec::App), " ->", doc(codomain, Prec::Top)))
+        }
+        SyntaxExprKind::Pi(binder, body) => {
+            paren_if(prec > Prec::Top, infix(binder_doc(binder), " ->", doc(body, Prec::Top)))
+        }
+        SyntaxExprKind::Sigma(SyntaxBinder::Explicit(name, domain), body) if name == "_" => {
+            paren_if(prec > Prec::Top, infix(doc(domain, Prec::App), " ×", doc(body, Prec::Top)))
+        }
+        SyntaxExprKind::Sigma(binder, body) => {
+            paren_if(prec > Prec::Top, infix(binder_doc(binder), " ×", doc(body, Prec::Top)))
+        }
+        SyntaxExprKind::Lambda { binders, body } => {
+            let mut head = alloc::vec![Doc::text("λ")];
+            for binder in binders {
+                head.extend([Doc::text(" "), binder_doc(binder)]);
+            }
+            head.push(Doc::text(" =>"));
+            let lambda = Doc::concat([Doc::concat(head), Doc::concat([Doc::Line, doc(body, Prec::Top)]).nest(2)]);
+            paren_if(prec > Prec::Top, lambda.group())
+        }
+        SyntaxExprKind::Let {
+            name,
+            type_ann,
+            value,
+            body,
+        } => {
+            let mut binding = alloc::vec![Doc::text(format!("let {}", name))];
+            if let Some(type_) = type_ann {
+                binding.extend([Doc::text(" : "), doc(type_, Prec::Top)]);
+            }
+            binding.extend([Doc::text(" ="), Doc::Line, doc(value, Prec::Top)]);
+            let let_ = Doc::concat([
+                Doc::concat(binding).nest(2).group(),
+                Doc::Line,
+                Doc::text("in "),
+                doc(body, Prec::Top),
+            ]);
+            paren_if(prec > Prec::Top, let_.group())
+        }
+        SyntaxExprKind::Tuple(items) => Doc::concat([Doc::text("("), comma_separated(items).nest(1), Doc::text(")")]).group(),
+        SyntaxExprKind::Array(items) => Doc::concat([Doc::text("["), comma_separated(items).nest(1), Doc::text("]")]).group(),
+        SyntaxExprKind::AnonymousConstructor(items) => {
+            Doc::concat([Doc::text("⟨"), comma_separated(items).nest(1), Doc::text("⟩")]).group()
+        }
+        SyntaxExprKind::Match { scrutinee, arms } => {
+            let mut docs = alloc::vec![Doc::text("match "), doc(scrutinee, Prec::Top), Doc::text(" with")];
+            for SyntaxMatchArm { pattern, body, .. } in arms {
+                docs.extend([
+                    Doc::HardLine,
+                    Doc::text(format!("| {} =>", pattern_text(pattern, false))),
+                    Doc::concat([Doc::Line, doc(body, Prec::Top)]).nest(2).group(),
+                ]);
+            }
+            paren_if(prec > Prec::Top, Doc::concat(docs).nest(2))
+        }
+        SyntaxExprKind::Root(commands) => {
+            let mut docs = Vec::new();
+            for (i, command) in commands.iter().enumerate() {
+                if i > 0 {
+                    docs.extend([Doc::HardLine, Doc::HardLine]);
+                }
+                docs.push(doc(command, Prec::Top));
+            }
+            Doc::concat(docs)
+        }
+        SyntaxExprKind::Def {
+            name,
+            binders,
+            return_type,
+            body,
+        } => {
+            let signature = Doc::concat([
+                Doc::text(format!("def {}", name)),
+                binders_doc(binders),
+                Doc::text(" :"),
+                Doc::Line,
+                doc(return_type, Prec::Top),
+                Doc::text(" ="),
+            ]);
+            Doc::concat([signature.nest(2).group(), Doc::concat([Doc::Line, doc(body, Prec::Top)]).nest(2)]).group()
+        }
+        SyntaxExprKind::Eval(term) => Doc::concat([Doc::text("eval "), doc(term, Prec::Top), Doc::text(";")]).nest(2),
+        SyntaxExprKind::Mutual(defs) => {
+            let mut docs = alloc::vec![Doc::text("mutual")];
+            for def in defs {
+                docs.extend([Doc::HardLine, doc(def, Prec::Top)]);
+            }
+            Doc::concat([Doc::concat(docs).nest(2), Doc::HardLine, Doc::text("end")])
+        }
+        SyntaxExprKind::Inductive {
+            name,
+            binders,
+            type_,
+            constructors,
+        } => {
+            let mut docs = alloc::vec![Doc::text(format!("inductive {}", name)), binders_doc(binders)];
+            if let Some(type_) = type_ {
+                docs.extend([Doc::text(" : "), doc(type_, Prec::Top)]);
+            }
+            docs.push(Doc::text(" where"));
+            for SyntaxConstructor { name, binders, type_ } in constructors {
+                docs.extend([Doc::HardLine, Doc::text(format!("| {}", name)), binders_doc(binders)]);
+                if let Some(type_) = type_ {
+                    docs.extend([Doc::text(" : "), doc(type_, Prec::Top)]);
+                }
+            }
+            Doc::concat(docs)
+        }
+        SyntaxExprKind::Struct { name, binders, fields } | SyntaxExprKind::Class { name, binders, fields } => {
+            let keyword = match syntax.kind {
+                SyntaxExprKind::Class { .. } => "class",
+                _ => "struct",
+            };
+            let mut docs = alloc::vec![Doc::text(format!("{} {}", keyword, name)), binders_doc(binders), Doc::text(" where")];
+            for field in fields {
+                let (name, type_) = binder_parts(field);
+                docs.extend([Doc::HardLine, Doc::text(format!("{} : ", name)), doc(type_, Prec::Top)]);
+            }
+            Doc::concat(docs).nest(2)
+        }
+        SyntaxExprKind::Instance {
+            name,
+            binders,
+            type_,
+            body,
+        } => {
+            let signature = Doc::concat([
+                Doc::text(format!("instance {}", name)),
+                binders_doc(binders),
+                Doc::text(" :"),
+                Doc::Line,
+                doc(type_, Prec::Top),
+                Doc::text(" ="),
+            ]);
+            Doc::concat([signature.nest(2).group(), Doc::concat([Doc::Line, doc(body, Prec::Top)]).nest(2)]).group()
+        }
+        SyntaxExprKind::Import(module) => Doc::text(format!("import {}", module)),
+        SyntaxExprKind::Open(paths) => Doc::text(format!("open {}", paths.join(" "))),
+        SyntaxExprKind::Namespace { name, commands } => {
+            let mut docs = alloc::vec![Doc::text(format!("namespace {}", name))];
+            for command in commands {
+                docs.extend([Doc::HardLine, Doc::HardLine, doc(command, Prec::Top)]);
+            }
+            docs.extend([Doc::HardLine, Doc::HardLine, Doc::text("end")]);
+            Doc::concat(docs)
+        }
     }
 }
 
-fn binder_surrounding(binder_info: &BinderInfo, str: String) -> String {
-    match binder_info {
-        BinderInfo::Explicit => format!("({})", str),
-        BinderInfo::Implicit => format!("{{{}}}", str),
-        BinderInfo::InstanceImplicit => format!("[{}]", str),
-        BinderInfo::StrictImplicit => format!("{{{{{}}}}}", str),
+fn infix(lhs: Doc, operator: &str, rhs: Doc) -> Doc {
+    Doc::concat([lhs, Doc::text(operator), Doc::Line, rhs]).group()
+}
+
+fn comma_separated(items: &[SyntaxExpr]) -> Doc {
+    let mut docs = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            docs.extend([Doc::text(","), Doc::Line]);
+        }
+        docs.push(doc(item, Prec::Top));
+    }
+    Doc::concat(docs)
+}
+
+fn binder_parts(binder: &SyntaxBinder) -> (&String, &SyntaxExpr) {
+    match binder {
+        SyntaxBinder::Explicit(name, type_) | SyntaxBinder::Implicit(name, type_) | SyntaxBinder::Instance(name, type_) => {
+            (name, type_)
+        }
+    }
+}
+
+/// An explicit binder whose type is `_` is written as just its name.
+fn binder_doc(binder: &SyntaxBinder) -> Doc {
+    let (open, close) = match binder {
+        SyntaxBinder::Explicit(name, type_) if type_.kind == SyntaxExprKind::Hole => return Doc::text(name.clone()),
+        SyntaxBinder::Explicit(..) => ("(", ")"),
+        SyntaxBinder::Implicit(..) => ("{", "}"),
+        SyntaxBinder::Instance(..) => ("[", "]"),
+    };
+    let (name, type_) = binder_parts(binder);
+    Doc::concat([Doc::text(format!("{}{} : ", open, name)), doc(type_, Prec::Top), Doc::text(close)])
+}
+
+fn binders_doc(binders: &[SyntaxBinder]) -> Doc {
+    Doc::concat(binders.iter().flat_map(|binder| [Doc::text(" "), binder_doc(binder)]))
+}
+
+fn level_atom(level: &SyntaxLevel) -> String {
+    match level {
+        SyntaxLevel::Nat(n) => n.to_string(),
+        SyntaxLevel::Param(name) => name.clone(),
+        SyntaxLevel::Add(base, n) => format!("({} + {})", level_atom(base), n),
+        SyntaxLevel::Max(a, b) => format!("(max {} {})", level_atom(a), level_atom(b)),
+        SyntaxLevel::IMax(a, b) => format!("(imax {} {})", level_atom(a), level_atom(b)),
     }
-}
\ No newline at end of file
+}
+
+fn pattern_text(pattern: &SyntaxPattern, nested: bool) -> String {
+    match &pattern.kind {
+        SyntaxPatternKind::Wildcard => String::from("_"),
+        SyntaxPatternKind::Var(name) => name.clone(),
+        SyntaxPatternKind::Constructor(name, args) if args.is_empty() => name.clone(),
+        SyntaxPatternKind::Constructor(name, args) => {
+            let args: Vec<String> = args.iter().map(|arg| pattern_text(arg, true)).collect();
+            let text = format!("{} {}", name, args.join(" "));
+            if nested { format!("({})", text) } else { text }
+        }
+    }
+}
+
+fn paren_if(cond: bool, doc: Doc) -> Doc {
+    if cond { doc.parens() } else { doc }
+}
+
+/// One `name : type` line per local, or `name : type := value` for a let,
+/// each on a new indented line.
+pub fn pretty_local_context(lctx: &LocalContext) -> String {
+    let mut out = String::new();
+    for local in &lctx.decls {
+        out.push_str(&format!("\n  {} : {}", Term::FVar(local.fvar.clone()), local.type_));
+        if let Some(value) = &local.value {
+            out.push_str(&format!(" := {}", value));
+        }
+    }
+    out
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if let Some(n) = self.to_nat() {
+            return write!(f, "{}", n);
+        }
+        match self {
+            Level::Succ(_) => {
+                let mut base = self;
+                let mut offset = 0;
+                while let Level::Succ(l) = base {
+                    base = l;
+                    offset += 1;
+                }
+                write!(f, "{} + {}", pretty_level_atom(base), offset)
+            }
+            Level::Max(a, b) => write!(f, "max {} {}", pretty_level_atom(a), pretty_level_atom(b)),
+            Level::IMax(a, b) => write!(f, "imax {} {}", pretty_level_atom(a), pretty_level_atom(b)),
+            Level::Param(name) => write!(f, "{}", name),
+            Level::MVar(unique) => write!(f, "?u{}", unique.id),
+            Level::Zero => write!(f, "0"),
+        }
+    }
+}
+
+fn pretty_level_atom(level: &Level) -> String {
+    match level {
+        Level::Max(..) | Level::IMax(..) => format!("({})", level),
+        Level::Succ(_) if level.to_nat().is_none() => format!("({})", level),
+        _ => level.to_string(),
+    }
+}
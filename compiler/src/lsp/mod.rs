@@ -0,0 +1,446 @@
+//! A language server: diagnostics, hover types, go to definition and name
+//! completion for the documents an editor has open.
+//!
+//! Each open document is compiled again whenever it changes, with its
+//! imports found next to it like `compiler` does, `A.B` in `A/B.src`. Open
+//! documents are read from the editor rather than from disk. The binary in
+//! `bin/lsp.rs` moves messages between this and the editor.
+
+pub mod json;
+
+use alloc::{
+    boxed::Box,
+    collections::btree_map::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use miette::Diagnostic;
+
+use crate::{
+    driver::{CompiledModule, Files, ModuleLoader, compile},
+    elaboration::Declaration,
+    log::pretty::{PrettyOptions, pretty_term_with},
+    lsp::json::Json,
+    module::{ModuleId, name::QualifiedName},
+    spine::Term,
+    syntax::Span,
+};
+
+pub struct Server {
+    /// Reads the files that aren't open in the editor.
+    files: Box<dyn Files>,
+    /// The open documents by URI.
+    documents: BTreeMap<String, Document>,
+    shut_down: bool,
+    exit_code: Option<i32>,
+}
+
+struct Document {
+    text: String,
+    analysis: Option<Analysis>,
+}
+
+/// The compiled document, last of `modules`, and the modules it imports.
+struct Analysis {
+    modules: Vec<CompiledModule>,
+    /// The file each module was read from.
+    paths: BTreeMap<ModuleId, String>,
+}
+
+impl Analysis {
+    fn main(&self) -> &CompiledModule {
+        self.modules.last().unwrap()
+    }
+}
+
+impl Server {
+    pub fn new(files: Box<dyn Files>) -> Self {
+        Self {
+            files,
+            documents: BTreeMap::new(),
+            shut_down: false,
+            exit_code: None,
+        }
+    }
+
+    /// Set once the editor asked the server to exit.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    /// Handles a message from the editor, returning the messages to send
+    /// back.
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").as_str().unwrap_or_default();
+        let params = message.get("params");
+        let id = message.get("id");
+        if *id == Json::Null {
+            return self.notify(method, params);
+        }
+
+        let result = match method {
+            _ if self.shut_down => Err((-32600, "The server was shut down")),
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "textDocument/hover" => Ok(self.hover(params).unwrap_or(Json::Null)),
+            "textDocument/definition" => Ok(self.definition(params).unwrap_or(Json::Null)),
+            "textDocument/completion" => Ok(self.completion(params).unwrap_or(Json::Array(Vec::new()))),
+            _ => Err((-32601, "Unknown method")),
+        };
+        let response = match result {
+            Ok(result) => Json::object([("jsonrpc", "2.0".into()), ("id", id.clone()), ("result", result)]),
+            Err((code, message)) => Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id.clone()),
+                ("error", Json::object([("code", Json::Number(code as f64)), ("message", message.into())])),
+            ]),
+        };
+        alloc::vec![response]
+    }
+
+    fn notify(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or_default();
+        match method {
+            "exit" => {
+                self.exit_code = Some(if self.shut_down { 0 } else { 1 });
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str().unwrap_or_default();
+                self.update(uri, text.into())
+            }
+            "textDocument/didChange" => {
+                // Documents are synchronized in full, so the last change is
+                // the whole text.
+                let changes = params.get("contentChanges").as_array().unwrap_or_default();
+                match changes.last().and_then(|change| change.get("text").as_str()) {
+                    Some(text) => self.update(uri, text.into()),
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                alloc::vec![publish_diagnostics(uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Compiles the document at `uri` with its new text and publishes its
+    /// diagnostics.
+    fn update(&mut self, uri: &str, text: String) -> Vec<Json> {
+        self.documents.insert(
+            uri.into(),
+            Document {
+                text: text.clone(),
+                analysis: None,
+            },
+        );
+        let Some(path) = uri_to_path(uri) else {
+            return Vec::new();
+        };
+        let dir = path.rsplit_once('/').map_or(".", |(dir, _)| dir).to_string();
+        let mut loader = DocumentLoader {
+            files: &mut *self.files,
+            documents: &self.documents,
+            dir,
+            paths: BTreeMap::new(),
+        };
+        let modules = compile(&mut loader, path.clone(), text);
+        let mut paths = loader.paths;
+        paths.insert(path.clone(), path);
+        let analysis = Analysis { modules, paths };
+        let diagnostics = diagnostics(analysis.main());
+        if let Some(document) = self.documents.get_mut(uri) {
+            document.analysis = Some(analysis);
+        }
+        alloc::vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    /// The document and byte offset a request is about.
+    fn locate(&self, params: &Json) -> Option<(&Analysis, usize)> {
+        let uri = params.get("textDocument").get("uri").as_str()?;
+        let document = self.documents.get(uri)?;
+        let offset = offset(&document.text, params.get("position"))?;
+        Some((document.analysis.as_ref()?, offset))
+    }
+
+    fn hover(&self, params: &Json) -> Option<Json> {
+        let (analysis, offset) = self.locate(params)?;
+        let main = analysis.main();
+        let (span, term, type_) = main.state.term_at(offset)?;
+        let env = Some(&main.state.env);
+        let options = PrettyOptions {
+            show_implicit: false,
+            ..PrettyOptions::default()
+        };
+        let type_ = pretty_term_with(&type_, env, &options);
+        let contents = match term {
+            Term::Const(..) | Term::FVar(_) => format!("{} : {}", pretty_term_with(&term, env, &options), type_),
+            _ => type_,
+        };
+        Some(Json::object([
+            (
+                "contents",
+                Json::object([("kind", "markdown".into()), ("value", format!("```\n{}\n```", contents).into())]),
+            ),
+            ("range", range(&main.source, span)),
+        ]))
+    }
+
+    fn definition(&self, params: &Json) -> Option<Json> {
+        let (analysis, offset) = self.locate(params)?;
+        let (_, term, _) = analysis.main().state.term_at(offset)?;
+        let Term::Const(name, _) = term.app_head() else {
+            return None;
+        };
+        let QualifiedName::User(unique) = name else {
+            return None;
+        };
+        let module = analysis.modules.iter().find(|module| module.module_id == unique.module_id)?;
+        let span = module.state.decl_span(name)?;
+        let path = analysis.paths.get(&module.module_id)?;
+        let uri = match self.documents.keys().find(|uri| uri_to_path(uri).as_ref() == Some(path)) {
+            Some(uri) => uri.clone(),
+            None => path_to_uri(path),
+        };
+        Some(Json::object([("uri", uri.into()), ("range", range(&module.source, span))]))
+    }
+
+    fn completion(&self, params: &Json) -> Option<Json> {
+        let (analysis, offset) = self.locate(params)?;
+        let state = &analysis.main().state;
+        let options = PrettyOptions {
+            width: usize::MAX,
+            show_implicit: false,
+        };
+        let mut items: Vec<Json> = state
+            .locals_at(offset)
+            .into_iter()
+            .map(|local| Json::object([("label", local.into()), ("kind", Json::Number(6.0))]))
+            .collect();
+        for (path, name) in &state.env.names {
+            let Some(decl) = state.env.lookup(name) else {
+                continue;
+            };
+            let path = path.to_string();
+            let detail = pretty_term_with(decl.type_(), Some(&state.env), &options);
+            let opened = state.opens.iter().filter_map(|open| {
+                let prefix = format!("{}.", open);
+                path.strip_prefix(&prefix).map(String::from)
+            });
+            for label in core::iter::once(path.clone()).chain(opened) {
+                items.push(Json::object([
+                    ("label", label.into()),
+                    ("kind", completion_kind(decl).into()),
+                    ("detail", detail.clone().into()),
+                ]));
+            }
+        }
+        Some(Json::Array(items))
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("textDocumentSync", Json::Number(1.0)),
+                ("hoverProvider", true.into()),
+                ("definitionProvider", true.into()),
+                ("completionProvider", Json::object([("triggerCharacters", alloc::vec![".".into()].into())])),
+            ]),
+        ),
+        ("serverInfo", Json::object([("name", "compiler".into())])),
+    ])
+}
+
+/// `CompletionItemKind`s of the protocol.
+fn completion_kind(decl: &Declaration) -> usize {
+    match decl {
+        Declaration::Definition { .. } | Declaration::Recursor { .. } => 3,
+        Declaration::Intro { .. } => 4,
+        Declaration::Inductive { .. } => 7,
+        Declaration::Constructor { .. } => 21,
+    }
+}
+
+/// Reads imports like the `compiler` binary's search path, from the open
+/// documents first.
+struct DocumentLoader<'a> {
+    files: &'a mut dyn Files,
+    documents: &'a BTreeMap<String, Document>,
+    dir: String,
+    paths: BTreeMap<ModuleId, String>,
+}
+
+impl ModuleLoader for DocumentLoader<'_> {
+    fn load(&mut self, module: &str) -> Option<String> {
+        let path = format!("{}/{}.src", self.dir, module.replace('.', "/"));
+        let open = self.documents.iter().find(|(uri, _)| uri_to_path(uri).as_ref() == Some(&path));
+        let source = match open {
+            Some((_, document)) => document.text.clone(),
+            None => self.files.read(&path)?,
+        };
+        self.paths.insert(module.into(), path);
+        Some(source)
+    }
+
+    fn records_info(&self, _module: &str) -> bool {
+        true
+    }
+}
+
+fn diagnostics(module: &CompiledModule) -> Vec<Json> {
+    let source = &module.source;
+    let lex = module.parsed.lex_errors.iter().map(|err| diagnostic(source, err.span, err));
+    let parse = module.parsed.parse_errors.iter().map(|err| diagnostic(source, err.span, err));
+    let elab = module.state.errors.iter().map(|err| diagnostic(source, err.span, err));
+    lex.chain(parse).chain(elab).collect()
+}
+
+fn diagnostic(source: &str, span: Span, error: &dyn Diagnostic) -> Json {
+    let mut message = error.to_string();
+    if let Some(help) = error.help() {
+        message = format!("{}\n{}", message, help);
+    }
+    let mut fields = BTreeMap::new();
+    fields.insert("range".into(), range(source, span));
+    fields.insert("severity".into(), Json::Number(1.0));
+    fields.insert("source".into(), "compiler".into());
+    fields.insert("message".into(), message.into());
+    if let Some(code) = error.code() {
+        fields.insert("code".into(), code.to_string().into());
+    }
+    Json::Object(fields)
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        ("params", Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())])),
+    ])
+}
+
+fn range(source: &str, span: Span) -> Json {
+    Json::object([("start", position(source, span.start)), ("end", position(source, span.end))])
+}
+
+/// The protocol's position of byte `offset`: a line and a column counted in
+/// UTF-16 code units.
+fn position(source: &str, offset: usize) -> Json {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    Json::object([("line", before.matches('\n').count().into()), ("character", character.into())])
+}
+
+/// The byte offset of a position, clamped to the end of its line.
+fn offset(source: &str, position: &Json) -> Option<usize> {
+    let line = position.get("line").as_usize()?;
+    let character = position.get("character").as_usize()?;
+    let line_start = match line {
+        0 => 0,
+        _ => source.match_indices('\n').nth(line - 1)?.0 + 1,
+    };
+    let mut units = 0;
+    for (i, c) in source[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Some(line_start + i);
+        }
+        units += c.len_utf16();
+    }
+    Some(source.len())
+}
+
+pub fn uri_to_path(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("file://")?;
+    // `file:///C:/dir` on Windows.
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] => &path[1..],
+        _ => path,
+    };
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, hex_byte(tail)) {
+            (b'%', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The byte two hex digits at the start of `bytes` stand for.
+fn hex_byte(bytes: &[u8]) -> Option<u8> {
+    let digits = bytes.get(..2).filter(|digits| digits.iter().all(u8::is_ascii_hexdigit))?;
+    u8::from_str_radix(core::str::from_utf8(digits).ok()?, 16).ok()
+}
+
+pub fn path_to_uri(path: &str) -> String {
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Splits the byte stream from the editor into messages, each a header
+/// with its `Content-Length` followed by a JSON body.
+#[derive(Default)]
+pub struct MessageReader {
+    buffer: Vec<u8>,
+}
+
+impl MessageReader {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The body of the next complete message, if one has arrived. A body
+    /// that isn't JSON is returned as `Json::Null`.
+    pub fn next_message(&mut self) -> Option<Json> {
+        let header_end = self.buffer.windows(4).position(|window| window == b"\r\n\r\n")?;
+        let header = String::from_utf8_lossy(&self.buffer[..header_end]).into_owned();
+        let length = header
+            .split("\r\n")
+            .find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case("content-length").then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        let body_start = header_end + 4;
+        if self.buffer.len() < body_start + length {
+            return None;
+        }
+        let body: Vec<u8> = self.buffer.drain(..body_start + length).skip(body_start).collect();
+        let body = core::str::from_utf8(&body).ok();
+        Some(body.and_then(Json::parse).unwrap_or(Json::Null))
+    }
+}
+
+/// `message` with the header the editor expects before it.
+pub fn frame(message: &Json) -> String {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
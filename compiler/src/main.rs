@@ -1,115 +1,293 @@
 #![no_std]
 #![no_main]
 
-use alloc::string::{String, ToString};
+use alloc::{
+    boxed::Box,
+    collections::btree_map::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 use api::{
-    io::{fs::MappedFile, stdin::Args},
-    println,
+    io::{fs::{MappedFile, write_file}, stdin::{self, Arg, Args}, stdout, time::now_micros},
+    print, println,
 };
-use miette::{NamedSource, NarratableReportHandler};
-
-use crate::{log::ErrorWithSource, syntax::{SourceFile, lexer::Lexer, parser::parse}};
+use compiler::{
+    anf::lower::lower_environment,
+    driver::{CompiledModule, Files, ModuleLoader, compile},
+    interp::{Console, run_main},
+    log::render,
+    repl::Repl,
+};
+use miette::{Diagnostic, NamedSource};
 
 extern crate alloc;
 extern crate common;
 extern crate runtime;
 
-pub mod module;
-pub mod elaboration;
-pub mod syntax;
-pub mod spine;
-pub mod log;
+api::entry!(main);
 
-#[unsafe(no_mangle)]
-pub extern "C" fn _start() -> i32 {
-    let args = Args::get();
-    if args.is_none() {
-        println!("You must provide a source file as an argument.");
-        return 1;
-    }    
-    let mut args = args.unwrap();
-    if args.len() < 2 {
-        println!("You must provide a source file as an argument.");
-        return 1;
+#[cfg(target_os = "windows")]
+fn arg_to_string(arg: Arg) -> String {
+    String::from_utf16_lossy(arg.as_utf16())
+}
+
+#[cfg(target_os = "linux")]
+fn arg_to_string(arg: Arg) -> String {
+    String::from_utf8_lossy(arg.as_bytes()).into_owned()
+}
+
+/// Finds imported modules below the directories of the search path, `A.B`
+/// in `A/B.src`. Object files are kept next to the sources, in `A/B.obj`.
+struct SearchPath {
+    dirs: Vec<String>,
+    /// The object file path of each module found so far.
+    objects: BTreeMap<String, String>,
+}
+
+impl ModuleLoader for SearchPath {
+    fn load(&mut self, module: &str) -> Option<String> {
+        let (path, source) = self.dirs.iter().find_map(|dir| {
+            let path = format!("{}/{}", dir, module.replace('.', "/"));
+            let file = MappedFile::open(&format!("{}.src", path))?;
+            Some((path, String::from_utf8_lossy(file.as_bytes()).into_owned()))
+        })?;
+        self.objects.insert(module.to_string(), format!("{}.obj", path));
+        Some(source)
     }
 
-    let _program_name = args.next();
-    let source_file = String::from_utf16_lossy(args.next().unwrap().as_utf16());
-    println!("Opening file: {}", &source_file);
+    fn load_object(&mut self, module: &str) -> Option<Vec<u8>> {
+        let file = MappedFile::open(self.objects.get(module)?)?;
+        Some(file.as_bytes().to_vec())
+    }
 
-    if let Some(file) = MappedFile::open(&source_file) {
-        println!("Read {} bytes from file:", file.len());
-        let source_file = SourceFile {
-            id: 0,
-            name: &source_file,
-            source: file.as_bytes(),
-            package: None,
-        };
-        let mut lexer = Lexer::new(&source_file);
-
-        let mut tokens = alloc::vec::Vec::new();
-        let mut lex_errors = alloc::vec::Vec::new();
-        for result in &mut lexer {
-            match result {
-                Ok(token) => tokens.push((token, token.span)),
-                Err(err) => lex_errors.push(err),
-            }
+    fn store_object(&mut self, module: &str, object: &[u8]) {
+        if let Some(path) = self.objects.get(module)
+            && write_file(path, object).is_none()
+        {
+            println!("Couldn't write object file {}", path);
         }
+    }
+
+    fn now_micros(&self) -> u64 {
+        now_micros()
+    }
+}
+
+struct FileSystem;
+
+impl Files for FileSystem {
+    fn read(&mut self, path: &str) -> Option<String> {
+        let file = MappedFile::open(path)?;
+        Some(String::from_utf8_lossy(file.as_bytes()).into_owned())
+    }
+}
 
-        let handler = NarratableReportHandler::new();
-        let source_str = ::core::str::from_utf8(file.as_bytes()).unwrap_or("<invalid utf8>");
-        let named_source = NamedSource::new(source_file.name, String::from(source_str));
-
-        for err in &lex_errors {
-            let mut output = String::new();
-            let err_with_source = ErrorWithSource {
-                error: err,
-                source: &named_source,
-            };
-            if handler.render_report(&mut output, &err_with_source).is_ok() {
-                println!("{}", output);
+/// The lines of standard input.
+#[derive(Default)]
+struct StdinLines {
+    buffer: Vec<u8>,
+}
+
+impl Iterator for StdinLines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let mut chunk = [0; 1024];
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                return Some(String::from_utf8_lossy(&line).into_owned());
             }
+            let read = stdin::read(&mut chunk);
+            if read == 0 {
+                let rest = core::mem::take(&mut self.buffer);
+                return (!rest.is_empty()).then(|| String::from_utf8_lossy(&rest).into_owned());
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
         }
+    }
+}
 
-        let eoi_span = lexer.eoi_span();
-        let (ast, errors) = parse(&tokens, eoi_span);
-
-        for err in &errors {
-            let mut output = String::new();
-            let err_with_source = ErrorWithSource {
-                error: err,
-                source: &named_source,
-            };
-            if handler.render_report(&mut output, &err_with_source).is_ok() {
-                println!("{}", output);
-            }
+/// Runs programs on standard input and output.
+#[derive(Default)]
+struct StdConsole {
+    lines: StdinLines,
+}
+
+impl Console for StdConsole {
+    fn write(&mut self, bytes: &[u8]) {
+        stdout::write(bytes);
+    }
+
+    fn read_line(&mut self) -> Option<Vec<u8>> {
+        let line = self.lines.next()?;
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        Some(line.strip_suffix('\r').unwrap_or(line).as_bytes().to_vec())
+    }
+}
+
+/// Reads inputs until `:quit` or the end of standard input. An input goes on
+/// over the following lines while it is incomplete, up to an empty line.
+fn run_repl(search_path: SearchPath) -> i32 {
+    let mut repl = Repl::new(Box::new(search_path), Box::new(FileSystem));
+    let mut lines = StdinLines::default();
+    let mut input = String::new();
+    while !repl.has_quit() {
+        print!("{}", if input.is_empty() { "> " } else { "| " });
+        let Some(line) = lines.next() else {
+            println!();
+            break;
+        };
+        input.push_str(&line);
+        if !line.trim().is_empty() && repl.is_incomplete(&input) {
+            continue;
         }
+        print!("{}", repl.run(&input));
+        input.clear();
+    }
+    0
+}
 
-        match ast {
-            Some(tree) => {
-                println!("AST produced for module {}: {:#?}", source_file.name, tree);
-                let module_id = source_file.name.to_string();
-                match elaboration::elaborate_file(
-                    module_id,
-                    &tree
-                ) {
-                    Ok(elab) => println!("Elaboration successful:\n{}", elab),
-                    Err(errs) => {
-                        for err in &errs {
-                            println!("Error during elaboration: {}", err);
-                        }
-                        println!("Elaboration failed with {} error(s)", errs.len());
-                    }
+fn print_error<E: Diagnostic + core::fmt::Debug>(error: &E, source: &NamedSource<String>) {
+    println!("{}", render(error, source));
+}
+
+/// Which commands of `module` were reused from its object file and how long
+/// the others took.
+fn print_timings(module: &CompiledModule) {
+    if module.from_object {
+        println!("{}: loaded from its object file", module.module_id);
+        return;
+    }
+    let reused = module.commands.iter().filter(|command| command.reused).count();
+    let total: u64 = module.commands.iter().map(|command| command.micros).sum();
+    println!("{}: reused {} of {} commands, {}us", module.module_id, reused, module.commands.len(), total);
+    for command in &module.commands {
+        match command.reused {
+            true => println!("  {:>10}  {}", "reused", command.summary),
+            false => println!("  {:>8}us  {}", command.micros, command.summary),
+        }
+    }
+}
+
+fn main() -> i32 {
+    let Some(args) = Args::get() else {
+        println!("You must provide a source file as an argument.");
+        return 1;
+    };
+
+    // `compiler [-I dir]... [--timings] [--anf] [--run] file` or `compiler [-I dir]... --repl`
+    let mut search_path = SearchPath {
+        dirs: Vec::new(),
+        objects: BTreeMap::new(),
+    };
+    let mut source_file = None;
+    let mut timings = false;
+    let mut repl = false;
+    let mut run = false;
+    let mut anf = false;
+    let mut args = args.skip(1).map(arg_to_string);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-I" => match args.next() {
+                Some(dir) => search_path.dirs.push(dir),
+                None => {
+                    println!("-I expects a directory.");
+                    return 1;
                 }
-                
             },
-            None if errors.is_empty() && lex_errors.is_empty() => println!("No AST produced"),
-            None => {}
+            "--timings" => timings = true,
+            "--repl" => repl = true,
+            "--anf" => anf = true,
+            "--run" => run = true,
+            _ => source_file = Some(arg),
         }
-        
-        return 0;
-    } else {
+    }
+    if repl {
+        search_path.dirs.push(".".to_string());
+        return run_repl(search_path);
+    }
+    let Some(source_file) = source_file else {
+        println!("You must provide a source file as an argument.");
+        return 1;
+    };
+    println!("Opening file: {}", &source_file);
+
+    let Some(file) = MappedFile::open(&source_file) else {
         println!("File not found!");
         return 1;
+    };
+    println!("Read {} bytes from file:", file.len());
+    let source = String::from_utf8_lossy(file.as_bytes()).into_owned();
+    let dir = source_file.rsplit_once('/').map_or(".", |(dir, _)| dir);
+    search_path.dirs.push(dir.to_string());
+    let stem = source_file.strip_suffix(".src").unwrap_or(&source_file);
+    search_path.objects.insert(source_file.clone(), format!("{}.obj", stem));
+
+    let modules = compile(&mut search_path, source_file.clone(), source);
+    let mut failed = false;
+    for module in &modules {
+        if module.from_object {
+            println!("Loaded {} from its object file", module.module_id);
+        }
+        if let Some(err) = &module.object_error {
+            println!("Ignoring the object file of {}: {}", module.module_id, err);
+        }
+        let named_source = NamedSource::new(module.module_id.clone(), module.source.clone());
+        for err in &module.parsed.lex_errors {
+            print_error(err, &named_source);
+        }
+        for err in &module.parsed.parse_errors {
+            print_error(err, &named_source);
+        }
+        for err in &module.state.errors {
+            print_error(err, &named_source);
+        }
+        if !module.state.errors.is_empty() {
+            println!("Elaboration of {} failed with {} error(s)", module.module_id, module.state.errors.len());
+            failed = true;
+        }
+    }
+
+    if timings {
+        for module in &modules {
+            print_timings(module);
+        }
+    }
+
+    let main = modules.last().unwrap();
+    match &main.parsed.ast {
+        Some(tree) => {
+            // Under `--run` only the program's own output follows.
+            if !run {
+                println!("AST produced for module {}: {:#?}", main.module_id, tree);
+            }
+            for term in &main.state.evaluations {
+                println!("Evaluated term: {}", term);
+            }
+            if !failed && !run {
+                println!("Elaboration successful:\n{}", main.state.env);
+            }
+            if !failed && anf {
+                match lower_environment(&main.state.env) {
+                    Ok(program) => print!("A-normal form:\n{}", program),
+                    Err(err) => {
+                        println!("Error: {}", err);
+                        return 1;
+                    }
+                }
+            }
+            if !failed
+                && run
+                && let Err(err) = run_main(&main.state.env, &mut StdConsole::default())
+            {
+                println!("Error: {}", err);
+                return 1;
+            }
+        }
+        None if main.parsed.parse_errors.is_empty() && main.parsed.lex_errors.is_empty() => println!("No AST produced"),
+        None => {}
     }
+    0
 }
@@ -1,7 +1,10 @@
 use alloc::string::String;
 
 pub mod name;
+pub mod object;
 pub mod prim;
 pub mod unique;
 
+/// The dotted name of a module, such as `Data.List`, which is also its path
+/// below a directory of the module search path.
 pub type ModuleId = String;
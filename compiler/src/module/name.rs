@@ -1,5 +1,60 @@
+use core::fmt;
+
+use alloc::{string::String, vec::Vec};
+
 use crate::module::{unique::Unique};
 
+/// A hierarchical name such as `Data.List.map`. The root namespace has no
+/// components.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Name {
+    components: Vec<String>,
+}
+
+impl Name {
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(path: &str) -> Self {
+        Self {
+            components: path.split('.').filter(|c| !c.is_empty()).map(String::from).collect(),
+        }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    pub fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    /// `self` followed by the components of `other`.
+    pub fn join(&self, other: &Name) -> Name {
+        let mut components = self.components.clone();
+        components.extend(other.components.iter().cloned());
+        Name { components }
+    }
+
+    pub fn parent(&self) -> Option<Name> {
+        let (_, init) = self.components.split_last()?;
+        Some(Name {
+            components: init.to_vec(),
+        })
+    }
+
+    pub fn starts_with(&self, prefix: &Name) -> bool {
+        self.components.starts_with(&prefix.components)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.components.join("."))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum QualifiedName {
     User(Unique),
@@ -7,12 +62,17 @@ pub enum QualifiedName {
 }
 
 impl QualifiedName {
+    /// The full dotted name the declaration was given.
     pub fn display(&self) -> Option<&str> {
         match self {
             QualifiedName::User(u) => u.display_name.as_deref(),
             QualifiedName::Intrinsic(i) => Some(i.name()),
         }
     }
+
+    pub fn path(&self) -> Option<Name> {
+        self.display().map(Name::parse)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -23,9 +83,49 @@ pub enum IntrinsicName {
     Array,
     ArrayNil,
     ArrayCons,
+    NatAdd,
+    NatSub,
+    NatMul,
+    NatDiv,
+    NatMod,
+    Unit,
+    UnitUnit,
+    IO,
+    IOPure,
+    IOBind,
+    IOPrint,
+    IOPrintln,
+    IOReadLine,
+    NatToStr,
+    StrAppend,
 }
 
 impl IntrinsicName {
+    /// In the order their tags in object files are numbered.
+    pub const ALL: [IntrinsicName; 21] = [
+        IntrinsicName::Nat,
+        IntrinsicName::Str,
+        IntrinsicName::Fin,
+        IntrinsicName::Array,
+        IntrinsicName::ArrayNil,
+        IntrinsicName::ArrayCons,
+        IntrinsicName::NatAdd,
+        IntrinsicName::NatSub,
+        IntrinsicName::NatMul,
+        IntrinsicName::NatDiv,
+        IntrinsicName::NatMod,
+        IntrinsicName::Unit,
+        IntrinsicName::UnitUnit,
+        IntrinsicName::IO,
+        IntrinsicName::IOPure,
+        IntrinsicName::IOBind,
+        IntrinsicName::IOPrint,
+        IntrinsicName::IOPrintln,
+        IntrinsicName::IOReadLine,
+        IntrinsicName::NatToStr,
+        IntrinsicName::StrAppend,
+    ];
+
     pub fn name(&self) -> &str {
         match self {
             IntrinsicName::Nat => "Nat",
@@ -33,7 +133,22 @@ impl IntrinsicName {
             IntrinsicName::Fin => "Fin",
             IntrinsicName::Array => "Array",
             IntrinsicName::ArrayNil => "Array.nil",
-            IntrinsicName::ArrayCons => "Array.cons"
+            IntrinsicName::ArrayCons => "Array.cons",
+            IntrinsicName::NatAdd => "Nat.add",
+            IntrinsicName::NatSub => "Nat.sub",
+            IntrinsicName::NatMul => "Nat.mul",
+            IntrinsicName::NatDiv => "Nat.div",
+            IntrinsicName::NatMod => "Nat.mod",
+            IntrinsicName::Unit => "Unit",
+            IntrinsicName::UnitUnit => "Unit.unit",
+            IntrinsicName::IO => "IO",
+            IntrinsicName::IOPure => "IO.pure",
+            IntrinsicName::IOBind => "IO.bind",
+            IntrinsicName::IOPrint => "IO.print",
+            IntrinsicName::IOPrintln => "IO.println",
+            IntrinsicName::IOReadLine => "IO.readLine",
+            IntrinsicName::NatToStr => "Nat.toStr",
+            IntrinsicName::StrAppend => "Str.append",
         }
     }
 }
\ No newline at end of file
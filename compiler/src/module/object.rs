@@ -0,0 +1,779 @@
+//! Object files: elaborated environments stored so that importing a module
+//! doesn't have to elaborate it again.
+//!
+//! An object file starts with a header of the magic bytes, the format version
+//! and a checksum of the rest, all little-endian. The rest is a table of
+//! strings, a table of levels, a table of terms and then the environment and
+//! the cached commands, which refer to entries of the tables by index. Numbers are LEB128
+//! encoded. Levels and terms are hash-consed: each distinct subterm is stored
+//! once, after its children, as a tag followed by the indices of its
+//! children.
+
+use alloc::{collections::btree_map::BTreeMap, string::String, vec::Vec};
+
+use thiserror::Error;
+
+use crate::{
+    elaboration::{Declaration, Environment, RecursorRule},
+    module::{
+        ModuleId,
+        name::{IntrinsicName, QualifiedName},
+        unique::Unique,
+    },
+    spine::{BinderInfo, BinderName, Level, Literal, Term},
+};
+
+pub const MAGIC: [u8; 4] = *b"MOBJ";
+
+/// Bumped whenever the encoding changes, so that object files written by
+/// another version are rejected instead of misread.
+pub const VERSION: u32 = 4;
+
+const HEADER_LEN: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum ObjectError {
+    #[error("not an object file")]
+    BadMagic,
+    #[error("object file has format version {found}, but this compiler reads version {expected}")]
+    VersionMismatch { found: u32, expected: u32 },
+    #[error("object file is corrupt: its checksum doesn't match its contents")]
+    ChecksumMismatch,
+    #[error("object file is truncated")]
+    Truncated,
+    #[error("object file is malformed: {0}")]
+    Malformed(&'static str),
+}
+
+/// What an object file stores about a module.
+#[derive(Debug, Clone)]
+pub struct Object {
+    /// The hash of the source the module was elaborated from.
+    pub source_hash: u64,
+    /// The modules imported and the hash of the object file of each, so an
+    /// object is only reused while its imports are unchanged.
+    pub imports: Vec<(ModuleId, u64)>,
+    pub env: Environment,
+    /// The module's top-level commands that declared something, so that
+    /// recompiling it can reuse those that are unchanged.
+    pub commands: Vec<CachedCommand>,
+}
+
+/// What elaborating a top-level command added to the environment.
+#[derive(Debug, Clone)]
+pub struct CachedCommand {
+    /// The hash of the command and of the scope it was elaborated in.
+    pub hash: u64,
+    pub decls: Vec<Declaration>,
+    pub classes: Vec<QualifiedName>,
+    pub instances: Vec<QualifiedName>,
+    /// The declarations of earlier commands that `decls` refer to.
+    pub dependencies: Vec<QualifiedName>,
+    /// The global names the command mentions and what each resolved to.
+    pub resolutions: Vec<(String, QualifiedName)>,
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+pub fn write_object(object: &Object) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.uint(object.source_hash);
+    writer.uint(object.imports.len() as u64);
+    for (module, hash) in &object.imports {
+        writer.string(module);
+        writer.uint(*hash);
+    }
+    writer.environment(&object.env);
+    writer.uint(object.commands.len() as u64);
+    for command in &object.commands {
+        writer.command(command);
+    }
+
+    let mut body = Vec::new();
+    uint(&mut body, writer.strings.len() as u64);
+    for string in &writer.strings {
+        uint(&mut body, string.len() as u64);
+        body.extend_from_slice(string.as_bytes());
+    }
+    for table in [&writer.levels, &writer.terms] {
+        uint(&mut body, table.len() as u64);
+        for node in table {
+            body.extend_from_slice(node);
+        }
+    }
+    body.extend_from_slice(&writer.out);
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&hash(&body).to_le_bytes());
+    bytes.extend_from_slice(&body);
+    bytes
+}
+
+pub fn read_object(bytes: &[u8]) -> Result<Object, ObjectError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(if bytes.starts_with(&MAGIC) {
+            ObjectError::Truncated
+        } else {
+            ObjectError::BadMagic
+        });
+    }
+    if bytes[..4] != MAGIC {
+        return Err(ObjectError::BadMagic);
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version != VERSION {
+        return Err(ObjectError::VersionMismatch {
+            found: version,
+            expected: VERSION,
+        });
+    }
+    let checksum = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+    let body = &bytes[HEADER_LEN..];
+    if hash(body) != checksum {
+        return Err(ObjectError::ChecksumMismatch);
+    }
+
+    let mut reader = Reader {
+        bytes: body,
+        pos: 0,
+        strings: Vec::new(),
+        levels: Vec::new(),
+        terms: Vec::new(),
+    };
+    reader.tables()?;
+    let source_hash = reader.uint()?;
+    let mut imports = Vec::new();
+    for _ in 0..reader.uint()? {
+        imports.push((reader.string()?, reader.uint()?));
+    }
+    let env = reader.environment()?;
+    let mut commands = Vec::new();
+    for _ in 0..reader.uint()? {
+        commands.push(reader.command()?);
+    }
+    if reader.pos != body.len() {
+        return Err(ObjectError::Malformed("trailing bytes"));
+    }
+    Ok(Object {
+        source_hash,
+        imports,
+        env,
+        commands,
+    })
+}
+
+fn uint(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    strings: Vec<String>,
+    string_ids: BTreeMap<String, u64>,
+    levels: Vec<Vec<u8>>,
+    level_ids: BTreeMap<Vec<u8>, u64>,
+    terms: Vec<Vec<u8>>,
+    term_ids: BTreeMap<Vec<u8>, u64>,
+    /// The environment, written after the tables it refers to.
+    out: Vec<u8>,
+}
+
+impl Writer {
+    fn uint(&mut self, n: u64) {
+        uint(&mut self.out, n);
+    }
+
+    fn string(&mut self, string: &str) {
+        let id = self.string_id(string);
+        self.uint(id);
+    }
+
+    fn string_id(&mut self, string: &str) -> u64 {
+        if let Some(id) = self.string_ids.get(string) {
+            return *id;
+        }
+        let id = self.strings.len() as u64;
+        self.strings.push(string.into());
+        self.string_ids.insert(string.into(), id);
+        id
+    }
+
+    fn unique(&mut self, node: &mut Vec<u8>, unique: &Unique) {
+        uint(node, unique.id as u64);
+        let module = self.string_id(&unique.module_id);
+        uint(node, module);
+        self.display_name(node, &unique.display_name);
+    }
+
+    fn display_name(&mut self, node: &mut Vec<u8>, name: &Option<String>) {
+        match name {
+            Some(name) => {
+                let name = self.string_id(name);
+                uint(node, name + 1);
+            }
+            None => uint(node, 0),
+        }
+    }
+
+    fn name(&mut self, node: &mut Vec<u8>, name: &QualifiedName) {
+        match name {
+            QualifiedName::User(unique) => {
+                node.push(0);
+                self.unique(node, unique);
+            }
+            QualifiedName::Intrinsic(intrinsic) => {
+                node.push(1);
+                node.push(intrinsic_tag(intrinsic));
+            }
+        }
+    }
+
+    fn level(&mut self, level: &Level) -> u64 {
+        let mut node = Vec::new();
+        match level {
+            Level::Zero => node.push(0),
+            Level::Succ(inner) => {
+                let inner = self.level(inner);
+                node.push(1);
+                uint(&mut node, inner);
+            }
+            Level::Max(a, b) | Level::IMax(a, b) => {
+                let (a, b) = (self.level(a), self.level(b));
+                node.push(if matches!(level, Level::Max(..)) { 2 } else { 3 });
+                uint(&mut node, a);
+                uint(&mut node, b);
+            }
+            Level::Param(name) => {
+                node.push(4);
+                let name = self.string_id(name);
+                uint(&mut node, name);
+            }
+            Level::MVar(unique) => {
+                node.push(5);
+                self.unique(&mut node, unique);
+            }
+        }
+        intern(&mut self.levels, &mut self.level_ids, node)
+    }
+
+    fn term(&mut self, term: &Term) -> u64 {
+        let mut node = Vec::new();
+        match term {
+            Term::BVar(index) => {
+                node.push(0);
+                uint(&mut node, *index as u64);
+            }
+            Term::FVar(unique) | Term::MVar(unique) => {
+                node.push(if matches!(term, Term::FVar(_)) { 1 } else { 2 });
+                self.unique(&mut node, unique);
+            }
+            Term::App(f, a) => {
+                let (f, a) = (self.term(f), self.term(a));
+                node.push(3);
+                uint(&mut node, f);
+                uint(&mut node, a);
+            }
+            Term::Sort(level) => {
+                let level = self.level(level);
+                node.push(4);
+                uint(&mut node, level);
+            }
+            Term::Const(name, levels) => {
+                let levels: Vec<u64> = levels.iter().map(|level| self.level(level)).collect();
+                node.push(5);
+                self.name(&mut node, name);
+                uint(&mut node, levels.len() as u64);
+                for level in levels {
+                    uint(&mut node, level);
+                }
+            }
+            Term::Lam(name, info, a, b) | Term::Pi(name, info, a, b) | Term::Sigma(name, info, a, b) => {
+                let (a, b) = (self.term(a), self.term(b));
+                node.push(match term {
+                    Term::Lam(..) => 6,
+                    Term::Pi(..) => 7,
+                    _ => 8,
+                });
+                self.display_name(&mut node, &name.0);
+                node.push(binder_info_tag(info));
+                uint(&mut node, a);
+                uint(&mut node, b);
+            }
+            Term::Let(name, a, b, c) => {
+                let (a, b, c) = (self.term(a), self.term(b), self.term(c));
+                node.push(9);
+                self.display_name(&mut node, &name.0);
+                uint(&mut node, a);
+                uint(&mut node, b);
+                uint(&mut node, c);
+            }
+            Term::Pair(a, b, c) => {
+                let (a, b, c) = (self.term(a), self.term(b), self.term(c));
+                node.push(10);
+                uint(&mut node, a);
+                uint(&mut node, b);
+                uint(&mut node, c);
+            }
+            Term::Lit(Literal::Nat(n)) => {
+                node.push(11);
+                uint(&mut node, *n);
+            }
+            Term::Lit(Literal::Str(string)) => {
+                node.push(12);
+                let string = self.string_id(string);
+                uint(&mut node, string);
+            }
+            Term::Fst(pair) | Term::Snd(pair) => {
+                let pair = self.term(pair);
+                node.push(if matches!(term, Term::Fst(_)) { 13 } else { 14 });
+                uint(&mut node, pair);
+            }
+        }
+        intern(&mut self.terms, &mut self.term_ids, node)
+    }
+
+    fn term_ref(&mut self, term: &Term) {
+        let id = self.term(term);
+        self.uint(id);
+    }
+
+    fn name_ref(&mut self, name: &QualifiedName) {
+        let mut node = Vec::new();
+        self.name(&mut node, name);
+        self.out.extend_from_slice(&node);
+    }
+
+    fn declaration(&mut self, decl: &Declaration) {
+        let tag = match decl {
+            Declaration::Definition { .. } => 0,
+            Declaration::Constructor { .. } => 1,
+            Declaration::Inductive { .. } => 2,
+            Declaration::Intro { .. } => 3,
+            Declaration::Recursor { .. } => 4,
+        };
+        self.out.push(tag);
+        self.name_ref(decl.name());
+        self.uint(decl.level_params().len() as u64);
+        for param in decl.level_params() {
+            self.string(param);
+        }
+        self.term_ref(decl.type_());
+        match decl {
+            Declaration::Definition { value, .. } => self.term_ref(value),
+            Declaration::Constructor { .. } => {}
+            Declaration::Inductive {
+                num_params,
+                num_indices,
+                constructors,
+                recursor,
+                ..
+            } => {
+                self.uint(*num_params as u64);
+                self.uint(*num_indices as u64);
+                self.uint(constructors.len() as u64);
+                for ctor in constructors {
+                    self.name_ref(ctor);
+                }
+                self.name_ref(recursor);
+            }
+            Declaration::Intro {
+                inductive,
+                num_params,
+                num_fields,
+                ..
+            } => {
+                self.name_ref(inductive);
+                self.uint(*num_params as u64);
+                self.uint(*num_fields as u64);
+            }
+            Declaration::Recursor {
+                inductive,
+                num_params,
+                num_indices,
+                num_minors,
+                rules,
+                ..
+            } => {
+                self.name_ref(inductive);
+                self.uint(*num_params as u64);
+                self.uint(*num_indices as u64);
+                self.uint(*num_minors as u64);
+                self.uint(rules.len() as u64);
+                for rule in rules {
+                    self.name_ref(&rule.constructor);
+                    self.uint(rule.num_fields as u64);
+                    self.term_ref(&rule.rhs);
+                }
+            }
+        }
+    }
+
+    fn command(&mut self, command: &CachedCommand) {
+        self.out.extend_from_slice(&command.hash.to_le_bytes());
+        self.uint(command.decls.len() as u64);
+        for decl in &command.decls {
+            self.declaration(decl);
+        }
+        for names in [&command.classes, &command.instances, &command.dependencies] {
+            self.uint(names.len() as u64);
+            for name in names {
+                self.name_ref(name);
+            }
+        }
+        self.uint(command.resolutions.len() as u64);
+        for (name, resolved) in &command.resolutions {
+            self.string(name);
+            self.name_ref(resolved);
+        }
+    }
+
+    fn environment(&mut self, env: &Environment) {
+        self.string(&env.module_id);
+        self.uint(env.decls.len() as u64);
+        for decl in env.decls.values() {
+            self.declaration(decl);
+        }
+        for names in [
+            env.classes.iter().collect::<Vec<_>>(),
+            env.instances.iter().collect(),
+        ] {
+            self.uint(names.len() as u64);
+            for name in names {
+                self.name_ref(name);
+            }
+        }
+    }
+}
+
+/// The index of `node` in `table`, adding it if it isn't there yet.
+fn intern(table: &mut Vec<Vec<u8>>, ids: &mut BTreeMap<Vec<u8>, u64>, node: Vec<u8>) -> u64 {
+    if let Some(id) = ids.get(&node) {
+        return *id;
+    }
+    let id = table.len() as u64;
+    table.push(node.clone());
+    ids.insert(node, id);
+    id
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    strings: Vec<String>,
+    levels: Vec<Level>,
+    terms: Vec<Term>,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, ObjectError> {
+        let byte = *self.bytes.get(self.pos).ok_or(ObjectError::Truncated)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn uint(&mut self) -> Result<u64, ObjectError> {
+        let mut n = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 64 {
+                return Err(ObjectError::Malformed("number out of range"));
+            }
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+
+    fn usize(&mut self) -> Result<usize, ObjectError> {
+        usize::try_from(self.uint()?).map_err(|_| ObjectError::Malformed("number out of range"))
+    }
+
+    fn string(&mut self) -> Result<String, ObjectError> {
+        let id = self.usize()?;
+        self.strings
+            .get(id)
+            .cloned()
+            .ok_or(ObjectError::Malformed("string index out of range"))
+    }
+
+    fn level_ref(&mut self) -> Result<Level, ObjectError> {
+        let id = self.usize()?;
+        self.levels
+            .get(id)
+            .cloned()
+            .ok_or(ObjectError::Malformed("level index out of range"))
+    }
+
+    fn term_ref(&mut self) -> Result<Term, ObjectError> {
+        let id = self.usize()?;
+        self.terms
+            .get(id)
+            .cloned()
+            .ok_or(ObjectError::Malformed("term index out of range"))
+    }
+
+    fn tables(&mut self) -> Result<(), ObjectError> {
+        for _ in 0..self.uint()? {
+            let len = self.usize()?;
+            let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len());
+            let bytes = &self.bytes[self.pos..end.ok_or(ObjectError::Truncated)?];
+            let string = core::str::from_utf8(bytes).map_err(|_| ObjectError::Malformed("string is not UTF-8"))?;
+            self.strings.push(string.into());
+            self.pos += len;
+        }
+        for _ in 0..self.uint()? {
+            let level = self.level()?;
+            self.levels.push(level);
+        }
+        for _ in 0..self.uint()? {
+            let term = self.term()?;
+            self.terms.push(term);
+        }
+        Ok(())
+    }
+
+    fn unique(&mut self) -> Result<Unique, ObjectError> {
+        let id = self.usize()?;
+        let module_id = self.string()?;
+        let display_name = self.display_name()?;
+        Ok(Unique::new(id, module_id, display_name))
+    }
+
+    fn display_name(&mut self) -> Result<Option<String>, ObjectError> {
+        Ok(match self.usize()? {
+            0 => None,
+            name => Some(
+                self.strings
+                    .get(name - 1)
+                    .cloned()
+                    .ok_or(ObjectError::Malformed("string index out of range"))?,
+            ),
+        })
+    }
+
+    fn name(&mut self) -> Result<QualifiedName, ObjectError> {
+        match self.byte()? {
+            0 => Ok(QualifiedName::User(self.unique()?)),
+            1 => intrinsic(self.byte()?).map(QualifiedName::Intrinsic),
+            _ => Err(ObjectError::Malformed("invalid name tag")),
+        }
+    }
+
+    fn level(&mut self) -> Result<Level, ObjectError> {
+        Ok(match self.byte()? {
+            0 => Level::Zero,
+            1 => self.level_ref()?.succ(),
+            2 => Level::Max(self.level_ref()?.into(), self.level_ref()?.into()),
+            3 => Level::IMax(self.level_ref()?.into(), self.level_ref()?.into()),
+            4 => Level::Param(self.string()?),
+            5 => Level::MVar(self.unique()?),
+            _ => return Err(ObjectError::Malformed("invalid level tag")),
+        })
+    }
+
+    fn term(&mut self) -> Result<Term, ObjectError> {
+        Ok(match self.byte()? {
+            0 => Term::BVar(self.usize()?),
+            1 => Term::FVar(self.unique()?),
+            2 => Term::MVar(self.unique()?),
+            3 => Term::App(self.term_ref()?.boxed(), self.term_ref()?.boxed()),
+            4 => Term::Sort(self.level_ref()?),
+            5 => {
+                let name = self.name()?;
+                let mut levels = Vec::new();
+                for _ in 0..self.uint()? {
+                    levels.push(self.level_ref()?);
+                }
+                Term::Const(name, levels)
+            }
+            tag @ 6..=8 => {
+                let name = BinderName(self.display_name()?);
+                let info = binder_info(self.byte()?)?;
+                let (a, b) = (self.term_ref()?.boxed(), self.term_ref()?.boxed());
+                match tag {
+                    6 => Term::Lam(name, info, a, b),
+                    7 => Term::Pi(name, info, a, b),
+                    _ => Term::Sigma(name, info, a, b),
+                }
+            }
+            9 => Term::Let(
+                BinderName(self.display_name()?),
+                self.term_ref()?.boxed(),
+                self.term_ref()?.boxed(),
+                self.term_ref()?.boxed(),
+            ),
+            10 => Term::Pair(self.term_ref()?.boxed(), self.term_ref()?.boxed(), self.term_ref()?.boxed()),
+            11 => Term::Lit(Literal::Nat(self.uint()?)),
+            12 => Term::Lit(Literal::Str(self.string()?)),
+            13 => Term::Fst(self.term_ref()?.boxed()),
+            14 => Term::Snd(self.term_ref()?.boxed()),
+            _ => return Err(ObjectError::Malformed("invalid term tag")),
+        })
+    }
+
+    fn declaration(&mut self) -> Result<Declaration, ObjectError> {
+        let tag = self.byte()?;
+        let name = self.name()?;
+        let mut level_params = Vec::new();
+        for _ in 0..self.uint()? {
+            level_params.push(self.string()?);
+        }
+        let type_ = self.term_ref()?;
+        Ok(match tag {
+            0 => Declaration::Definition {
+                name,
+                level_params,
+                type_,
+                value: self.term_ref()?,
+            },
+            1 => Declaration::Constructor {
+                name,
+                level_params,
+                type_,
+            },
+            2 => {
+                let num_params = self.usize()?;
+                let num_indices = self.usize()?;
+                let mut constructors = Vec::new();
+                for _ in 0..self.uint()? {
+                    constructors.push(self.name()?);
+                }
+                Declaration::Inductive {
+                    name,
+                    level_params,
+                    type_,
+                    num_params,
+                    num_indices,
+                    constructors,
+                    recursor: self.name()?,
+                }
+            }
+            3 => Declaration::Intro {
+                name,
+                level_params,
+                type_,
+                inductive: self.name()?,
+                num_params: self.usize()?,
+                num_fields: self.usize()?,
+            },
+            4 => {
+                let inductive = self.name()?;
+                let num_params = self.usize()?;
+                let num_indices = self.usize()?;
+                let num_minors = self.usize()?;
+                let mut rules = Vec::new();
+                for _ in 0..self.uint()? {
+                    rules.push(RecursorRule {
+                        constructor: self.name()?,
+                        num_fields: self.usize()?,
+                        rhs: self.term_ref()?,
+                    });
+                }
+                Declaration::Recursor {
+                    name,
+                    level_params,
+                    type_,
+                    inductive,
+                    num_params,
+                    num_indices,
+                    num_minors,
+                    rules,
+                }
+            }
+            _ => return Err(ObjectError::Malformed("invalid declaration tag")),
+        })
+    }
+
+    fn names(&mut self) -> Result<Vec<QualifiedName>, ObjectError> {
+        let mut names = Vec::new();
+        for _ in 0..self.uint()? {
+            names.push(self.name()?);
+        }
+        Ok(names)
+    }
+
+    fn command(&mut self) -> Result<CachedCommand, ObjectError> {
+        let end = self.pos + 8;
+        let hash = self.bytes.get(self.pos..end).ok_or(ObjectError::Truncated)?;
+        let hash = u64::from_le_bytes(hash.try_into().unwrap());
+        self.pos = end;
+        let mut decls = Vec::new();
+        for _ in 0..self.uint()? {
+            decls.push(self.declaration()?);
+        }
+        let classes = self.names()?;
+        let instances = self.names()?;
+        let dependencies = self.names()?;
+        let mut resolutions = Vec::new();
+        for _ in 0..self.uint()? {
+            resolutions.push((self.string()?, self.name()?));
+        }
+        Ok(CachedCommand {
+            hash,
+            decls,
+            classes,
+            instances,
+            dependencies,
+            resolutions,
+        })
+    }
+
+    fn environment(&mut self) -> Result<Environment, ObjectError> {
+        let mut env = Environment::empty(self.string()?);
+        for _ in 0..self.uint()? {
+            let decl = self.declaration()?;
+            env.insert(decl);
+        }
+        env.classes.extend(self.names()?);
+        env.instances = self.names()?;
+        Ok(env)
+    }
+}
+
+fn binder_info_tag(info: &BinderInfo) -> u8 {
+    match info {
+        BinderInfo::Explicit => 0,
+        BinderInfo::Implicit => 1,
+        BinderInfo::InstanceImplicit => 2,
+        BinderInfo::StrictImplicit => 3,
+    }
+}
+
+fn binder_info(tag: u8) -> Result<BinderInfo, ObjectError> {
+    Ok(match tag {
+        0 => BinderInfo::Explicit,
+        1 => BinderInfo::Implicit,
+        2 => BinderInfo::InstanceImplicit,
+        3 => BinderInfo::StrictImplicit,
+        _ => return Err(ObjectError::Malformed("invalid binder tag")),
+    })
+}
+
+fn intrinsic_tag(intrinsic: &IntrinsicName) -> u8 {
+    IntrinsicName::ALL.iter().position(|i| i == intrinsic).unwrap() as u8
+}
+
+fn intrinsic(tag: u8) -> Result<IntrinsicName, ObjectError> {
+    IntrinsicName::ALL
+        .get(tag as usize)
+        .cloned()
+        .ok_or(ObjectError::Malformed("invalid intrinsic"))
+}
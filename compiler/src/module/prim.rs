@@ -5,4 +5,37 @@ pub const PRIM_FIN: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::Fin)
 pub const PRIM_STRING: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::Str);
 pub const PRIM_ARRAY: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::Array);
 pub const PRIM_ARRAY_NIL: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::ArrayNil);
-pub const PRIM_ARRAY_CONS: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::ArrayCons);
\ No newline at end of file
+pub const PRIM_ARRAY_CONS: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::ArrayCons);
+pub const PRIM_NAT_ADD: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::NatAdd);
+pub const PRIM_NAT_SUB: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::NatSub);
+pub const PRIM_NAT_MUL: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::NatMul);
+pub const PRIM_NAT_DIV: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::NatDiv);
+pub const PRIM_NAT_MOD: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::NatMod);
+pub const PRIM_UNIT: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::Unit);
+pub const PRIM_UNIT_UNIT: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::UnitUnit);
+pub const PRIM_IO: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::IO);
+pub const PRIM_IO_PURE: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::IOPure);
+pub const PRIM_IO_BIND: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::IOBind);
+pub const PRIM_IO_PRINT: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::IOPrint);
+pub const PRIM_IO_PRINTLN: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::IOPrintln);
+pub const PRIM_IO_READ_LINE: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::IOReadLine);
+pub const PRIM_NAT_TO_STR: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::NatToStr);
+pub const PRIM_STR_APPEND: QualifiedName = QualifiedName::Intrinsic(IntrinsicName::StrAppend);
+
+pub const PRIM_NAT_BINOPS: [QualifiedName; 5] = [PRIM_NAT_ADD, PRIM_NAT_SUB, PRIM_NAT_MUL, PRIM_NAT_DIV, PRIM_NAT_MOD];
+
+/// Computes a `Nat` primitive on literals. Subtraction truncates at zero and
+/// division by zero yields zero; overflowing results stay unreduced.
+pub fn eval_nat_binop(name: &QualifiedName, a: u64, b: u64) -> Option<u64> {
+    let QualifiedName::Intrinsic(intrinsic) = name else {
+        return None;
+    };
+    match intrinsic {
+        IntrinsicName::NatAdd => a.checked_add(b),
+        IntrinsicName::NatSub => Some(a.saturating_sub(b)),
+        IntrinsicName::NatMul => a.checked_mul(b),
+        IntrinsicName::NatDiv => Some(a.checked_div(b).unwrap_or(0)),
+        IntrinsicName::NatMod => Some(a.checked_rem(b).unwrap_or(a)),
+        _ => None,
+    }
+}
@@ -70,6 +70,12 @@ impl UniqueGen {
         Unique::new(id as usize, self.module_id.clone(), Some(name))
     }
     
+    /// Makes sure no later unique has an id up to `id`, such as those of
+    /// declarations read back from an object file.
+    pub fn skip_past(&mut self, id: usize) {
+        self.next = self.next.max(id as u64 + 1);
+    }
+
     pub fn fresh_unnamed(&mut self) -> Unique {
         let id = self.next;
         self.next += 1;
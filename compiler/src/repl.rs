@@ -0,0 +1,254 @@
+//! An interactive session that elaborates one input at a time into the same
+//! state, so each input sees what the ones before it declared.
+//!
+//! Nothing here reads or prints: the host passes each input to `Repl::run`
+//! and prints the reply, so the same session runs from `compiler --repl` and
+//! from a shell without a file system, where `:load` and imports find
+//! nothing.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::fmt::Write;
+
+use miette::NamedSource;
+
+use crate::{
+    driver::{Files, ModuleLoader, Parsed, collect_imports, compile_imports, parse_expr_source, parse_source},
+    elaboration::{ElabState, nbe},
+    log::{
+        pretty::{PrettyOptions, pretty_declaration, pretty_term_with},
+        render,
+    },
+    spine::Term,
+    syntax::{
+        SourceFile, Span,
+        error::ParseErrorKind,
+        lexer::Lexer,
+        token::TokenKind,
+        tree::{SyntaxExpr, SyntaxExprKind},
+    },
+};
+
+/// The module the inputs are elaborated in.
+const MODULE: &str = "repl";
+
+const HELP: &str = "\
+Enter commands such as `def` and `eval e;`, or an expression to evaluate.
+  :type e      the type of expression `e`
+  :print name  the declaration `name` refers to
+  :load file   runs the commands of `file`
+  :reset       forgets everything declared so far
+  :quit        ends the session
+";
+
+pub struct Repl {
+    state: ElabState,
+    loader: Box<dyn ModuleLoader>,
+    files: Box<dyn Files>,
+    /// How many sources were parsed so far, each input or loaded file being
+    /// its own source.
+    sources: usize,
+    quit: bool,
+}
+
+impl Repl {
+    pub fn new(loader: Box<dyn ModuleLoader>, files: Box<dyn Files>) -> Self {
+        Self {
+            state: ElabState::pre_loaded(MODULE.into()),
+            loader,
+            files,
+            sources: 0,
+            quit: false,
+        }
+    }
+
+    /// Whether `:quit` was entered.
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Whether `input` stops in the middle of a command or expression, so
+    /// the host should read another line before running it.
+    pub fn is_incomplete(&mut self, input: &str) -> bool {
+        if input.trim_start().starts_with(':') {
+            return false;
+        }
+        let parsed = self.parse(input, "<input>");
+        parsed.lex_errors.is_empty()
+            && !parsed.parse_errors.is_empty()
+            && parsed
+                .parse_errors
+                .iter()
+                .all(|err| err.kind == ParseErrorKind::UnexpectedEndOfInput)
+    }
+
+    /// Runs one input and returns what to print for it.
+    pub fn run(&mut self, input: &str) -> String {
+        let mut out = String::new();
+        let input = input.trim();
+        let (command, argument) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let argument = argument.trim();
+        match command {
+            "" => {}
+            ":quit" | ":q" => self.quit = true,
+            ":help" | ":h" => out.push_str(HELP),
+            ":reset" => self.state = ElabState::pre_loaded(MODULE.into()),
+            ":type" | ":t" => self.type_of(argument, &mut out),
+            ":print" | ":p" => self.print(argument, &mut out),
+            ":load" | ":l" => match self.files.read(argument) {
+                Some(source) => self.run_source(&source, argument, &mut out),
+                None => _ = writeln!(out, "Couldn't read {}", argument),
+            },
+            _ if command.starts_with(':') => _ = writeln!(out, "Unknown command {}, see :help", command),
+            _ => self.run_source(input, "<input>", &mut out),
+        }
+        out
+    }
+
+    /// Parses `source` as commands if it starts with one, and otherwise as an
+    /// expression.
+    fn parse(&mut self, source: &str, name: &str) -> Parsed {
+        let source_file = SourceFile {
+            id: self.sources,
+            name,
+            source: source.as_bytes(),
+            package: None,
+        };
+        self.sources += 1;
+        match starts_with_command(&source_file) {
+            true => parse_source(&source_file),
+            false => parse_expr_source(&source_file),
+        }
+    }
+
+    fn run_source(&mut self, source: &str, name: &str, out: &mut String) {
+        let named_source = NamedSource::new(name, String::from(source));
+        let Some(syntax) = self.parse_reporting(source, name, &named_source, out) else {
+            return;
+        };
+        match &syntax.kind {
+            SyntaxExprKind::Root(commands) => {
+                self.import(&syntax, out);
+                for command in commands {
+                    self.state.elaborate_command(command);
+                }
+            }
+            _ => {
+                let errors_before = self.state.errors.len();
+                let (term, _) = self.state.elaborate_expr(&syntax);
+                if self.state.errors.len() == errors_before {
+                    self.state.evaluations.push(nbe::normalize(&self.state, &term));
+                }
+            }
+        }
+        self.report(&named_source, out);
+    }
+
+    fn type_of(&mut self, source: &str, out: &mut String) {
+        let named_source = NamedSource::new("<input>", String::from(source));
+        let Some(syntax) = self.parse_reporting(source, "<input>", &named_source, out) else {
+            return;
+        };
+        let errors_before = self.state.errors.len();
+        let (_, type_) = self.state.elaborate_expr(&syntax);
+        if self.state.errors.len() == errors_before {
+            let _ = writeln!(out, "{} : {}", source, self.pretty(&type_));
+        }
+        self.report(&named_source, out);
+    }
+
+    fn print(&mut self, name: &str, out: &mut String) {
+        let named_source = NamedSource::new("<input>", String::from(name));
+        // The name is its own source, so errors about it point at it.
+        self.state.span = Span::new(self.sources, 0, name.len());
+        self.sources += 1;
+        let found = self.state.resolve_global(name);
+        for err in core::mem::take(&mut self.state.errors) {
+            let _ = writeln!(out, "{}", render(&err, &named_source));
+        }
+        match found.and_then(|found| self.state.env.lookup(&found)) {
+            Some(decl) => {
+                let decl = pretty_declaration(decl, Some(&self.state.env), &PrettyOptions::default());
+                let _ = writeln!(out, "{}", decl);
+            }
+            None => _ = writeln!(out, "Unknown declaration {}", name),
+        }
+    }
+
+    fn parse_reporting(
+        &mut self,
+        source: &str,
+        name: &str,
+        named_source: &NamedSource<String>,
+        out: &mut String,
+    ) -> Option<SyntaxExpr> {
+        let parsed = self.parse(source, name);
+        for err in &parsed.lex_errors {
+            let _ = writeln!(out, "{}", render(err, named_source));
+        }
+        for err in &parsed.parse_errors {
+            let _ = writeln!(out, "{}", render(err, named_source));
+        }
+        if !parsed.lex_errors.is_empty() || !parsed.parse_errors.is_empty() {
+            return None;
+        }
+        parsed.ast
+    }
+
+    /// Compiles the modules `syntax` imports that weren't imported before.
+    fn import(&mut self, syntax: &SyntaxExpr, out: &mut String) {
+        let mut imports = Vec::new();
+        collect_imports(syntax, &mut imports);
+        imports.retain(|import| !self.state.imports.contains_key(import));
+        if imports.is_empty() {
+            return;
+        }
+        let (environments, modules) = compile_imports(&mut *self.loader, &imports);
+        for module in &modules {
+            let named_source = NamedSource::new(module.module_id.clone(), module.source.clone());
+            for err in &module.parsed.lex_errors {
+                let _ = writeln!(out, "{}", render(err, &named_source));
+            }
+            for err in &module.parsed.parse_errors {
+                let _ = writeln!(out, "{}", render(err, &named_source));
+            }
+            for err in &module.state.errors {
+                let _ = writeln!(out, "{}", render(err, &named_source));
+            }
+        }
+        self.state.imports.extend(environments);
+    }
+
+    /// Prints the errors and evaluations of the input just run.
+    fn report(&mut self, named_source: &NamedSource<String>, out: &mut String) {
+        for err in core::mem::take(&mut self.state.errors) {
+            let _ = writeln!(out, "{}", render(&err, named_source));
+        }
+        for term in core::mem::take(&mut self.state.evaluations) {
+            let _ = writeln!(out, "{}", self.pretty(&term));
+        }
+    }
+
+    fn pretty(&self, term: &Term) -> String {
+        pretty_term_with(term, Some(&self.state.env), &PrettyOptions::default())
+    }
+}
+
+fn starts_with_command(source_file: &SourceFile) -> bool {
+    let first = Lexer::new(source_file).find_map(Result::ok);
+    first.is_some_and(|token| {
+        matches!(
+            token.kind,
+            TokenKind::Def
+                | TokenKind::Eval
+                | TokenKind::Inductive
+                | TokenKind::Struct
+                | TokenKind::Class
+                | TokenKind::Instance
+                | TokenKind::Mutual
+                | TokenKind::Import
+                | TokenKind::Namespace
+                | TokenKind::Open
+        )
+    })
+}
+
@@ -1,4 +1,4 @@
-use alloc::{boxed::Box, string::String};
+use alloc::{boxed::Box, string::String, vec::Vec};
 
 use crate::module::{name::QualifiedName, unique::Unique};
 
@@ -9,12 +9,18 @@ pub enum Term {
     MVar(Unique),
     App(Box<Term>, Box<Term>),
     Sort(Level),
-    Const(QualifiedName),
-    Lam(BinderInfo, Box<Term>, Box<Term>),
-    Pi(BinderInfo, Box<Term>, Box<Term>),
-    Sigma(BinderInfo, Box<Term>, Box<Term>),
-    Let(Box<Term>, Box<Term>, Box<Term>),
+    /// A global constant with its universe level arguments.
+    Const(QualifiedName, Vec<Level>),
+    Lam(BinderName, BinderInfo, Box<Term>, Box<Term>),
+    Pi(BinderName, BinderInfo, Box<Term>, Box<Term>),
+    Sigma(BinderName, BinderInfo, Box<Term>, Box<Term>),
+    /// `Let(name, type, value, body)`.
+    Let(BinderName, Box<Term>, Box<Term>, Box<Term>),
     Lit(Literal),
+    /// `Pair(sigma, fst, snd)` carries its Sigma type so it can be inferred.
+    Pair(Box<Term>, Box<Term>, Box<Term>),
+    Fst(Box<Term>),
+    Snd(Box<Term>),
 }
 
 impl Term {
@@ -25,8 +31,68 @@ impl Term {
     pub fn mk_app(l: Term, r: Term) -> Self {
         Self::App(l.boxed(), r.boxed())
     }
+
+    pub fn mk_apps(f: Term, args: impl IntoIterator<Item = Term>) -> Self {
+        args.into_iter().fold(f, Self::mk_app)
+    }
+
+    pub fn app_head(&self) -> &Term {
+        match self {
+            Term::App(f, _) => f.app_head(),
+            _ => self,
+        }
+    }
+
+    pub fn const_name(&self) -> Option<&QualifiedName> {
+        match self {
+            Term::Const(name, _) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Applies `f` to every universe level in the term.
+    pub fn replace_levels(&self, f: &impl Fn(&Level) -> Level) -> Term {
+        let go = |term: &Term| Box::new(term.replace_levels(f));
+        match self {
+            Term::BVar(_) | Term::FVar(_) | Term::MVar(_) | Term::Lit(_) => self.clone(),
+            Term::Sort(level) => Term::Sort(f(level)),
+            Term::Const(name, levels) => Term::Const(name.clone(), levels.iter().map(f).collect()),
+            Term::App(a, b) => Term::App(go(a), go(b)),
+            Term::Lam(name, info, a, b) => Term::Lam(name.clone(), info.clone(), go(a), go(b)),
+            Term::Pi(name, info, a, b) => Term::Pi(name.clone(), info.clone(), go(a), go(b)),
+            Term::Sigma(name, info, a, b) => Term::Sigma(name.clone(), info.clone(), go(a), go(b)),
+            Term::Let(name, a, b, c) => Term::Let(name.clone(), go(a), go(b), go(c)),
+            Term::Pair(a, b, c) => Term::Pair(go(a), go(b), go(c)),
+            Term::Fst(p) => Term::Fst(go(p)),
+            Term::Snd(p) => Term::Snd(go(p)),
+        }
+    }
+
+    pub fn app_args(&self) -> Vec<&Term> {
+        let mut args = Vec::new();
+        let mut current = self;
+        while let Term::App(f, a) = current {
+            args.push(&**a);
+            current = f;
+        }
+        args.reverse();
+        args
+    }
 }
 
+/// The name a binder was written with. It is only kept to show the term the
+/// way it was written, so terms that differ in it are still equal.
+#[derive(Debug, Clone, Default)]
+pub struct BinderName(pub Option<String>);
+
+impl PartialEq for BinderName {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for BinderName {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BinderInfo {
     Explicit,
@@ -41,11 +107,138 @@ pub enum Literal {
     Str(String),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Level {
     Zero,
     Succ(Box<Level>),
     Max(Box<Level>, Box<Level>),
     IMax(Box<Level>, Box<Level>),
+    Param(String),
     MVar(Unique),
 }
+
+impl Level {
+    pub fn succ(self) -> Level {
+        Level::Succ(Box::new(self))
+    }
+
+    pub fn to_nat(&self) -> Option<u64> {
+        match self {
+            Level::Zero => Some(0),
+            Level::Succ(l) => l.to_nat().map(|n| n + 1),
+            Level::Max(a, b) | Level::IMax(a, b) => Some(a.to_nat()?.max(b.to_nat()?)),
+            Level::Param(_) | Level::MVar(_) => None,
+        }
+    }
+
+    pub fn max(a: Level, b: Level) -> Level {
+        match (a.to_nat(), b.to_nat()) {
+            (Some(x), Some(y)) if x >= y => a,
+            (Some(_), Some(_)) => b,
+            _ => Level::Max(Box::new(a), Box::new(b)),
+        }
+    }
+
+    pub fn is_never_zero(&self) -> bool {
+        match self {
+            Level::Zero | Level::Param(_) | Level::MVar(_) => false,
+            Level::Succ(_) => true,
+            Level::Max(a, b) => a.is_never_zero() || b.is_never_zero(),
+            Level::IMax(_, b) => b.is_never_zero(),
+        }
+    }
+
+    pub fn has_mvar(&self) -> bool {
+        match self {
+            Level::Zero | Level::Param(_) => false,
+            Level::MVar(_) => true,
+            Level::Succ(l) => l.has_mvar(),
+            Level::Max(a, b) | Level::IMax(a, b) => a.has_mvar() || b.has_mvar(),
+        }
+    }
+
+    /// Rebuilds the level, substituting the parameters and metavariables for
+    /// which `f` returns a replacement.
+    pub fn replace(&self, f: &impl Fn(&Level) -> Option<Level>) -> Level {
+        match self {
+            Level::Zero => Level::Zero,
+            Level::Succ(l) => l.replace(f).succ(),
+            Level::Max(a, b) => Level::Max(Box::new(a.replace(f)), Box::new(b.replace(f))),
+            Level::IMax(a, b) => Level::IMax(Box::new(a.replace(f)), Box::new(b.replace(f))),
+            Level::Param(_) | Level::MVar(_) => f(self).unwrap_or_else(|| self.clone()),
+        }
+    }
+
+    pub fn instantiate_params(&self, params: &[String], levels: &[Level]) -> Level {
+        self.replace(&|level| match level {
+            Level::Param(name) => params
+                .iter()
+                .position(|param| param == name)
+                .and_then(|i| levels.get(i).cloned()),
+            _ => None,
+        })
+    }
+
+    /// The level as `base + offset` components whose maximum it is, with a
+    /// `None` base standing for zero.
+    fn components(&self) -> Vec<(Option<Level>, u64)> {
+        match self {
+            Level::Zero => alloc::vec![(None, 0)],
+            Level::Succ(l) => l
+                .components()
+                .into_iter()
+                .map(|(base, offset)| (base, offset + 1))
+                .collect(),
+            Level::Max(a, b) => {
+                let mut components = a.components();
+                components.extend(b.components());
+                components
+            }
+            // `imax a b` is `0` when `b` is and `max a b` otherwise.
+            Level::IMax(a, b) if b.is_never_zero() => Level::Max(a.clone(), b.clone()).components(),
+            Level::IMax(a, b) => match b.normalize() {
+                Level::Zero => alloc::vec![(None, 0)],
+                b => alloc::vec![(Some(Level::IMax(Box::new(a.normalize()), Box::new(b))), 0)],
+            },
+            Level::Param(_) | Level::MVar(_) => alloc::vec![(Some(self.clone()), 0)],
+        }
+    }
+
+    /// A canonical form: levels equal under every assignment of their
+    /// parameters normalise to the same level.
+    pub fn normalize(&self) -> Level {
+        let mut components = self.components();
+        components.sort();
+        let mut kept: Vec<(Option<Level>, u64)> = Vec::new();
+        for (base, offset) in components {
+            match kept.last_mut() {
+                Some((last, last_offset)) if *last == base => *last_offset = offset,
+                _ => kept.push((base, offset)),
+            }
+        }
+        let max_offset = kept.iter().filter(|(base, _)| base.is_some()).map(|(_, offset)| *offset).max();
+        if let Some((None, constant)) = kept.first()
+            && max_offset.is_some_and(|max| max >= *constant)
+        {
+            kept.remove(0);
+        }
+
+        kept.into_iter()
+            .map(|(base, offset)| (0..offset).fold(base.unwrap_or(Level::Zero), |level, _| level.succ()))
+            .reduce(|acc, level| Level::Max(Box::new(acc), Box::new(level)))
+            .unwrap_or(Level::Zero)
+    }
+
+    pub fn is_equivalent(&self, other: &Level) -> bool {
+        self == other || self.normalize() == other.normalize()
+    }
+
+    /// Whether `self >= other` under every assignment of parameters.
+    pub fn geq(&self, other: &Level) -> bool {
+        let ours = self.normalize().components();
+        other.normalize().components().iter().all(|(base, offset)| {
+            ours.iter()
+                .any(|(our_base, our_offset)| our_offset >= offset && (our_base == base || base.is_none()))
+        })
+    }
+}
@@ -142,6 +142,22 @@ impl fmt::Display for TokenKind {
             TokenKind::FatArrow => write!(f, "`=>`"),
             TokenKind::Dot => write!(f, "`.`"),
             TokenKind::Underscore => write!(f, "`_`"),
+            TokenKind::Inductive => write!(f, "`inductive`"),
+            TokenKind::Where => write!(f, "`where`"),
+            TokenKind::Pipe => write!(f, "`|`"),
+            TokenKind::LAngle => write!(f, "`⟨`"),
+            TokenKind::RAngle => write!(f, "`⟩`"),
+            TokenKind::Match => write!(f, "`match`"),
+            TokenKind::With => write!(f, "`with`"),
+            TokenKind::Mutual => write!(f, "`mutual`"),
+            TokenKind::End => write!(f, "`end`"),
+            TokenKind::Plus => write!(f, "`+`"),
+            TokenKind::Question => write!(f, "`?`"),
+            TokenKind::Class => write!(f, "`class`"),
+            TokenKind::Instance => write!(f, "`instance`"),
+            TokenKind::Import => write!(f, "`import`"),
+            TokenKind::Namespace => write!(f, "`namespace`"),
+            TokenKind::Open => write!(f, "`open`"),
         }
     }
 }
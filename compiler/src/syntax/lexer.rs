@@ -12,12 +12,13 @@ fn decode_utf8_char(bytes: &[u8]) -> Option<char> {
     s.chars().next()
 }
 
+// `λ` is alphabetic but reserved for lambdas.
 fn is_ident_start(c: char) -> bool {
-    c.is_alphabetic() || c == '_'
+    (c.is_alphabetic() && c != 'λ') || c == '_'
 }
 
 fn is_ident_continue(c: char) -> bool {
-    c.is_alphabetic() || c.is_ascii_digit() || c == '_'
+    (c.is_alphabetic() && c != 'λ') || c.is_ascii_digit() || c == '_'
 }
 
 pub struct Lexer<'a> {
@@ -111,6 +112,18 @@ impl<'a> Iterator for Lexer<'a> {
                     b"let" => TokenKind::Let,
                     b"in" => TokenKind::In,
                     b"eval" => TokenKind::Eval,
+                    b"inductive" => TokenKind::Inductive,
+                    b"where" => TokenKind::Where,
+                    b"match" => TokenKind::Match,
+                    b"with" => TokenKind::With,
+                    b"mutual" => TokenKind::Mutual,
+                    b"end" => TokenKind::End,
+                    b"class" => TokenKind::Class,
+                    b"instance" => TokenKind::Instance,
+                    b"import" => TokenKind::Import,
+                    b"namespace" => TokenKind::Namespace,
+                    b"open" => TokenKind::Open,
+                    b"_" => TokenKind::Underscore,
                     _ if is_upper => TokenKind::UpperIdentifier,
                     _ => TokenKind::LowerIdentifier,
                 };
@@ -207,6 +220,30 @@ impl<'a> Iterator for Lexer<'a> {
                     span: self.cursor.span_from(start),
                 }))
             }
+            '|' => {
+                self.cursor.advance(1);
+                Some(Ok(Token {
+                    kind: TokenKind::Pipe,
+                    lexeme: &source[start..self.cursor.byte_offset],
+                    span: self.cursor.span_from(start),
+                }))
+            }
+            '+' => {
+                self.cursor.advance(1);
+                Some(Ok(Token {
+                    kind: TokenKind::Plus,
+                    lexeme: &source[start..self.cursor.byte_offset],
+                    span: self.cursor.span_from(start),
+                }))
+            }
+            '?' => {
+                self.cursor.advance(1);
+                Some(Ok(Token {
+                    kind: TokenKind::Question,
+                    lexeme: &source[start..self.cursor.byte_offset],
+                    span: self.cursor.span_from(start),
+                }))
+            }
             ',' => {
                 self.cursor.advance(1);
                 Some(Ok(Token {
@@ -321,6 +358,18 @@ impl<'a> Iterator for Lexer<'a> {
                     }))
                 }
             }
+            '⟨' | '⟩' => {
+                self.cursor.advance_char(current);
+                Some(Ok(Token {
+                    kind: if current == '⟨' {
+                        TokenKind::LAngle
+                    } else {
+                        TokenKind::RAngle
+                    },
+                    lexeme: &source[start..self.cursor.byte_offset],
+                    span: self.cursor.span_from(start),
+                }))
+            }
             '×' => {
                 self.cursor.advance_char('×');
                 Some(Ok(Token {
@@ -30,6 +30,11 @@ impl Span {
             end: offset,
         }
     }
+
+    /// The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.file, self.start, other.end)
+    }
 }
 
 impl SourceFile<'_> {
@@ -9,7 +9,7 @@ use crate::{spine::Literal, syntax::{
     Span,
     error::{ParseError, ParseErrorKind},
     token::{Token, TokenKind},
-    tree::{SyntaxBinder, SyntaxExpr as Expr},
+    tree::{SyntaxBinder, SyntaxConstructor, SyntaxExpr as Expr, SyntaxExprKind as ExprKind, SyntaxLevel, SyntaxMatchArm, SyntaxPattern, SyntaxPatternKind},
 }};
 
 impl chumsky::span::Span for Span {
@@ -65,23 +65,48 @@ pub fn parse<'a>(
     (output, errors)
 }
 
+/// Parses a lone expression, such as one typed at the REPL.
+pub fn parse_expr<'a>(
+    tokens: &'a [(Token<'a>, Span)],
+    eoi_span: Span,
+) -> (Option<Expr>, Vec<ParseError>) {
+    let input = tokens.split_token_span(eoi_span);
+
+    let mut expr = Recursive::declare();
+    expr.define(expr_impl(expr.clone()));
+
+    let (output, errors) = expr.parse(input).into_output_errors();
+    let errors = errors.into_iter().map(rich_to_parse_error).collect();
+
+    (output, errors)
+}
+
 fn program<'a>() -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> {
     let mut expr = Recursive::declare();
 
-    let def = choice((
-        def_parser(expr.clone()),
-        eval_parser(expr.clone()),
-    ));
+    let def = recursive(|def| {
+        choice((
+            def_parser(expr.clone()),
+            eval_parser(expr.clone()),
+            inductive_parser(expr.clone()),
+            struct_parser(expr.clone()),
+            instance_parser(expr.clone()),
+            mutual_parser(expr.clone()),
+            import_parser(),
+            open_parser(),
+            namespace_parser(def),
+        ))
+    });
 
     expr.define(expr_impl(expr.clone()));
 
     let defs = def.repeated().collect();
-    defs.map(Expr::Root)
+    defs.map_with(|defs, e| Expr::new(ExprKind::Root(defs), e.span()))
 }
 
 fn def_parser<'a>(
     expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone,
-) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> {
+) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
     just_token(TokenKind::Def)
         .ignore_then(just_token(TokenKind::LowerIdentifier))
         .then(
@@ -93,21 +118,212 @@ fn def_parser<'a>(
                 .then_ignore(just_token(TokenKind::Equal))
                 .then(expr),
         )
-        .map(|(name, ((binders, ret_type), body))| Expr::Def {
-            name: lexeme_to_string(name.lexeme),
-            binders,
-            return_type: Box::new(ret_type),
-            body: Box::new(body),
+        .map_with(|(name, ((binders, ret_type), body)), e| {
+            let kind = ExprKind::Def {
+                name: lexeme_to_string(name.lexeme),
+                binders,
+                return_type: Box::new(ret_type),
+                body: Box::new(body),
+            };
+            Expr::new(kind, e.span())
+        })
+}
+
+fn mutual_parser<'a>(
+    expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone,
+) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
+    just_token(TokenKind::Mutual)
+        .ignore_then(def_parser(expr).repeated().at_least(1).collect())
+        .then_ignore(just_token(TokenKind::End))
+        .map_with(|defs, e| Expr::new(ExprKind::Mutual(defs), e.span()))
+}
+
+/// A dotted path such as `Data.List`, whose components may be upper or
+/// lower case.
+fn path<'a>() -> impl Parser<'a, ParserInput<'a>, String, ParserExtra<'a>> + Clone {
+    let component = choice((
+        just_token(TokenKind::UpperIdentifier),
+        just_token(TokenKind::LowerIdentifier),
+    ))
+    .map(|t| lexeme_to_string(t.lexeme));
+    component
+        .clone()
+        .foldl(just_token(TokenKind::Dot).ignore_then(component).repeated(), |mut path, next| {
+            path.push('.');
+            path.push_str(&next);
+            path
         })
 }
 
+fn import_parser<'a>() -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
+    just_token(TokenKind::Import)
+        .ignore_then(path())
+        .map_with(|path, e| Expr::new(ExprKind::Import(path), e.span()))
+}
+
+fn open_parser<'a>() -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
+    just_token(TokenKind::Open)
+        .ignore_then(path().repeated().at_least(1).collect())
+        .map_with(|paths, e| Expr::new(ExprKind::Open(paths), e.span()))
+}
+
+fn namespace_parser<'a>(
+    command: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone,
+) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
+    just_token(TokenKind::Namespace)
+        .ignore_then(path())
+        .then(command.repeated().collect())
+        .then_ignore(just_token(TokenKind::End))
+        .map_with(|(name, commands), e| Expr::new(ExprKind::Namespace { name, commands }, e.span()))
+}
+
 fn eval_parser<'a>(
     expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone,
-) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> {
+) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
     just_token(TokenKind::Eval)
         .ignore_then(expr)
         .then_ignore(just_token(TokenKind::Semicolon))
-        .map(|e| Expr::Eval(Box::new(e)))
+        .map_with(|term, e| Expr::new(ExprKind::Eval(Box::new(term)), e.span()))
+}
+
+fn inductive_parser<'a>(
+    expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone,
+) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
+    let constructor = just_token(TokenKind::Pipe)
+        .ignore_then(just_token(TokenKind::LowerIdentifier))
+        .then(binder(expr.clone()).repeated().collect())
+        .then(
+            just_token(TokenKind::Colon)
+                .ignore_then(expr.clone())
+                .or_not(),
+        )
+        .map(|((name, binders), type_)| SyntaxConstructor {
+            name: lexeme_to_string(name.lexeme),
+            binders,
+            type_: type_.map(Box::new),
+        });
+
+    just_token(TokenKind::Inductive)
+        .ignore_then(just_token(TokenKind::UpperIdentifier))
+        .then(binder(expr.clone()).repeated().collect())
+        .then(just_token(TokenKind::Colon).ignore_then(expr).or_not())
+        .then_ignore(just_token(TokenKind::Where))
+        .then(constructor.repeated().collect())
+        .map_with(|(((name, binders), type_), constructors), e| {
+            let kind = ExprKind::Inductive {
+                name: lexeme_to_string(name.lexeme),
+                binders,
+                type_: type_.map(Box::new),
+                constructors,
+            };
+            Expr::new(kind, e.span())
+        })
+}
+
+fn struct_parser<'a>(
+    expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone,
+) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
+    // Nothing separates one field from the next, so a field's type is the
+    // tokens up to the next `name :` outside brackets (other than a `let`'s),
+    // parsed on their own.
+    let field_start = just_token(TokenKind::LowerIdentifier).then_ignore(just_token(TokenKind::Colon));
+    let type_tokens = choice((
+        just_token(TokenKind::Let).then(field_start.clone()).ignored(),
+        token_tree().and_is(field_start.clone().not()),
+    ))
+    .repeated()
+    .at_least(1)
+    .to_slice()
+    .map_with(|tokens: &'a [(Token<'a>, Span)], e| {
+        let span: Span = e.span();
+        let eoi_span = Span::empty(span.file, span.end);
+        tokens.split_token_span(eoi_span)
+    });
+
+    let field = field_start
+        .then(expr.clone().nested_in(type_tokens))
+        .map(|(name, ty)| SyntaxBinder::Explicit(lexeme_to_string(name.lexeme), Box::new(ty)));
+
+    just_token(TokenKind::Struct)
+        .or(just_token(TokenKind::Class))
+        .then(just_token(TokenKind::UpperIdentifier))
+        .then(binder(expr).repeated().collect())
+        .then_ignore(just_token(TokenKind::Where))
+        .then(field.repeated().collect())
+        .map_with(|(((keyword, name), binders), fields), e| {
+            let name = lexeme_to_string(name.lexeme);
+            let kind = match keyword.kind {
+                TokenKind::Class => ExprKind::Class { name, binders, fields },
+                _ => ExprKind::Struct { name, binders, fields },
+            };
+            Expr::new(kind, e.span())
+        })
+}
+
+/// A token, or a bracketed group of them, that can't start a declaration.
+fn token_tree<'a>() -> impl Parser<'a, ParserInput<'a>, (), ParserExtra<'a>> + Clone {
+    recursive(|tree| {
+        let group = |open, close| {
+            tree.clone()
+                .repeated()
+                .delimited_by(just_token(open), just_token(close))
+        };
+        let token = any()
+            .filter(|t: &Token| {
+                !matches!(
+                    t.kind,
+                    TokenKind::LParen
+                        | TokenKind::RParen
+                        | TokenKind::LBrace
+                        | TokenKind::RBrace
+                        | TokenKind::LBracket
+                        | TokenKind::RBracket
+                        | TokenKind::LAngle
+                        | TokenKind::RAngle
+                        | TokenKind::Def
+                        | TokenKind::Eval
+                        | TokenKind::Inductive
+                        | TokenKind::Struct
+                        | TokenKind::Class
+                        | TokenKind::Instance
+                        | TokenKind::Mutual
+                        | TokenKind::End
+                        | TokenKind::Import
+                        | TokenKind::Open
+                        | TokenKind::Namespace
+                )
+            })
+            .ignored();
+
+        choice((
+            group(TokenKind::LParen, TokenKind::RParen),
+            group(TokenKind::LBrace, TokenKind::RBrace),
+            group(TokenKind::LBracket, TokenKind::RBracket),
+            group(TokenKind::LAngle, TokenKind::RAngle),
+            token,
+        ))
+    })
+}
+
+fn instance_parser<'a>(
+    expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone,
+) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
+    just_token(TokenKind::Instance)
+        .ignore_then(just_token(TokenKind::LowerIdentifier))
+        .then(binder(expr.clone()).repeated().collect())
+        .then_ignore(just_token(TokenKind::Colon))
+        .then(expr.clone())
+        .then_ignore(just_token(TokenKind::Equal))
+        .then(expr)
+        .map_with(|(((name, binders), type_), body), e| {
+            let kind = ExprKind::Instance {
+                name: lexeme_to_string(name.lexeme),
+                binders,
+                type_: Box::new(type_),
+                body: Box::new(body),
+            };
+            Expr::new(kind, e.span())
+        })
 }
 
 fn binder<'a>(
@@ -142,18 +358,25 @@ fn expr_impl<'a>(
 ) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
     let atom = expr_atom(expr.clone());
 
-    let app = atom.clone().foldl(atom.clone().repeated(), |lhs, rhs| {
-        Expr::App(Box::new(lhs), Box::new(rhs))
-    });
-
-    let proj = app.clone().foldl(
+    let proj = atom.foldl(
         just_token(TokenKind::Dot)
-            .ignore_then(just_token(TokenKind::LowerIdentifier))
+            .ignore_then(choice((
+                just_token(TokenKind::LowerIdentifier),
+                just_token(TokenKind::UpperIdentifier),
+            )))
             .repeated(),
-        |lhs, field| Expr::Proj(Box::new(lhs), lexeme_to_string(field.lexeme)),
+        |lhs, field| {
+            let span = lhs.span.to(field.span);
+            Expr::new(ExprKind::Proj(Box::new(lhs), lexeme_to_string(field.lexeme)), span)
+        },
     );
 
-    let arrow_or_product = proj
+    let app = proj.clone().foldl(proj.clone().repeated(), |lhs, rhs| {
+        let span = lhs.span.to(rhs.span);
+        Expr::new(ExprKind::App(Box::new(lhs), Box::new(rhs)), span)
+    });
+
+    let arrow_or_product = app
         .clone()
         .then(
             choice((
@@ -165,30 +388,47 @@ fn expr_impl<'a>(
         )
         .map(|(lhs, rest)| match rest {
             None => lhs,
-            Some((true, rhs)) => Expr::Arrow(Box::new(lhs), Box::new(rhs)),
-            Some((false, rhs)) => Expr::Sigma(
-                SyntaxBinder::Explicit(String::from("_"), Box::new(lhs)),
-                Box::new(rhs),
-            ),
+            Some((true, rhs)) => {
+                let span = lhs.span.to(rhs.span);
+                Expr::new(ExprKind::Arrow(Box::new(lhs), Box::new(rhs)), span)
+            }
+            Some((false, rhs)) => {
+                let span = lhs.span.to(rhs.span);
+                let binder = SyntaxBinder::Explicit(String::from("_"), Box::new(lhs));
+                Expr::new(ExprKind::Sigma(binder, Box::new(rhs)), span)
+            }
         });
 
     let pi = binder(expr.clone())
         .then_ignore(just_token(TokenKind::Arrow))
         .then(expr.clone())
-        .map(|(binder, body)| Expr::Pi(binder, Box::new(body)));
+        .map_with(|(binder, body), e| Expr::new(ExprKind::Pi(binder, Box::new(body)), e.span()));
 
     let sigma = binder(expr.clone())
         .then_ignore(just_token(TokenKind::Product))
         .then(expr.clone())
-        .map(|(binder, body)| Expr::Sigma(binder, Box::new(body)));
+        .map_with(|(binder, body), e| Expr::new(ExprKind::Sigma(binder, Box::new(body)), e.span()));
+
+    let untyped_binder = just_token(TokenKind::LowerIdentifier).map(|name| {
+        let hole = Expr::new(ExprKind::Hole, name.span);
+        SyntaxBinder::Explicit(lexeme_to_string(name.lexeme), Box::new(hole))
+    });
 
     let lambda = just_token(TokenKind::Lambda)
-        .ignore_then(binder(expr.clone()).repeated().at_least(1).collect())
+        .ignore_then(
+            choice((binder(expr.clone()), untyped_binder))
+                .repeated()
+                .at_least(1)
+                .collect(),
+        )
         .then_ignore(just_token(TokenKind::FatArrow))
         .then(expr.clone())
-        .map(|(binders, body)| Expr::Lambda {
-            binders,
-            body: Box::new(body),
+        .map_with(|(binders, body), e| {
+            let kind = ExprKind::Lambda {
+                binders,
+                body: Box::new(body),
+            };
+            Expr::new(kind, e.span())
         });
 
     let let_typed = just_token(TokenKind::Let)
@@ -199,11 +439,14 @@ fn expr_impl<'a>(
         .then(expr.clone())
         .then_ignore(just_token(TokenKind::In))
         .then(expr.clone())
-        .map(|(((name, ty), value), body)| Expr::Let {
-            name: lexeme_to_string(name.lexeme),
-            type_ann: Some(Box::new(ty)),
-            value: Box::new(value),
-            body: Box::new(body),
+        .map_with(|(((name, ty), value), body), e| {
+            let kind = ExprKind::Let {
+                name: lexeme_to_string(name.lexeme),
+                type_ann: Some(Box::new(ty)),
+                value: Box::new(value),
+                body: Box::new(body),
+            };
+            Expr::new(kind, e.span())
         });
 
     let let_untyped = just_token(TokenKind::Let)
@@ -211,38 +454,106 @@ fn expr_impl<'a>(
         .then_ignore(just_token(TokenKind::Equal))
         .then(expr.clone())
         .then_ignore(just_token(TokenKind::In))
-        .then(expr)
-        .map(|((name, value), body)| Expr::Let {
-            name: lexeme_to_string(name.lexeme),
-            type_ann: None,
-            value: Box::new(value),
-            body: Box::new(body),
+        .then(expr.clone())
+        .map_with(|((name, value), body), e| {
+            let kind = ExprKind::Let {
+                name: lexeme_to_string(name.lexeme),
+                type_ann: None,
+                value: Box::new(value),
+                body: Box::new(body),
+            };
+            Expr::new(kind, e.span())
+        });
+
+    let arm = just_token(TokenKind::Pipe)
+        .ignore_then(pattern())
+        .then_ignore(just_token(TokenKind::FatArrow))
+        .then(expr.clone())
+        .map_with(|(pattern, body), e| SyntaxMatchArm {
+            pattern,
+            body,
+            span: e.span(),
         });
 
-    choice((lambda, let_typed, let_untyped, pi, sigma, arrow_or_product))
+    let match_ = just_token(TokenKind::Match)
+        .ignore_then(expr)
+        .then_ignore(just_token(TokenKind::With))
+        .then(arm.repeated().collect())
+        .map_with(|(scrutinee, arms), e| {
+            let kind = ExprKind::Match {
+                scrutinee: Box::new(scrutinee),
+                arms,
+            };
+            Expr::new(kind, e.span())
+        });
+
+    choice((lambda, let_typed, let_untyped, match_, pi, sigma, arrow_or_product))
+}
+
+fn pattern<'a>() -> impl Parser<'a, ParserInput<'a>, SyntaxPattern, ParserExtra<'a>> + Clone {
+    recursive(|pattern| {
+        let path = just_token(TokenKind::UpperIdentifier)
+            .then(
+                just_token(TokenKind::Dot)
+                    .ignore_then(just_token(TokenKind::LowerIdentifier))
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .map(|(head, fields)| {
+                let mut path = lexeme_to_string(head.lexeme);
+                for field in fields {
+                    path.push('.');
+                    path.push_str(&lexeme_to_string(field.lexeme));
+                }
+                path
+            });
+        let var = just_token(TokenKind::LowerIdentifier).map(|t| lexeme_to_string(t.lexeme));
+
+        let atom = choice((
+            just_token(TokenKind::Underscore)
+                .map_with(|_, e| SyntaxPattern::new(SyntaxPatternKind::Wildcard, e.span())),
+            var.clone()
+                .map_with(|name, e| SyntaxPattern::new(SyntaxPatternKind::Var(name), e.span())),
+            path.clone()
+                .map_with(|path, e| SyntaxPattern::new(SyntaxPatternKind::Constructor(path, Vec::new()), e.span())),
+            pattern
+                .delimited_by(just_token(TokenKind::LParen), just_token(TokenKind::RParen)),
+        ));
+
+        let application = choice((path, var))
+            .then(atom.clone().repeated().at_least(1).collect())
+            .map_with(|(head, args), e| SyntaxPattern::new(SyntaxPatternKind::Constructor(head, args), e.span()));
+
+        choice((application, atom))
+    })
 }
 
 fn expr_atom<'a>(
     expr: impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone,
 ) -> impl Parser<'a, ParserInput<'a>, Expr, ParserExtra<'a>> + Clone {
-    let var = just_token(TokenKind::LowerIdentifier).map(|t| Expr::Var(lexeme_to_string(t.lexeme)));
+    let var = just_token(TokenKind::LowerIdentifier)
+        .map(|t| Expr::new(ExprKind::Var(lexeme_to_string(t.lexeme)), t.span));
 
     let constructor = just_token(TokenKind::UpperIdentifier)
-        .map(|t| Expr::Constructor(lexeme_to_string(t.lexeme)));
+        .map(|t| Expr::new(ExprKind::Constructor(lexeme_to_string(t.lexeme)), t.span));
 
     let number = just_token(TokenKind::Number).map(|t| {
         let s = lexeme_to_string(t.lexeme);
         let n = s.parse::<u64>().unwrap_or(0);
-        Expr::Lit(Literal::Nat(n))
+        Expr::new(ExprKind::Lit(Literal::Nat(n)), t.span)
     });
 
     let string = just_token(TokenKind::String).map(|t| {
         let s = lexeme_to_string(t.lexeme);
         let inner = if s.len() >= 2 { &s[1..s.len() - 1] } else { &s };
-        Expr::Lit(Literal::Str(String::from(inner)))
+        Expr::new(ExprKind::Lit(Literal::Str(String::from(inner))), t.span)
     });
 
-    let hole = just_token(TokenKind::Underscore).map(|_| Expr::Hole);
+    let hole = just_token(TokenKind::Underscore).map(|t| Expr::new(ExprKind::Hole, t.span));
+
+    let named_hole = just_token(TokenKind::Question)
+        .ignore_then(just_token(TokenKind::LowerIdentifier))
+        .map_with(|t, e| Expr::new(ExprKind::NamedHole(lexeme_to_string(t.lexeme)), e.span()));
 
     let tuple_or_grouped = just_token(TokenKind::LParen)
         .ignore_then(
@@ -251,20 +562,63 @@ fn expr_atom<'a>(
                 .collect::<Vec<_>>(),
         )
         .then_ignore(just_token(TokenKind::RParen))
-        .map(|items| {
+        .map_with(|items: Vec<Expr>, e| {
             if items.len() == 1 {
                 items.into_iter().next().unwrap()
             } else {
-                Expr::Tuple(items)
+                Expr::new(ExprKind::Tuple(items), e.span())
             }
         });
     
     let array = just_token(TokenKind::LBracket)
         .ignore_then(expr.clone().separated_by(just_token(TokenKind::Comma)).collect())
         .then_ignore(just_token(TokenKind::RBracket))
-        .map(Expr::Array);
+        .map_with(|items, e| Expr::new(ExprKind::Array(items), e.span()));
+
+    let anonymous_constructor = just_token(TokenKind::LAngle)
+        .ignore_then(expr.clone().separated_by(just_token(TokenKind::Comma)).collect())
+        .then_ignore(just_token(TokenKind::RAngle))
+        .map_with(|args, e| Expr::new(ExprKind::AnonymousConstructor(args), e.span()));
+
+    let sort = just_token(TokenKind::UpperIdentifier)
+        .filter(|t: &Token| t.lexeme == b"Type")
+        .ignore_then(level_atom().or_not())
+        .map_with(|level, e| Expr::new(ExprKind::Sort(level), e.span()));
+
+    choice((var, sort, constructor, number, string, hole, named_hole, tuple_or_grouped, array, anonymous_constructor))
+}
 
-    choice((var, constructor, number, string, hole, tuple_or_grouped, array))
+/// `0`, `u`, or a parenthesised `max l l`, `imax l l` or `l + n`.
+fn level_atom<'a>() -> impl Parser<'a, ParserInput<'a>, SyntaxLevel, ParserExtra<'a>> + Clone {
+    recursive(|atom| {
+        let number = just_token(TokenKind::Number)
+            .map(|t| lexeme_to_string(t.lexeme).parse::<u64>().unwrap_or(0));
+        let keyword = |word: &'static [u8]| {
+            just_token(TokenKind::LowerIdentifier).filter(move |t: &Token| t.lexeme == word)
+        };
+
+        let max = keyword(b"max")
+            .ignore_then(atom.clone())
+            .then(atom.clone())
+            .map(|(a, b)| SyntaxLevel::Max(Box::new(a), Box::new(b)));
+        let imax = keyword(b"imax")
+            .ignore_then(atom.clone())
+            .then(atom.clone())
+            .map(|(a, b)| SyntaxLevel::IMax(Box::new(a), Box::new(b)));
+        let add = atom
+            .clone()
+            .foldl(
+                just_token(TokenKind::Plus).ignore_then(number.clone()).repeated(),
+                |level, n| SyntaxLevel::Add(Box::new(level), n),
+            );
+
+        choice((
+            number.map(SyntaxLevel::Nat),
+            just_token(TokenKind::LowerIdentifier).map(|t| SyntaxLevel::Param(lexeme_to_string(t.lexeme))),
+            choice((max, imax, add))
+                .delimited_by(just_token(TokenKind::LParen), just_token(TokenKind::RParen)),
+        ))
+    })
 }
 
 fn rich_to_parse_error(err: Rich<'_, Token<'_>, Span>) -> ParseError {
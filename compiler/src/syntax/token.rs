@@ -35,6 +35,22 @@ pub enum TokenKind {
     FatArrow,
     Dot,
     Underscore,
+    Inductive,
+    Where,
+    Pipe,
+    LAngle,
+    RAngle,
+    Match,
+    With,
+    Mutual,
+    End,
+    Plus,
+    Question,
+    Class,
+    Instance,
+    Import,
+    Namespace,
+    Open,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
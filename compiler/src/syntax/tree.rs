@@ -4,9 +4,22 @@ use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 use crate::spine::Literal;
+use crate::syntax::Span;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum SyntaxExpr {
+pub struct SyntaxExpr {
+    pub kind: SyntaxExprKind,
+    pub span: Span,
+}
+
+impl SyntaxExpr {
+    pub fn new(kind: SyntaxExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxExprKind {
     Root(Vec<SyntaxExpr>),
     Def {
         name: String,
@@ -31,11 +44,63 @@ pub enum SyntaxExpr {
     Tuple(Vec<SyntaxExpr>),
     Proj(Box<SyntaxExpr>, String),
     Hole,
+    /// `?name`, a hole reported with its goal rather than solved.
+    NamedHole(String),
     Arrow(Box<SyntaxExpr>, Box<SyntaxExpr>),
     Array(Vec<SyntaxExpr>),
     Pi(SyntaxBinder, Box<SyntaxExpr>),
     Sigma(SyntaxBinder, Box<SyntaxExpr>),
-    Eval(Box<SyntaxExpr>)
+    Eval(Box<SyntaxExpr>),
+    Mutual(Vec<SyntaxExpr>),
+    Inductive {
+        name: String,
+        binders: Vec<SyntaxBinder>,
+        type_: Option<Box<SyntaxExpr>>,
+        constructors: Vec<SyntaxConstructor>,
+    },
+    Struct {
+        name: String,
+        binders: Vec<SyntaxBinder>,
+        fields: Vec<SyntaxBinder>,
+    },
+    /// A structure whose values are found by instance resolution.
+    Class {
+        name: String,
+        binders: Vec<SyntaxBinder>,
+        fields: Vec<SyntaxBinder>,
+    },
+    Instance {
+        name: String,
+        binders: Vec<SyntaxBinder>,
+        type_: Box<SyntaxExpr>,
+        body: Box<SyntaxExpr>,
+    },
+    AnonymousConstructor(Vec<SyntaxExpr>),
+    Match {
+        scrutinee: Box<SyntaxExpr>,
+        arms: Vec<SyntaxMatchArm>,
+    },
+    /// `Type` or `Type l`.
+    Sort(Option<SyntaxLevel>),
+    /// `import A.B`, bringing the declarations of module `A.B` into scope.
+    Import(String),
+    /// `namespace A.B ... end`, prefixing the names declared inside.
+    Namespace {
+        name: String,
+        commands: Vec<SyntaxExpr>,
+    },
+    /// `open A B`, letting the names in namespaces `A` and `B` be used
+    /// unqualified until the end of the enclosing namespace.
+    Open(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxLevel {
+    Nat(u64),
+    Param(String),
+    Add(Box<SyntaxLevel>, u64),
+    Max(Box<SyntaxLevel>, Box<SyntaxLevel>),
+    IMax(Box<SyntaxLevel>, Box<SyntaxLevel>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -44,3 +109,38 @@ pub enum SyntaxBinder {
     Implicit(String, Box<SyntaxExpr>),
     Instance(String, Box<SyntaxExpr>),
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxMatchArm {
+    pub pattern: SyntaxPattern,
+    pub body: SyntaxExpr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxPattern {
+    pub kind: SyntaxPatternKind,
+    pub span: Span,
+}
+
+impl SyntaxPattern {
+    pub fn new(kind: SyntaxPatternKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+/// A lone lowercase name is a variable unless it names a constructor of the
+/// matched type; `Constructor` heads may be written `List.cons` or `cons`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxPatternKind {
+    Wildcard,
+    Var(String),
+    Constructor(String, Vec<SyntaxPattern>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxConstructor {
+    pub name: String,
+    pub binders: Vec<SyntaxBinder>,
+    pub type_: Option<Box<SyntaxExpr>>,
+}
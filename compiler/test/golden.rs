@@ -0,0 +1,233 @@
+//! Runs every `test/golden/*.src` program through lexing, parsing and
+//! elaboration and compares the result against the `.snap` file next to it.
+//! Run with `BLESS=1` to rewrite the snapshots. Modules the fixtures import
+//! are found below `test/golden/modules`. Each environment is also written
+//! to an object file and read back, which must give the same environment,
+//! and each fixture is compiled a second time reusing the object files of
+//! the first, which must give the same result. A fixture with an `.edit`
+//! file next to it is then compiled from the edited source, still reusing
+//! the object files, which must give what compiling the edit afresh gives;
+//! the snapshot shows that too. Fixtures that define `main`
+//! are also run, reading their input from the `.in` file next to them, and
+//! show their lowering to A-normal form. Every fixture that elaborates must
+//! lower to a program the validator accepts.
+
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use compiler::{
+    anf::{FunctionName, Program, lower::lower_environment, validate::validate},
+    driver::{CompiledModule, ModuleLoader, compile},
+    interp::{Console, run_main},
+    module::{
+        name::{Name, QualifiedName},
+        object::{Object, read_object, write_object},
+    },
+};
+use miette::Diagnostic;
+
+struct Modules {
+    dir: PathBuf,
+    objects: HashMap<String, Vec<u8>>,
+}
+
+impl ModuleLoader for Modules {
+    fn load(&mut self, module: &str) -> Option<String> {
+        let path = self.dir.join(module.replace('.', "/")).with_extension("src");
+        fs::read_to_string(path).ok()
+    }
+
+    fn load_object(&mut self, module: &str) -> Option<Vec<u8>> {
+        self.objects.get(module).cloned()
+    }
+
+    fn store_object(&mut self, module: &str, object: &[u8]) {
+        self.objects.insert(module.to_string(), object.to_vec());
+    }
+}
+
+/// Collects what a program writes and feeds it the lines of `input`.
+struct Script<'a> {
+    input: std::str::Lines<'a>,
+    output: Vec<u8>,
+}
+
+impl Console for Script<'_> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.output.extend_from_slice(bytes);
+    }
+
+    fn read_line(&mut self) -> Option<Vec<u8>> {
+        self.input.next().map(|line| line.as_bytes().to_vec())
+    }
+}
+
+fn code(diagnostic: &dyn Diagnostic) -> String {
+    diagnostic
+        .code()
+        .map(|code| code.to_string())
+        .unwrap_or_default()
+}
+
+fn run(name: &str, source: &str, edited: Option<&str>, input: &str, modules: &Path) -> String {
+    let loader = || Modules {
+        dir: modules.to_path_buf(),
+        objects: HashMap::new(),
+    };
+    let mut cached = loader();
+    let mut out = output(name, input, compile(&mut cached, name.to_string(), source.to_string()));
+    let again = output(name, input, compile(&mut cached, name.to_string(), source.to_string()));
+    assert_eq!(again, out, "{name}: recompiling with object files gives a different result");
+
+    if let Some(edited) = edited {
+        let reused = output(name, input, compile(&mut cached, name.to_string(), edited.to_string()));
+        let afresh = output(name, input, compile(&mut loader(), name.to_string(), edited.to_string()));
+        assert_eq!(reused, afresh, "{name}: recompiling the edit with object files gives a different result");
+        writeln!(out, "-- after the edit").unwrap();
+        out.push_str(&reused);
+    }
+    out
+}
+
+fn output(name: &str, input: &str, mut compiled: Vec<CompiledModule>) -> String {
+    let main = compiled.pop().unwrap();
+
+    let mut out = String::new();
+    for import in &compiled {
+        write_errors(&mut out, import);
+    }
+    let CompiledModule { parsed, state, .. } = main;
+    writeln!(out, "-- lex errors").unwrap();
+    for err in &parsed.lex_errors {
+        writeln!(out, "[{}] {}..{}: {}", code(err), err.span.start, err.span.end, err).unwrap();
+    }
+    writeln!(out, "-- parse errors").unwrap();
+    for err in &parsed.parse_errors {
+        writeln!(out, "[{}] {}..{}: {}", code(err), err.span.start, err.span.end, err).unwrap();
+    }
+
+    if parsed.ast.is_none() {
+        return out;
+    }
+
+    writeln!(out, "-- evaluations").unwrap();
+    for term in &state.evaluations {
+        writeln!(out, "{}", term).unwrap();
+    }
+    writeln!(out, "-- elaboration errors").unwrap();
+    for err in &state.errors {
+        writeln!(out, "[{}] {}..{}: {}", code(err), err.span.start, err.span.end, err).unwrap();
+    }
+    writeln!(out, "-- environment").unwrap();
+    write!(out, "{}", state.env).unwrap();
+
+    let program = match state.errors.is_empty() {
+        true => {
+            let program = lower_environment(&state.env).unwrap_or_else(|err| panic!("{name}: {err}"));
+            let errors = validate(&program);
+            assert!(errors.is_empty(), "{name}: invalid lowering: {errors:?}\n{program}");
+            Some(program)
+        }
+        false => None,
+    };
+
+    if let Some(program) = program
+        && state.env.lookup_name(&Name::parse("main")).is_some()
+    {
+        // The primitives are the same in every fixture.
+        let functions = program.functions.into_iter().filter(|(name, _)| {
+            !matches!(
+                name,
+                FunctionName::Global(QualifiedName::Intrinsic(_)) | FunctionName::Lifted(QualifiedName::Intrinsic(_), _)
+            )
+        });
+        writeln!(out, "-- anf").unwrap();
+        write!(out, "{}", Program { functions: functions.collect() }).unwrap();
+
+        let mut script = Script {
+            input: input.lines(),
+            output: Vec::new(),
+        };
+        let result = run_main(&state.env, &mut script);
+        writeln!(out, "-- output").unwrap();
+        out.push_str(&String::from_utf8_lossy(&script.output));
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        if let Err(err) = result {
+            writeln!(out, "-- runtime error\n{}", err).unwrap();
+        }
+    }
+
+    let object = write_object(&Object {
+        source_hash: 0,
+        imports: Vec::new(),
+        env: state.env.clone(),
+        commands: Vec::new(),
+    });
+    let read = read_object(&object).unwrap_or_else(|err| panic!("{name}: {err}"));
+    assert_eq!(read.env.to_string(), state.env.to_string(), "{name}: object file round trip");
+    out
+}
+
+/// The errors in an imported module, if it has any.
+fn write_errors(out: &mut String, module: &CompiledModule) {
+    let parsed = &module.parsed;
+    let errors: Vec<(&dyn Diagnostic, usize, usize)> = parsed
+        .lex_errors
+        .iter()
+        .map(|err| (err as &dyn Diagnostic, err.span.start, err.span.end))
+        .chain(parsed.parse_errors.iter().map(|err| (err as &dyn Diagnostic, err.span.start, err.span.end)))
+        .chain(module.state.errors.iter().map(|err| (err as &dyn Diagnostic, err.span.start, err.span.end)))
+        .collect();
+    if errors.is_empty() {
+        return;
+    }
+    writeln!(out, "-- errors in {}", module.module_id).unwrap();
+    for (err, start, end) in errors {
+        writeln!(out, "[{}] {}..{}: {}", code(err), start, end, err).unwrap();
+    }
+}
+
+#[test]
+fn golden() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test/golden");
+    let bless = std::env::var_os("BLESS").is_some();
+
+    let mut fixtures: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "src"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for path in &fixtures {
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let source = fs::read_to_string(path).unwrap();
+        let edited = fs::read_to_string(path.with_extension("edit")).ok();
+        let input = fs::read_to_string(path.with_extension("in")).unwrap_or_default();
+        let actual = run(name, &source, edited.as_deref(), &input, &dir.join("modules"));
+
+        let snap = path.with_extension("snap");
+        if bless {
+            fs::write(&snap, &actual).unwrap();
+            continue;
+        }
+
+        match fs::read_to_string(&snap) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{name}: snapshot mismatch\n--- expected\n{expected}\n--- actual\n{actual}"
+            )),
+            Err(_) => failures.push(format!("{name}: missing snapshot {}", snap.display())),
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
@@ -0,0 +1,133 @@
+//! The pure parts of the language server: URIs, JSON, and converting
+//! between byte offsets and the protocol's UTF-16 positions.
+
+use compiler::{
+    driver::Files,
+    lsp::{Server, json::Json, path_to_uri, uri_to_path},
+};
+
+struct NoFiles;
+
+impl Files for NoFiles {
+    fn read(&mut self, _path: &str) -> Option<String> {
+        None
+    }
+}
+
+const URI: &str = "file:///work/main.src";
+
+fn open(server: &mut Server, text: &str) -> Vec<Json> {
+    let message = Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/didOpen".into()),
+        (
+            "params",
+            Json::object([("textDocument", Json::object([("uri", URI.into()), ("text", text.into())]))]),
+        ),
+    ]);
+    server.handle(&message)
+}
+
+fn position(line: usize, character: usize) -> Json {
+    Json::object([("line", line.into()), ("character", character.into())])
+}
+
+fn range(start: (usize, usize), end: (usize, usize)) -> Json {
+    Json::object([("start", position(start.0, start.1)), ("end", position(end.0, end.1))])
+}
+
+#[test]
+fn uri_to_path_decodes_escapes() {
+    assert_eq!(uri_to_path("file:///home/a%20b/x.src").as_deref(), Some("/home/a b/x.src"));
+    assert_eq!(uri_to_path("file:///caf%c3%a9.src").as_deref(), Some("/café.src"));
+    assert_eq!(uri_to_path("file:///C:/dir/x.src").as_deref(), Some("C:/dir/x.src"));
+}
+
+#[test]
+fn uri_to_path_keeps_malformed_escapes() {
+    assert_eq!(uri_to_path("file:///a%+1").as_deref(), Some("/a%+1"));
+    assert_eq!(uri_to_path("file:///a%2").as_deref(), Some("/a%2"));
+    assert_eq!(uri_to_path("file:///a%zz").as_deref(), Some("/a%zz"));
+}
+
+#[test]
+fn uri_to_path_rejects_other_uris() {
+    assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+    assert_eq!(uri_to_path("file:///%ff"), None);
+}
+
+#[test]
+fn path_to_uri_round_trips() {
+    for path in ["/home/a b/café.src", "/x%y/#1.src", "C:/dir/x.src"] {
+        let uri = path_to_uri(path);
+        assert!(uri.is_ascii(), "{uri}");
+        assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+    }
+    assert_eq!(path_to_uri("/home/a b/x.src"), "file:///home/a%20b/x.src");
+    assert_eq!(path_to_uri("C:/x.src"), "file:///C:/x.src");
+}
+
+#[test]
+fn json_parses_escapes() {
+    let parsed = Json::parse(r#""q\" b\\ s\/ \b\f\n\r\t \u0041\u00e9""#);
+    assert_eq!(parsed, Some(Json::String("q\" b\\ s/ \u{8}\u{c}\n\r\t Aé".into())));
+    assert_eq!(Json::parse(r#""\x""#), None);
+    assert_eq!(Json::parse(r#""\u+041""#), None);
+    assert_eq!(Json::parse(r#""\u00""#), None);
+}
+
+#[test]
+fn json_parses_surrogate_pairs() {
+    assert_eq!(Json::parse(r#""\ud83d\ude00""#), Some(Json::String("😀".into())));
+    assert_eq!(Json::parse(r#""\uD83D\uDE00""#), Some(Json::String("😀".into())));
+    assert_eq!(Json::parse(r#""\ud83d\u0041""#), None);
+    assert_eq!(Json::parse(r#""\ud83d\ue000""#), None);
+}
+
+#[test]
+fn json_round_trips() {
+    let value = Json::object([
+        ("text", "line\n\"quoted\"\t\u{1}😀".into()),
+        ("items", vec![Json::Null, Json::Bool(true), Json::Number(-1.5)].into()),
+        ("empty", Json::object([])),
+    ]);
+    assert_eq!(Json::parse(&value.to_string()), Some(value));
+    assert_eq!(Json::parse(" [1 , 2] "), Some(vec![Json::Number(1.0), Json::Number(2.0)].into()));
+    assert_eq!(Json::parse("[1,]"), None);
+    assert_eq!(Json::parse("{} {}"), None);
+}
+
+#[test]
+fn diagnostics_count_utf16_columns() {
+    let mut server = Server::new(Box::new(NoFiles));
+    let replies = open(&mut server, "def n : Str = Str.append \"😀\" x");
+    let diagnostics = replies[0].get("params").get("diagnostics").as_array().unwrap();
+    assert_eq!(*diagnostics[0].get("range"), range((0, 30), (0, 31)));
+}
+
+#[test]
+fn hover_converts_positions_both_ways() {
+    let mut server = Server::new(Box::new(NoFiles));
+    open(&mut server, "def s : Str = \"a\"\ndef n : Str = Str.append \"😀\" s");
+    let hover = |server: &mut Server, character| {
+        let request = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", 1usize.into()),
+            ("method", "textDocument/hover".into()),
+            (
+                "params",
+                Json::object([
+                    ("textDocument", Json::object([("uri", URI.into())])),
+                    ("position", position(1, character)),
+                ]),
+            ),
+        ]);
+        server.handle(&request).remove(0).get("result").clone()
+    };
+
+    let result = hover(&mut server, 30);
+    assert_eq!(*result.get("range"), range((1, 30), (1, 31)));
+    assert_eq!(result.get("contents").get("value").as_str(), Some("```\ns : Str\n```"));
+    // Past the end of the line is its end, which is still on `s`.
+    assert_eq!(*hover(&mut server, 40).get("range"), range((1, 30), (1, 31)));
+}
@@ -0,0 +1,113 @@
+//! Object files: what reading back a written one gives, and how reading a
+//! damaged one fails.
+
+use compiler::{
+    driver::{ModuleLoader, compile},
+    module::object::{MAGIC, Object, ObjectError, VERSION, hash, read_object, write_object},
+};
+
+struct NoModules;
+
+impl ModuleLoader for NoModules {
+    fn load(&mut self, _module: &str) -> Option<String> {
+        None
+    }
+}
+
+const SOURCE: &str = "\
+inductive N where
+  | zero
+  | succ (n : N)
+
+def two : N = N.succ (N.succ N.zero)
+";
+
+fn object() -> Object {
+    let module = compile(&mut NoModules, "main".into(), SOURCE.into()).pop().unwrap();
+    assert!(module.state.errors.is_empty());
+    Object {
+        source_hash: hash(SOURCE.as_bytes()),
+        imports: vec![("Lib".into(), 7)],
+        env: module.state.env,
+        commands: Vec::new(),
+    }
+}
+
+/// Replaces the body after the header and updates the checksum to match, so
+/// that reading gets past the header.
+fn with_body(bytes: &[u8], body: &[u8]) -> Vec<u8> {
+    let mut bytes = bytes[..16].to_vec();
+    bytes[8..16].copy_from_slice(&hash(body).to_le_bytes());
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+#[test]
+fn round_trip() {
+    let object = object();
+    let read = read_object(&write_object(&object)).unwrap();
+    assert_eq!(read.source_hash, object.source_hash);
+    assert_eq!(read.imports, object.imports);
+    assert_eq!(read.env.to_string(), object.env.to_string());
+}
+
+#[test]
+fn rejects_bad_magic() {
+    let mut bytes = write_object(&object());
+    bytes[0] ^= 1;
+    assert!(matches!(read_object(&bytes), Err(ObjectError::BadMagic)));
+    assert!(matches!(read_object(b"MO"), Err(ObjectError::BadMagic)));
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut bytes = write_object(&object());
+    bytes[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        read_object(&bytes),
+        Err(ObjectError::VersionMismatch { found, expected }) if found == VERSION + 1 && expected == VERSION
+    ));
+}
+
+#[test]
+fn rejects_corrupt_bytes() {
+    let bytes = write_object(&object());
+    for index in [16, bytes.len() / 2, bytes.len() - 1] {
+        let mut corrupt = bytes.clone();
+        corrupt[index] ^= 0x40;
+        assert!(matches!(read_object(&corrupt), Err(ObjectError::ChecksumMismatch)), "byte {index}");
+    }
+}
+
+#[test]
+fn rejects_truncated_files() {
+    let bytes = write_object(&object());
+    assert!(matches!(read_object(&bytes[..10]), Err(ObjectError::Truncated)));
+    assert!(matches!(read_object(&MAGIC), Err(ObjectError::Truncated)));
+    // Cut short with a checksum that matches, reading runs out of bytes.
+    let cut = with_body(&bytes, &bytes[16..bytes.len() - 1]);
+    assert!(matches!(read_object(&cut), Err(ObjectError::Truncated)));
+    // Cutting without updating the checksum is caught by the checksum first.
+    assert!(matches!(read_object(&bytes[..bytes.len() - 1]), Err(ObjectError::ChecksumMismatch)));
+}
+
+#[test]
+fn rejects_malformed_bodies() {
+    let bytes = write_object(&object());
+    let mut trailing = bytes[16..].to_vec();
+    trailing.push(0);
+    assert!(matches!(
+        read_object(&with_body(&bytes, &trailing)),
+        Err(ObjectError::Malformed("trailing bytes"))
+    ));
+    // A string table longer than the body can hold.
+    assert!(matches!(
+        read_object(&with_body(&bytes, &[0xff, 0xff, 0xff, 0xff, 0x0f])),
+        Err(ObjectError::Truncated)
+    ));
+    // A string that isn't UTF-8.
+    assert!(matches!(
+        read_object(&with_body(&bytes, &[1, 1, 0xff])),
+        Err(ObjectError::Malformed("string is not UTF-8"))
+    ));
+}
@@ -0,0 +1,91 @@
+//! The interactive session, run one input at a time.
+
+use compiler::{
+    driver::{Files, ModuleLoader},
+    repl::Repl,
+};
+
+struct NoModules;
+
+impl ModuleLoader for NoModules {
+    fn load(&mut self, _module: &str) -> Option<String> {
+        None
+    }
+}
+
+struct NoFiles;
+
+impl Files for NoFiles {
+    fn read(&mut self, _path: &str) -> Option<String> {
+        None
+    }
+}
+
+fn repl() -> Repl {
+    Repl::new(Box::new(NoModules), Box::new(NoFiles))
+}
+
+#[test]
+fn definitions_persist_between_inputs() {
+    let mut repl = repl();
+    assert_eq!(repl.run("def two : Nat = Nat.add 1 1"), "");
+    assert_eq!(repl.run("eval Nat.mul two 3;"), "6\n");
+    assert_eq!(repl.run("Nat.add two two"), "4\n");
+}
+
+#[test]
+fn errors_are_rendered() {
+    let mut repl = repl();
+    assert_eq!(
+        repl.run("undefined"),
+        "undefined variable `undefined` [E0201]\n  @0+9: not found in scope\n  \
+         help: check the spelling, or define it before this point\n"
+    );
+    assert_eq!(repl.run(":bogus"), "Unknown command :bogus, see :help\n");
+}
+
+#[test]
+fn type_shows_the_type() {
+    let mut repl = repl();
+    repl.run("def two : Nat = 2");
+    assert_eq!(repl.run(":type two"), "two : Nat\n");
+    assert_eq!(repl.run(":t Nat.add"), "Nat.add : Nat -> Nat -> Nat\n");
+}
+
+#[test]
+fn print_shows_the_declaration() {
+    let mut repl = repl();
+    repl.run("def two : Nat = Nat.add 1 1");
+    assert_eq!(repl.run(":print two"), "def two : Nat := Nat.add 1 1\n");
+    assert_eq!(repl.run(":print nothing"), "Unknown declaration nothing\n");
+}
+
+#[test]
+fn print_renders_ambiguity() {
+    let mut repl = repl();
+    repl.run("namespace A def x : Nat = 1 end namespace B def x : Nat = 2 end open A open B");
+    assert_eq!(
+        repl.run(":print x"),
+        "`x` is ambiguous: it could refer to `A.x`, `B.x` [E0231]\n  @0+1: here\n  \
+         help: write the full name of the declaration you mean\ndef A.x : Nat := 1\n"
+    );
+}
+
+#[test]
+fn reset_forgets_declarations() {
+    let mut repl = repl();
+    repl.run("def two : Nat = 2");
+    assert_eq!(repl.run(":reset"), "");
+    assert_eq!(repl.run(":print two"), "Unknown declaration two\n");
+    assert!(repl.run("two").starts_with("undefined variable `two`"));
+}
+
+#[test]
+fn incomplete_inputs_wait_for_more() {
+    let mut repl = repl();
+    assert!(repl.is_incomplete("def two : Nat ="));
+    assert!(repl.is_incomplete("Nat.add (1"));
+    assert!(!repl.is_incomplete("def two : Nat = 2"));
+    assert!(!repl.is_incomplete("def two : Nat = $"));
+    assert!(!repl.is_incomplete(":type"));
+}